@@ -3,9 +3,11 @@ use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    committee, cycles, digest,
     keccak::Digest,
     local_balance_tree::{BalanceTree, BalanceTreeByNetwork},
     local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    pause::{PauseError, PauseList},
     withdrawal::NetworkId,
     Withdrawal,
 };
@@ -48,14 +50,30 @@ impl Batch {
         let mut new_local_exit_tree = self.prev_local_exit_tree.clone();
 
         for withdrawal in &self.withdrawals {
-            new_local_exit_tree.add_leaf(withdrawal.hash());
+            cycles::start(cycles::LEAF_HASHING);
+            let leaf = withdrawal.hash();
+            cycles::end(cycles::LEAF_HASHING);
+            new_local_exit_tree.add_leaf(leaf);
         }
 
         new_local_exit_tree.get_root()
     }
 
-    /// Compute the new balance tree.
-    pub fn compute_new_balance_tree(&self) -> BalanceTreeByNetwork {
+    /// Computes the hash of the withdrawals, as attested by the committee of the network.
+    pub fn withdrawals_hash(&self) -> Digest {
+        committee::withdrawals_hash(self.withdrawals.iter().map(Withdrawal::hash))
+    }
+
+    /// Computes the digest of the input data of the network, as committed by the proof.
+    pub fn input_digest(&self) -> Digest {
+        digest::input_digest(self.origin_network, self.withdrawals.iter().map(Withdrawal::hash))
+    }
+
+    /// Compute the new balance tree, refusing the withdrawals which are `paused`.
+    pub fn compute_new_balance_tree(
+        &self,
+        paused: &PauseList,
+    ) -> Result<BalanceTreeByNetwork, PauseError> {
         let mut aggregate: BalanceTreeByNetwork = {
             let base: BTreeMap<NetworkId, BalanceTree> =
                 [(self.origin_network, self.prev_local_balance_tree.clone())].into();
@@ -63,9 +81,9 @@ impl Batch {
         };
 
         for withdrawal in &self.withdrawals {
-            aggregate.insert(self.origin_network, withdrawal.clone());
+            aggregate.insert(self.origin_network, withdrawal.clone(), paused)?;
         }
 
-        aggregate
+        Ok(aggregate)
     }
 }
@@ -0,0 +1,54 @@
+//! Replays bridge event dumps and checks them against the exit roots checkpointed on-chain.
+//!
+//! Usage: `replay [--logs] <FILE>...`
+//!
+//! Each file is a JSON array or JSON lines of bridge events, or of raw `eth_getLogs` objects with
+//! `--logs`. Several files are merged as chunks sorted by position in the chain.
+
+use std::process::ExitCode;
+
+use poly_pessimistic_proof::{
+    ingest::stream::{stream_bridge_events, MergeSorted},
+    replay::replay,
+};
+use reth_primitives::hex;
+
+fn main() -> ExitCode {
+    let mut raw_logs = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--logs" => raw_logs = true,
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        eprintln!("usage: replay [--logs] <FILE>...");
+        return ExitCode::FAILURE;
+    }
+
+    let chunks =
+        match paths.iter().map(|path| stream_bridge_events(path.as_ref(), raw_logs)).collect() {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+    match replay(MergeSorted::new(chunks)) {
+        Ok(summary) => {
+            println!("deposits:            {}", summary.deposits);
+            println!("mainnet checkpoints: {}", summary.mainnet_checkpoints);
+            println!("rollup checkpoints:  {}", summary.rollup_checkpoints);
+            println!("mainnet exit root:   0x{}", hex::encode(summary.mainnet_exit_root));
+            println!("rollup exit root:    0x{}", hex::encode(summary.rollup_exit_root));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
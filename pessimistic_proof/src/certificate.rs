@@ -0,0 +1,344 @@
+//! Certificates: the batch of a network, signed by its sequencer at a given height.
+//!
+//! The header of a certificate commits to the roots of the network before and after the batch, and
+//! is signed with the ECDSA secp256k1 key of the signer registered for the network in the
+//! [`ProofContext`]. Each certificate must directly follow the last settled one of its network, so
+//! that a certificate cannot be settled twice, nor out of order.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+pub use k256::ecdsa::SigningKey;
+use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+use reth_primitives::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    batch::Batch,
+    context::ProofContext,
+    keccak::{keccak256, keccak256_combine, Digest},
+    BalanceRoot, ExitRoot, NetworkId,
+};
+
+/// An ECDSA secp256k1 signature over a digest, from which the signer can be recovered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    /// Parity of the y coordinate of the curve point whose x coordinate is `r`
+    pub recovery_id: u8,
+}
+
+impl Signature {
+    /// Signs `digest` with `signing_key`.
+    pub fn sign(signing_key: &SigningKey, digest: &Digest) -> Self {
+        let (signature, recovery_id) = signing_key
+            .sign_prehash_recoverable(digest)
+            .expect("a digest is a valid prehash");
+
+        Self {
+            r: signature.r().to_bytes().into(),
+            s: signature.s().to_bytes().into(),
+            recovery_id: recovery_id.to_byte(),
+        }
+    }
+
+    /// Recovers the address of the key which signed `digest`, if the signature is valid.
+    pub fn recover(&self, digest: &Digest) -> Option<Address> {
+        let signature = EcdsaSignature::from_scalars(self.r, self.s).ok()?;
+        let recovery_id = RecoveryId::from_byte(self.recovery_id)?;
+
+        let key = VerifyingKey::recover_from_prehash(digest, &signature, recovery_id).ok()?;
+        Some(address_of(&key))
+    }
+}
+
+/// Returns the address of the key, as derived on Ethereum.
+fn address_of(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+
+    Address::from_slice(&keccak256(&point.as_bytes()[1..])[12..])
+}
+
+/// Returns the address of the signing key, as registered in the [`ProofContext`].
+pub fn signer_address(signing_key: &SigningKey) -> Address {
+    address_of(signing_key.verifying_key())
+}
+
+/// The part of a certificate which is signed by the sequencer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateHeader {
+    /// Network which the certificate is for
+    pub network: NetworkId,
+    /// Height of the certificate, which follows the last settled one of the network
+    pub height: u64,
+    /// Local exit root before the batch
+    pub prev_local_exit_root: ExitRoot,
+    /// Local exit root after the batch
+    pub new_local_exit_root: ExitRoot,
+    /// Root of the balance tree before the batch
+    pub prev_balance_root: BalanceRoot,
+    /// Signature of [`CertificateHeader::signed_hash`] by the sequencer of the network
+    pub signature: Signature,
+    /// Signatures of [`CertificateHeader::attested_hash`] by members of the committee of the
+    /// network, if it has one
+    #[serde(default)]
+    pub committee_signatures: Vec<Signature>,
+}
+
+impl CertificateHeader {
+    /// Computes the digest signed by the sequencer.
+    pub fn signed_hash(&self) -> Digest {
+        keccak256_combine([
+            self.network.to_be_bytes().as_slice(),
+            &self.height.to_be_bytes(),
+            &self.prev_local_exit_root,
+            &self.new_local_exit_root,
+            &self.prev_balance_root,
+        ])
+    }
+
+    /// Computes the digest signed by the committee, given the withdrawals hash of the batch.
+    ///
+    /// It binds the withdrawals to the network, height and local exit roots of the certificate, so
+    /// that an attestation only holds for the certificate it was made for.
+    pub fn attested_hash(&self, withdrawals_hash: &Digest) -> Digest {
+        keccak256_combine([
+            self.network.to_be_bytes().as_slice(),
+            &self.height.to_be_bytes(),
+            &self.prev_local_exit_root,
+            &self.new_local_exit_root,
+            withdrawals_hash,
+        ])
+    }
+
+    /// Recovers the address of the key which signed the header, if the signature is valid.
+    pub fn signer(&self) -> Option<Address> {
+        self.signature.recover(&self.signed_hash())
+    }
+}
+
+/// The batch of a network, signed by its sequencer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Certificate {
+    pub header: CertificateHeader,
+    pub batch: Batch,
+}
+
+impl Certificate {
+    /// Creates the certificate of `batch` at the given height, signed with `signing_key`.
+    pub fn new(batch: Batch, height: u64, signing_key: &SigningKey) -> Self {
+        let mut header = CertificateHeader {
+            network: batch.origin_network,
+            height,
+            prev_local_exit_root: batch.prev_local_exit_root,
+            new_local_exit_root: batch.compute_new_exit_root(),
+            prev_balance_root: batch.prev_local_balance_tree.hash(),
+            signature: Signature {
+                r: [0; 32],
+                s: [0; 32],
+                recovery_id: 0,
+            },
+            committee_signatures: Vec::new(),
+        };
+        header.signature = Signature::sign(signing_key, &header.signed_hash());
+
+        Self { header, batch }
+    }
+
+    /// Adds the attestation of the batch by a member of the committee of the network.
+    pub fn attest(&mut self, member_key: &SigningKey) {
+        let digest = self.header.attested_hash(&self.batch.withdrawals_hash());
+        let signature = Signature::sign(member_key, &digest);
+        self.header.committee_signatures.push(signature);
+    }
+}
+
+/// Represents all errors that can occur while checking certificates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificateError {
+    /// The number of certificate headers differs from the number of batches.
+    CountMismatch { headers: usize, batches: usize },
+    /// The header does not describe the batch at the same position.
+    HeaderMismatch { network: NetworkId },
+    /// The network has several certificates.
+    DuplicateNetwork { network: NetworkId },
+    /// The certificate does not directly follow the last settled one of the network.
+    UnexpectedHeight {
+        network: NetworkId,
+        got: u64,
+        expected: u64,
+    },
+    /// The network is not registered.
+    UnknownNetwork { network: NetworkId },
+    /// The network is disabled.
+    DisabledNetwork { network: NetworkId },
+    /// The certificate is not signed by the registered signer of the network.
+    InvalidSignature { network: NetworkId },
+    /// A committee signature is invalid, not from a member, or from a member who already signed.
+    InvalidAttestation { network: NetworkId },
+    /// The certificate is attested by fewer committee members than the threshold.
+    InsufficientAttestations {
+        network: NetworkId,
+        got: usize,
+        threshold: u32,
+    },
+    /// The threshold of the committee of the network is zero or above its number of members.
+    InvalidThreshold {
+        network: NetworkId,
+        threshold: u32,
+        members: usize,
+    },
+}
+
+impl fmt::Display for CertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertificateError::CountMismatch { headers, batches } => {
+                write!(f, "{headers} certificate headers for {batches} batches")
+            }
+            CertificateError::HeaderMismatch { network } => {
+                write!(f, "the certificate of network {} does not describe its batch", **network)
+            }
+            CertificateError::DuplicateNetwork { network } => {
+                write!(f, "network {} has several certificates", **network)
+            }
+            CertificateError::UnexpectedHeight {
+                network,
+                got,
+                expected,
+            } => write!(
+                f,
+                "certificate of network {} at height {got}, expected {expected}",
+                **network
+            ),
+            CertificateError::UnknownNetwork { network } => {
+                write!(f, "network {} is not registered", **network)
+            }
+            CertificateError::DisabledNetwork { network } => {
+                write!(f, "network {} is disabled", **network)
+            }
+            CertificateError::InvalidSignature { network } => {
+                write!(f, "the certificate of network {} is not signed by its sequencer", **network)
+            }
+            CertificateError::InvalidAttestation { network } => write!(
+                f,
+                "the certificate of network {} has an invalid committee signature",
+                **network
+            ),
+            CertificateError::InsufficientAttestations {
+                network,
+                got,
+                threshold,
+            } => write!(
+                f,
+                "the certificate of network {} is attested by {got} committee members, expected {threshold}",
+                **network
+            ),
+            CertificateError::InvalidThreshold {
+                network,
+                threshold,
+                members,
+            } => write!(
+                f,
+                "the committee of network {} has a threshold of {threshold} for {members} members",
+                **network
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CertificateError {}
+
+/// The roots of a batch before it is applied, which its certificate header commits to.
+pub(crate) struct PrevRoots {
+    pub origin_network: NetworkId,
+    pub local_exit_root: ExitRoot,
+    pub balance_root: BalanceRoot,
+}
+
+/// Checks the headers against the context and the roots of their batches, and returns the height
+/// of each network.
+pub(crate) fn check_headers<'a>(
+    context: &ProofContext,
+    certificates: impl Iterator<Item = (&'a CertificateHeader, PrevRoots)>,
+) -> Result<BTreeMap<NetworkId, u64>, CertificateError> {
+    let mut heights = BTreeMap::new();
+    for (header, batch) in certificates {
+        let network = header.network;
+
+        if network != batch.origin_network
+            || header.prev_local_exit_root != batch.local_exit_root
+            || header.prev_balance_root != batch.balance_root
+        {
+            return Err(CertificateError::HeaderMismatch { network });
+        }
+
+        let expected = context.next_height(network);
+        if header.height != expected {
+            return Err(CertificateError::UnexpectedHeight {
+                network,
+                got: header.height,
+                expected,
+            });
+        }
+
+        let config = context
+            .registry
+            .get(network)
+            .ok_or(CertificateError::UnknownNetwork { network })?;
+        if !config.enabled {
+            return Err(CertificateError::DisabledNetwork { network });
+        }
+        if header.signer() != Some(config.signer) {
+            return Err(CertificateError::InvalidSignature { network });
+        }
+
+        if heights.insert(network, header.height).is_some() {
+            return Err(CertificateError::DuplicateNetwork { network });
+        }
+    }
+
+    Ok(heights)
+}
+
+/// Checks the attestations of the networks which have a committee, given the withdrawals hash of
+/// their batches, and returns the hash of the committee of each attested network.
+pub(crate) fn check_attestations<'a, F>(
+    context: &ProofContext,
+    certificates: impl Iterator<Item = (&'a CertificateHeader, F)>,
+) -> Result<BTreeMap<NetworkId, Digest>, CertificateError>
+where
+    F: FnOnce() -> Digest,
+{
+    let mut committees = BTreeMap::new();
+    for (header, withdrawals_hash) in certificates {
+        let Some(committee) = context.committees.get(&header.network) else {
+            continue;
+        };
+
+        let digest = header.attested_hash(&withdrawals_hash());
+        committee.verify(header.network, &header.committee_signatures, &digest)?;
+        committees.insert(header.network, committee.hash());
+    }
+
+    Ok(committees)
+}
+
+/// Checks that the new local exit roots are the ones signed in the headers.
+pub(crate) fn check_new_roots<'a>(
+    headers: impl Iterator<Item = &'a CertificateHeader>,
+    exit_roots: &HashMap<NetworkId, ExitRoot>,
+) -> Result<(), CertificateError> {
+    for header in headers {
+        if exit_roots.get(&header.network) != Some(&header.new_local_exit_root) {
+            return Err(CertificateError::HeaderMismatch {
+                network: header.network,
+            });
+        }
+    }
+
+    Ok(())
+}
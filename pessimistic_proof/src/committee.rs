@@ -0,0 +1,93 @@
+//! Data availability committees, which attest the withdrawal data of validium networks.
+//!
+//! A network with a committee in the [`ProofContext`](crate::context::ProofContext) must have its
+//! certificates attested by at least `threshold` of the committee members, each signing the
+//! [`withdrawals_hash`] of the batch along with the network, height and local exit roots of the
+//! certificate (see
+//! [`CertificateHeader::attested_hash`](crate::certificate::CertificateHeader::attested_hash)). The
+//! hash of the committee is committed by the proof, so that L1 can check which committee attested
+//! each certificate.
+
+use reth_primitives::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    certificate::{CertificateError, Signature},
+    keccak::{keccak256_combine, Digest},
+    NetworkId,
+};
+
+/// An N-of-M committee: at least `threshold` of the `members` must sign.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Committee {
+    /// Addresses of the keys of the members
+    pub members: Vec<Address>,
+    /// Number of distinct members whose signatures are required
+    pub threshold: u32,
+}
+
+impl Committee {
+    /// Creates a new [`Committee`].
+    pub fn new(members: Vec<Address>, threshold: u32) -> Self {
+        Self { members, threshold }
+    }
+
+    /// Returns whether the threshold is between 1 and the number of members, so that the committee
+    /// can attest a certificate and an attestation takes at least one member.
+    pub fn has_valid_threshold(&self) -> bool {
+        self.threshold != 0 && self.threshold as usize <= self.members.len()
+    }
+
+    /// Computes the hash identifying the committee.
+    pub fn hash(&self) -> Digest {
+        keccak256_combine(
+            [self.threshold.to_be_bytes().as_slice()]
+                .into_iter()
+                .chain(self.members.iter().map(|member| member.as_slice())),
+        )
+    }
+
+    /// Checks that at least `threshold` distinct members signed `digest` for `network`, failing if
+    /// the threshold is not valid.
+    pub fn verify(
+        &self,
+        network: NetworkId,
+        signatures: &[Signature],
+        digest: &Digest,
+    ) -> Result<(), CertificateError> {
+        if !self.has_valid_threshold() {
+            return Err(CertificateError::InvalidThreshold {
+                network,
+                threshold: self.threshold,
+                members: self.members.len(),
+            });
+        }
+
+        let mut signers: Vec<Address> = Vec::with_capacity(signatures.len());
+        for signature in signatures {
+            match signature.recover(digest) {
+                Some(signer) if self.members.contains(&signer) && !signers.contains(&signer) => {
+                    signers.push(signer)
+                }
+                _ => return Err(CertificateError::InvalidAttestation { network }),
+            }
+        }
+
+        if signers.len() < self.threshold as usize {
+            return Err(CertificateError::InsufficientAttestations {
+                network,
+                got: signers.len(),
+                threshold: self.threshold,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Computes the hash of the withdrawals of a batch signed by the committee, from their leaves.
+pub fn withdrawals_hash(leaves: impl Iterator<Item = Digest>) -> Digest {
+    let leaves: Vec<Digest> = leaves.collect();
+
+    keccak256_combine(leaves.iter().map(|leaf| leaf.as_slice()))
+}
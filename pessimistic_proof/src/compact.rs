@@ -0,0 +1,452 @@
+//! Compact, fixed-layout encoding of the program input.
+//!
+//! Deserializing a `Vec<Batch>` in the zkVM spends many cycles on every [`Withdrawal`] and
+//! balance entry before any actual work. This encoding is instead read in place: once
+//! [`CompactBatches::new`] has checked the lengths, fields are sliced out of the input bytes as
+//! they are needed.
+//!
+//! All integers are big-endian. The input is a `u32` batch count followed by the batches, each
+//! laid out as:
+//!
+//! | Field                   | Size                                 |
+//! |-------------------------|--------------------------------------|
+//! | origin network          | 4                                    |
+//! | exit tree leaf count    | 4                                    |
+//! | exit tree frontier      | 32 × 32                              |
+//! | previous exit root      | 32                                   |
+//! | balance count           | 4                                    |
+//! | balances                | [`BALANCE_SIZE`] × balance count     |
+//! | withdrawal count        | 4                                    |
+//! | withdrawals             | [`WITHDRAWAL_SIZE`] × withdrawal count |
+//!
+//! A balance is the token origin network and address followed by the deposited and withdrawn
+//! amounts. A withdrawal, whose leaf type must be one of [`LeafType`], is laid out exactly as the
+//! preimage of its leaf hash (see [`Withdrawal::hash`]), with the metadata replaced by its hash, so
+//! that hashing a withdrawal does not copy anything.
+//!
+//! [`Withdrawal`]: crate::Withdrawal
+//! [`LeafType`]: crate::LeafType
+//! [`Withdrawal::hash`]: crate::Withdrawal::hash
+
+use std::fmt;
+
+use reth_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    batch::Batch,
+    keccak::{keccak256, Digest},
+    local_balance_tree::{Balance, BalanceTree},
+    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    withdrawal::{LeafType, NetworkId, TokenInfo},
+};
+
+const TREE_DEPTH: usize = 32;
+const FRONTIER_SIZE: usize = 32 * TREE_DEPTH;
+const TOKEN_INFO_SIZE: usize = 4 + 20;
+
+/// Size of an encoded balance entry.
+pub const BALANCE_SIZE: usize = TOKEN_INFO_SIZE + 32 + 32;
+/// Size of an encoded withdrawal.
+pub const WITHDRAWAL_SIZE: usize = 1 + TOKEN_INFO_SIZE + 4 + 20 + 32 + 32;
+
+/// How the batches are passed to the program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputFormat {
+    /// A serde-encoded `Vec<Batch>`.
+    Serde,
+    /// The compact encoding of [`encode_batches`].
+    #[default]
+    Compact,
+}
+
+/// Represents all errors that can occur while reading compact input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompactError {
+    /// The input ends in the middle of a batch.
+    UnexpectedEnd { batch: usize },
+    /// The input goes on after the last batch.
+    TrailingBytes { len: usize },
+    /// A withdrawal of the batch has an unknown leaf type.
+    UnknownLeafType { batch: usize, leaf_type: u8 },
+}
+
+impl fmt::Display for CompactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompactError::UnexpectedEnd { batch } => {
+                write!(f, "compact input ends in the middle of batch {batch}")
+            }
+            CompactError::TrailingBytes { len } => {
+                write!(f, "{len} trailing bytes after the last batch")
+            }
+            CompactError::UnknownLeafType { batch, leaf_type } => {
+                write!(f, "unknown leaf type {leaf_type} in batch {batch}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompactError {}
+
+/// Encodes batches in the compact layout.
+pub fn encode_batches(batches: &[Batch]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(batches.len() as u32).to_be_bytes());
+
+    for batch in batches {
+        let tree = &batch.prev_local_exit_tree;
+
+        bytes.extend_from_slice(&batch.origin_network.to_be_bytes());
+        bytes.extend_from_slice(&tree.leaf_count().to_be_bytes());
+        for digest in tree.frontier() {
+            bytes.extend_from_slice(digest);
+        }
+        bytes.extend_from_slice(&batch.prev_local_exit_root);
+
+        bytes.extend_from_slice(&(batch.prev_local_balance_tree.len() as u32).to_be_bytes());
+        for (token_info, balance) in batch.prev_local_balance_tree.iter() {
+            encode_token_info(&mut bytes, token_info);
+            bytes.extend_from_slice(&balance.deposited().to_be_bytes::<32>());
+            bytes.extend_from_slice(&balance.withdrawn().to_be_bytes::<32>());
+        }
+
+        bytes.extend_from_slice(&(batch.withdrawals.len() as u32).to_be_bytes());
+        for withdrawal in &batch.withdrawals {
+            bytes.push(withdrawal.leaf_type.into());
+            encode_token_info(&mut bytes, &withdrawal.token_info);
+            bytes.extend_from_slice(&withdrawal.dest_network.to_be_bytes());
+            bytes.extend_from_slice(withdrawal.dest_address.as_slice());
+            bytes.extend_from_slice(&withdrawal.amount.to_be_bytes::<32>());
+            bytes.extend_from_slice(&keccak256(&withdrawal.metadata));
+        }
+    }
+
+    bytes
+}
+
+fn encode_token_info(bytes: &mut Vec<u8>, token_info: &TokenInfo) {
+    bytes.extend_from_slice(&token_info.origin_network.to_be_bytes());
+    bytes.extend_from_slice(token_info.origin_token_address.as_slice());
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn read_token_info(bytes: &[u8]) -> TokenInfo {
+    TokenInfo {
+        origin_network: read_u32(bytes).into(),
+        origin_token_address: Address::from_slice(&bytes[4..TOKEN_INFO_SIZE]),
+    }
+}
+
+/// Batches in the compact layout, read in place.
+#[derive(Debug, Clone)]
+pub struct CompactBatches<'a> {
+    bytes: &'a [u8],
+    /// Start of each batch in `bytes`
+    offsets: Vec<usize>,
+}
+
+impl<'a> CompactBatches<'a> {
+    /// Checks the layout of `bytes` and the leaf types of the withdrawals, which are then read in
+    /// place.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, CompactError> {
+        let batch_count =
+            bytes.get(..4).map(read_u32).ok_or(CompactError::UnexpectedEnd { batch: 0 })? as usize;
+
+        // The count is not trusted until the batches are read, but cannot exceed the batches which
+        // fit in the input
+        let mut offsets = Vec::with_capacity(batch_count.min(bytes.len() / CompactBatch::MIN_LEN));
+        let mut offset = 4;
+        for batch in 0..batch_count {
+            let end =
+                CompactBatch::len_at(bytes, offset).ok_or(CompactError::UnexpectedEnd { batch })?;
+
+            let compact = CompactBatch {
+                bytes: &bytes[offset..],
+            };
+            for withdrawal in compact.withdrawals() {
+                let leaf_type = withdrawal.0[0];
+                if LeafType::try_from(leaf_type).is_err() {
+                    return Err(CompactError::UnknownLeafType { batch, leaf_type });
+                }
+            }
+
+            offsets.push(offset);
+            offset = end;
+        }
+
+        if offset != bytes.len() {
+            return Err(CompactError::TrailingBytes {
+                len: bytes.len() - offset,
+            });
+        }
+
+        Ok(Self { bytes, offsets })
+    }
+
+    /// Returns the number of batches.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns whether there are no batches.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Iterates over the batches.
+    pub fn iter(&self) -> impl Iterator<Item = CompactBatch<'a>> + '_ {
+        self.offsets.iter().map(|&offset| CompactBatch {
+            bytes: &self.bytes[offset..],
+        })
+    }
+}
+
+/// A [`Batch`] in the compact layout.
+#[derive(Debug, Clone, Copy)]
+pub struct CompactBatch<'a> {
+    /// The input from the start of the batch, possibly followed by the next batches
+    bytes: &'a [u8],
+}
+
+impl<'a> CompactBatch<'a> {
+    const LEAF_COUNT: usize = 4;
+    const FRONTIER: usize = Self::LEAF_COUNT + 4;
+    const PREV_LOCAL_EXIT_ROOT: usize = Self::FRONTIER + FRONTIER_SIZE;
+    const BALANCE_COUNT: usize = Self::PREV_LOCAL_EXIT_ROOT + 32;
+    const BALANCES: usize = Self::BALANCE_COUNT + 4;
+    /// Length of a batch without any balance or withdrawal
+    const MIN_LEN: usize = Self::BALANCES + 4;
+
+    /// Returns the end of the batch starting at `offset`, if `bytes` are long enough.
+    fn len_at(bytes: &[u8], offset: usize) -> Option<usize> {
+        let batch = bytes.get(offset..)?;
+
+        let balance_count = read_u32(batch.get(Self::BALANCE_COUNT..Self::BALANCES)?) as usize;
+        let withdrawal_count_offset =
+            Self::BALANCES.checked_add(balance_count.checked_mul(BALANCE_SIZE)?)?;
+        let withdrawal_count =
+            read_u32(batch.get(withdrawal_count_offset..withdrawal_count_offset + 4)?) as usize;
+        let len = (withdrawal_count_offset + 4)
+            .checked_add(withdrawal_count.checked_mul(WITHDRAWAL_SIZE)?)?;
+
+        (len <= batch.len()).then_some(offset + len)
+    }
+
+    /// Returns the origin network which emitted the batch.
+    pub fn origin_network(&self) -> NetworkId {
+        read_u32(self.bytes).into()
+    }
+
+    /// Returns the initial local exit tree.
+    pub fn prev_local_exit_tree(&self) -> LocalExitTree<Keccak256Hasher> {
+        let leaf_count = read_u32(&self.bytes[Self::LEAF_COUNT..]);
+
+        let mut frontier = [Digest::default(); TREE_DEPTH];
+        for (digest, bytes) in frontier
+            .iter_mut()
+            .zip(self.bytes[Self::FRONTIER..Self::PREV_LOCAL_EXIT_ROOT].chunks_exact(32))
+        {
+            digest.copy_from_slice(bytes);
+        }
+
+        LocalExitTree::from_parts(leaf_count, frontier)
+    }
+
+    /// Returns the initial local exit root.
+    pub fn prev_local_exit_root(&self) -> Digest {
+        self.bytes[Self::PREV_LOCAL_EXIT_ROOT..Self::BALANCE_COUNT].try_into().unwrap()
+    }
+
+    fn balance_count(&self) -> usize {
+        read_u32(&self.bytes[Self::BALANCE_COUNT..]) as usize
+    }
+
+    fn withdrawals_offset(&self) -> usize {
+        Self::BALANCES + self.balance_count() * BALANCE_SIZE + 4
+    }
+
+    /// Returns the initial balance tree.
+    pub fn prev_local_balance_tree(&self) -> BalanceTree {
+        let end = Self::BALANCES + self.balance_count() * BALANCE_SIZE;
+
+        self.bytes[Self::BALANCES..end]
+            .chunks_exact(BALANCE_SIZE)
+            .map(|bytes| {
+                let deposit = U256::from_be_slice(&bytes[TOKEN_INFO_SIZE..TOKEN_INFO_SIZE + 32]);
+                let withdraw = U256::from_be_slice(&bytes[TOKEN_INFO_SIZE + 32..]);
+
+                (read_token_info(bytes), Balance::new(deposit, withdraw))
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Iterates over the withdrawals.
+    pub fn withdrawals(&self) -> impl ExactSizeIterator<Item = CompactWithdrawal<'a>> {
+        let offset = self.withdrawals_offset();
+        let count = read_u32(&self.bytes[offset - 4..]) as usize;
+
+        self.bytes[offset..offset + count * WITHDRAWAL_SIZE]
+            .chunks_exact(WITHDRAWAL_SIZE)
+            .map(CompactWithdrawal)
+    }
+}
+
+/// A [`crate::Withdrawal`] in the compact layout.
+#[derive(Debug, Clone, Copy)]
+pub struct CompactWithdrawal<'a>(&'a [u8]);
+
+impl<'a> CompactWithdrawal<'a> {
+    const TOKEN_INFO: usize = 1;
+    const DEST_NETWORK: usize = Self::TOKEN_INFO + TOKEN_INFO_SIZE;
+    const DEST_ADDRESS: usize = Self::DEST_NETWORK + 4;
+    const AMOUNT: usize = Self::DEST_ADDRESS + 20;
+    const METADATA_HASH: usize = Self::AMOUNT + 32;
+
+    pub fn leaf_type(&self) -> LeafType {
+        LeafType::try_from(self.0[0]).expect("leaf types are checked by `CompactBatches::new`")
+    }
+
+    pub fn token_info(&self) -> TokenInfo {
+        read_token_info(&self.0[Self::TOKEN_INFO..])
+    }
+
+    /// Returns the token whose balance the withdrawal moves, as
+    /// [`crate::Withdrawal::balance_token`].
+    pub fn balance_token(&self) -> TokenInfo {
+        match self.leaf_type() {
+            LeafType::Asset => self.token_info(),
+            LeafType::Message => TokenInfo::ETH,
+        }
+    }
+
+    pub fn dest_network(&self) -> NetworkId {
+        read_u32(&self.0[Self::DEST_NETWORK..]).into()
+    }
+
+    pub fn dest_address(&self) -> Address {
+        Address::from_slice(&self.0[Self::DEST_ADDRESS..Self::AMOUNT])
+    }
+
+    pub fn amount(&self) -> U256 {
+        U256::from_be_slice(&self.0[Self::AMOUNT..Self::METADATA_HASH])
+    }
+
+    pub fn metadata_hash(&self) -> Digest {
+        self.0[Self::METADATA_HASH..].try_into().unwrap()
+    }
+
+    /// Hashes the withdrawal to be inserted in a [`LocalExitTree`], as
+    /// [`crate::Withdrawal::hash`].
+    pub fn hash(&self) -> Digest {
+        keccak256(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reth_primitives::address;
+
+    use super::*;
+    use crate::Withdrawal;
+
+    fn batch(withdrawals: Vec<Withdrawal>) -> Batch {
+        let token = TokenInfo {
+            origin_network: 0.into(),
+            origin_token_address: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+        };
+        let tree = LocalExitTree::from_leaves([[1_u8; 32], [2_u8; 32]].into_iter());
+        let root = tree.get_root();
+
+        Batch::new(
+            1.into(),
+            tree,
+            root,
+            vec![(token, Balance::new(U256::from(7), U256::from(3)))].into(),
+            withdrawals,
+        )
+    }
+
+    fn withdrawal(metadata: Vec<u8>) -> Withdrawal {
+        Withdrawal::new(
+            LeafType::Message,
+            0.into(),
+            address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            2.into(),
+            address!("c949254d682d8c9ad5682521675b8f43b102aec4"),
+            U256::from(10).pow(U256::from(18)),
+            metadata,
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let batches =
+            vec![batch(vec![withdrawal(vec![]), withdrawal(vec![0xab; 100])]), batch(vec![])];
+        let bytes = encode_batches(&batches);
+        let compact = CompactBatches::new(&bytes).unwrap();
+        assert_eq!(compact.len(), 2);
+
+        for (batch, compact) in batches.iter().zip(compact.iter()) {
+            assert_eq!(compact.origin_network(), batch.origin_network);
+            assert_eq!(compact.prev_local_exit_root(), batch.prev_local_exit_root);
+            assert_eq!(
+                compact.prev_local_exit_tree().get_root(),
+                batch.prev_local_exit_tree.get_root()
+            );
+            assert_eq!(
+                compact.prev_local_balance_tree().hash(),
+                batch.prev_local_balance_tree.hash()
+            );
+
+            assert_eq!(compact.withdrawals().len(), batch.withdrawals.len());
+            for (withdrawal, compact) in batch.withdrawals.iter().zip(compact.withdrawals()) {
+                assert_eq!(compact.leaf_type(), withdrawal.leaf_type);
+                assert_eq!(compact.token_info(), withdrawal.token_info);
+                assert_eq!(compact.dest_network(), withdrawal.dest_network);
+                assert_eq!(compact.dest_address(), withdrawal.dest_address);
+                assert_eq!(compact.amount(), withdrawal.amount);
+                assert_eq!(compact.metadata_hash(), keccak256(&withdrawal.metadata));
+                assert_eq!(compact.hash(), withdrawal.hash());
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        let bytes = encode_batches(&[batch(vec![withdrawal(vec![])])]);
+
+        assert_eq!(
+            CompactBatches::new(&bytes[..bytes.len() - 1]).unwrap_err(),
+            CompactError::UnexpectedEnd { batch: 0 }
+        );
+        assert_eq!(
+            CompactBatches::new(&[bytes.as_slice(), &[0]].concat()).unwrap_err(),
+            CompactError::TrailingBytes { len: 1 }
+        );
+        assert_eq!(CompactBatches::new(&[]).unwrap_err(), CompactError::UnexpectedEnd { batch: 0 });
+        assert_eq!(
+            CompactBatches::new(&[0xff; 4]).unwrap_err(),
+            CompactError::UnexpectedEnd { batch: 0 }
+        );
+    }
+
+    #[test]
+    fn test_unknown_leaf_type() {
+        let mut bytes = encode_batches(&[batch(vec![]), batch(vec![withdrawal(vec![])])]);
+        let leaf_type = bytes.len() - WITHDRAWAL_SIZE;
+        bytes[leaf_type] = 2;
+
+        assert_eq!(
+            CompactBatches::new(&bytes).unwrap_err(),
+            CompactError::UnknownLeafType {
+                batch: 1,
+                leaf_type: 2
+            }
+        );
+    }
+}
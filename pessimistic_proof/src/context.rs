@@ -0,0 +1,109 @@
+//! The context which the certificates are checked against, as known on L1: the registered networks,
+//! the height of the last settled certificate of each network, the committees of the networks
+//! whose withdrawal data must be attested, the caps on the amounts withdrawn per epoch along with
+//! the amounts already withdrawn in the current epoch, the networks and tokens whose withdrawals are
+//! paused, the credit lines of trusted networks, and optionally the token supplies which the new
+//! balances must conserve.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    committee::Committee,
+    credit::CreditLines,
+    keccak::{keccak256_combine, Digest},
+    pause::PauseList,
+    rate_limit::{EpochWithdrawn, RateLimits},
+    registry::NetworkRegistry,
+    supply::SupplyCheck,
+    NetworkId,
+};
+
+/// The registered networks and settled heights which the certificates are checked against.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofContext {
+    /// Registered networks, along with the signer of their certificates
+    pub registry: NetworkRegistry,
+    /// Height of the last settled certificate of each network
+    pub heights: BTreeMap<NetworkId, u64>,
+    /// Committee attesting the certificates of each validium network which has one
+    #[serde(default)]
+    pub committees: BTreeMap<NetworkId, Committee>,
+    /// Caps on the amount of each token withdrawn by each network per epoch
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    /// Amount of each capped token withdrawn by each network since the start of the epoch
+    #[serde(default)]
+    pub withdrawn: EpochWithdrawn,
+    /// Networks and tokens whose withdrawals are paused
+    #[serde(default)]
+    pub paused: PauseList,
+    /// Overdraft allowed to trusted networks on specific tokens
+    #[serde(default)]
+    pub credit_lines: CreditLines,
+    /// Previous totals and issuance of each token, if the supply conservation is checked
+    #[serde(default)]
+    pub supply: Option<SupplyCheck>,
+}
+
+impl ProofContext {
+    /// Creates a new [`ProofContext`] without any network.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the height which the next certificate of `network` must have.
+    pub fn next_height(&self, network: NetworkId) -> u64 {
+        self.heights.get(&network).map_or(0, |height| height + 1)
+    }
+
+    /// Records the heights of newly settled certificates.
+    pub fn settle(&mut self, heights: &BTreeMap<NetworkId, u64>) {
+        self.heights.extend(heights);
+    }
+
+    /// Computes the hash committed by the proof, so that L1 can check that the proof was generated
+    /// against its own context.
+    pub fn hash(&self) -> Digest {
+        let heights: Vec<[u8; 12]> = self
+            .heights
+            .iter()
+            .map(|(network, height)| {
+                let mut entry = [0; 12];
+                entry[..4].copy_from_slice(&network.to_be_bytes());
+                entry[4..].copy_from_slice(&height.to_be_bytes());
+                entry
+            })
+            .collect();
+        let committees: Vec<[u8; 36]> = self
+            .committees
+            .iter()
+            .map(|(network, committee)| {
+                let mut entry = [0; 36];
+                entry[..4].copy_from_slice(&network.to_be_bytes());
+                entry[4..].copy_from_slice(&committee.hash());
+                entry
+            })
+            .collect();
+
+        keccak256_combine([
+            self.registry.root().as_slice(),
+            keccak256_combine(heights.iter().map(|entry| entry.as_slice())).as_slice(),
+            keccak256_combine(committees.iter().map(|entry| entry.as_slice())).as_slice(),
+            self.rate_limits.hash().as_slice(),
+            self.withdrawn.hash().as_slice(),
+            self.paused.hash().as_slice(),
+            self.credit_lines.hash().as_slice(),
+            self.supply_hash().as_slice(),
+        ])
+    }
+
+    /// Returns the hash of the supply check, or the hash of nothing if the supply is not checked.
+    pub fn supply_hash(&self) -> Digest {
+        match &self.supply {
+            Some(supply) => supply.hash(),
+            None => keccak256_combine([]),
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Static estimation of the cycles spent by the program on a set of batches, so that batches which
+//! do not fit the prover budget can be rejected or split before proving.
+//!
+//! The cost is modelled as linear in a few [`CostFeatures`] of the batches. The coefficients of
+//! [`CostModel::CALIBRATED`] are fitted on the report of the `bench` command of the script, and
+//! must be refitted whenever the program changes.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    batch::Batch, certificate::Certificate, local_balance_tree::BalanceTree, withdrawal::TokenInfo,
+    NetworkId, Withdrawal,
+};
+
+/// The quantities which the cost of the program depends on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostFeatures {
+    /// Number of batches
+    pub batches: u64,
+    /// Number of secp256k1 signatures recovered: the signature of each certificate by its
+    /// sequencer, and the attestations of its committee
+    pub signatures: u64,
+    /// Number of withdrawals across all batches
+    pub withdrawals: u64,
+    /// Total size of the withdrawal metadata
+    pub metadata_bytes: u64,
+    /// Number of hashes needed to append the withdrawals to the exit trees, which grows with the
+    /// depth of the subtrees completed by each new leaf
+    pub exit_tree_merges: u64,
+    /// Number of distinct tokens held by each network after merging, summed over networks
+    pub balance_entries: u64,
+}
+
+impl CostFeatures {
+    /// Computes the features of a set of batches, each certified by its sequencer alone.
+    pub fn of(batches: &[Batch]) -> Self {
+        let mut accumulator = CostAccumulator::new();
+        for batch in batches {
+            accumulator.add_batch_and_withdrawals(batch);
+        }
+
+        accumulator.features()
+    }
+
+    /// Computes the features of a set of certificates, including their committee attestations.
+    pub fn of_certificates(certificates: &[Certificate]) -> Self {
+        let mut accumulator = CostAccumulator::new();
+        for certificate in certificates {
+            accumulator.add_batch_and_withdrawals(&certificate.batch);
+            accumulator.add_attestations(certificate.header.committee_signatures.len());
+        }
+
+        accumulator.features()
+    }
+}
+
+/// Incremental computation of [`CostFeatures`], withdrawal by withdrawal.
+#[derive(Debug, Clone, Default)]
+pub struct CostAccumulator {
+    features: CostFeatures,
+    /// Leaf count of the exit tree of the current batch
+    leaf_count: u32,
+    balance_entries: BTreeSet<(NetworkId, TokenInfo)>,
+}
+
+impl CostAccumulator {
+    /// Creates a new [`CostAccumulator`] without any batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a new batch, signed by its sequencer, whose withdrawals are then added with
+    /// [`CostAccumulator::add_withdrawal`].
+    pub fn add_batch(
+        &mut self,
+        origin_network: NetworkId,
+        leaf_count: u32,
+        balance_tree: &BalanceTree,
+    ) {
+        self.features.batches += 1;
+        self.features.signatures += 1;
+        self.leaf_count = leaf_count;
+
+        for token_info in balance_tree.keys() {
+            self.balance_entries.insert((origin_network, token_info.clone()));
+        }
+        self.features.balance_entries = self.balance_entries.len() as u64;
+    }
+
+    /// Adds a withdrawal of the current batch.
+    pub fn add_withdrawal(&mut self, origin_network: NetworkId, withdrawal: &Withdrawal) {
+        self.features.withdrawals += 1;
+        self.features.metadata_bytes += withdrawal.metadata.len() as u64;
+
+        self.leaf_count += 1;
+        self.features.exit_tree_merges += self.leaf_count.trailing_zeros() as u64;
+
+        self.balance_entries.insert((origin_network, withdrawal.balance_token()));
+        self.balance_entries
+            .insert((withdrawal.dest_network, withdrawal.balance_token()));
+        self.features.balance_entries = self.balance_entries.len() as u64;
+    }
+
+    /// Adds the committee attestations of the current batch.
+    pub fn add_attestations(&mut self, count: usize) {
+        self.features.signatures += count as u64;
+    }
+
+    /// Adds a whole batch.
+    fn add_batch_and_withdrawals(&mut self, batch: &Batch) {
+        self.add_batch(
+            batch.origin_network,
+            batch.prev_local_exit_tree.leaf_count(),
+            &batch.prev_local_balance_tree,
+        );
+        for withdrawal in &batch.withdrawals {
+            self.add_withdrawal(batch.origin_network, withdrawal);
+        }
+    }
+
+    /// Returns the features of everything added so far.
+    pub fn features(&self) -> CostFeatures {
+        self.features
+    }
+}
+
+/// Linear model of the cycles of the program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostModel {
+    /// Cycles independent of the input
+    pub base: f64,
+    pub per_batch: f64,
+    pub per_signature: f64,
+    pub per_withdrawal: f64,
+    pub per_metadata_byte: f64,
+    pub per_exit_tree_merge: f64,
+    pub per_balance_entry: f64,
+}
+
+impl CostModel {
+    /// The model fitted on the benchmark suite.
+    ///
+    /// Metadata is hashed by the host in the compact input, so its size does not cost anything in
+    /// the program.
+    ///
+    /// The cost of a signature is not fitted yet: it is an estimate of a secp256k1 recovery by
+    /// `k256` without precompile, until the suite with attestations is benchmarked and the model
+    /// refitted on its report.
+    pub const CALIBRATED: Self = Self {
+        base: 25_000.0,
+        per_batch: 140_000.0,
+        per_signature: 2_000_000.0,
+        per_withdrawal: 4_200.0,
+        per_metadata_byte: 0.0,
+        per_exit_tree_merge: 1_050.0,
+        per_balance_entry: 3_400.0,
+    };
+
+    /// Returns the coefficients, in the order of [`CostModel::features_vector`].
+    pub fn coefficients(&self) -> [f64; 7] {
+        [
+            self.base,
+            self.per_batch,
+            self.per_signature,
+            self.per_withdrawal,
+            self.per_metadata_byte,
+            self.per_exit_tree_merge,
+            self.per_balance_entry,
+        ]
+    }
+
+    /// Creates a model from its coefficients, in the order of [`CostModel::features_vector`].
+    pub fn from_coefficients(coefficients: [f64; 7]) -> Self {
+        let [base, per_batch, per_signature, per_withdrawal, per_metadata_byte, per_exit_tree_merge, per_balance_entry] =
+            coefficients;
+
+        Self {
+            base,
+            per_batch,
+            per_signature,
+            per_withdrawal,
+            per_metadata_byte,
+            per_exit_tree_merge,
+            per_balance_entry,
+        }
+    }
+
+    /// Returns the features as a vector, the first entry standing for the base cost.
+    pub fn features_vector(features: &CostFeatures) -> [f64; 7] {
+        [
+            1.0,
+            features.batches as f64,
+            features.signatures as f64,
+            features.withdrawals as f64,
+            features.metadata_bytes as f64,
+            features.exit_tree_merges as f64,
+            features.balance_entries as f64,
+        ]
+    }
+
+    /// Estimates the cycles of a program run with the given features.
+    pub fn estimate(&self, features: &CostFeatures) -> u64 {
+        let cycles: f64 = self
+            .coefficients()
+            .iter()
+            .zip(Self::features_vector(features))
+            .map(|(coefficient, feature)| coefficient * feature)
+            .sum();
+
+        cycles.max(0.0).round() as u64
+    }
+}
+
+/// Estimates the cycles spent by the program to prove `batches`, using
+/// [`CostModel::CALIBRATED`].
+pub fn estimate_cost(batches: &[Batch]) -> u64 {
+    CostModel::CALIBRATED.estimate(&CostFeatures::of(batches))
+}
+
+#[cfg(test)]
+mod tests {
+    use reth_primitives::{Address, U256};
+
+    use super::*;
+    use crate::{
+        certificate::SigningKey,
+        local_balance_tree::Deposit,
+        local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+        LeafType,
+    };
+
+    fn token(byte: u8) -> TokenInfo {
+        TokenInfo {
+            origin_network: 0.into(),
+            origin_token_address: Address::repeat_byte(byte),
+        }
+    }
+
+    fn batch(origin_network: u32, leaf_count: u32, withdrawals: Vec<Withdrawal>) -> Batch {
+        let tree: LocalExitTree<Keccak256Hasher> =
+            LocalExitTree::from_leaves((0..leaf_count).map(|_| [0; 32]));
+        let root = tree.get_root();
+        let balances = BalanceTree::from(vec![(token(1), Deposit(U256::from(100)).into())]);
+
+        Batch::new(origin_network.into(), tree, root, balances, withdrawals)
+    }
+
+    fn withdrawal(token_byte: u8, dest_network: u32, metadata_len: usize) -> Withdrawal {
+        let token = token(token_byte);
+
+        Withdrawal::new(
+            LeafType::Asset,
+            token.origin_network,
+            token.origin_token_address,
+            dest_network.into(),
+            Address::ZERO,
+            U256::from(1),
+            vec![0; metadata_len],
+        )
+    }
+
+    #[test]
+    fn test_features() {
+        let batches = [
+            batch(0, 3, vec![withdrawal(1, 1, 10), withdrawal(2, 1, 0)]),
+            batch(1, 0, vec![withdrawal(1, 0, 5)]),
+        ];
+
+        assert_eq!(
+            CostFeatures::of(&batches),
+            CostFeatures {
+                batches: 2,
+                signatures: 2,
+                withdrawals: 3,
+                metadata_bytes: 15,
+                // Leaves 4 and 5 of the first tree, and leaf 1 of the second one
+                exit_tree_merges: 2,
+                // Token 1 on networks 0 and 1, token 2 on networks 0 and 1
+                balance_entries: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_attestation_features() {
+        let key = |byte: u8| SigningKey::from_slice(&[byte; 32]).unwrap();
+        let mut certificate = Certificate::new(batch(2, 0, vec![withdrawal(1, 1, 0)]), 0, &key(1));
+        certificate.attest(&key(2));
+        certificate.attest(&key(3));
+        let certificates = [certificate];
+
+        let features = CostFeatures::of_certificates(&certificates);
+        assert_eq!(features.signatures, 3);
+        assert_eq!(
+            CostFeatures {
+                signatures: 1,
+                ..features
+            },
+            CostFeatures::of(&[certificates[0].batch.clone()])
+        );
+        assert!(
+            CostModel::CALIBRATED.estimate(&features)
+                > estimate_cost(&[certificates[0].batch.clone()])
+        );
+    }
+
+    #[test]
+    fn test_estimate_grows_with_input() {
+        let small = [batch(0, 0, vec![withdrawal(1, 1, 0)])];
+        let large = [batch(0, 0, (0..100).map(|_| withdrawal(1, 1, 0)).collect())];
+
+        assert!(estimate_cost(&[]) > 0);
+        assert!(estimate_cost(&small) > estimate_cost(&[]));
+        assert!(estimate_cost(&large) > estimate_cost(&small));
+    }
+
+    #[test]
+    fn test_coefficients_round_trip() {
+        let model = CostModel::CALIBRATED;
+
+        assert_eq!(CostModel::from_coefficients(model.coefficients()), model);
+    }
+}
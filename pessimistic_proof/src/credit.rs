@@ -0,0 +1,23 @@
+//! Credit lines, which allow trusted networks a bounded overdraft on specific tokens.
+//!
+//! The credit lines are part of the [`ProofContext`](crate::context::ProofContext): a network may
+//! withdraw up to its credit line more of a token than it was deposited, for example during a
+//! liquidity migration, without being considered in debt. Their hash is committed by the proof,
+//! along with the credit actually used, so that every overdraft is visible on L1.
+
+use reth_primitives::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::{token_amounts::TokenAmounts, NetworkId, TokenInfo};
+
+/// The overdraft of each `(network, token)` pair which has a credit line.
+pub type CreditLines = TokenAmounts;
+
+/// The overdraft of a token by a network after the proof, within its credit line.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreditUsed {
+    pub network: NetworkId,
+    pub token: TokenInfo,
+    /// Amount withdrawn beyond the amount deposited
+    pub amount: U256,
+}
@@ -0,0 +1,37 @@
+//! Markers delimiting the components of the proof whose cycles are reported by the zkVM
+//! executor.
+//!
+//! Each component is tracked across all its occurrences: the executor reports the total number of
+//! cycles spent between the matching [`start`] and [`end`] calls. Components may nest, such as
+//! [`LEAF_HASHING`] within [`EXIT_TREE`]. Outside of the zkVM, the markers are no-ops.
+
+/// Hashing the withdrawals into leaves, appending them to the local exit trees, and computing the
+/// new roots.
+pub const EXIT_TREE: &str = "exit_tree";
+/// Hashing the withdrawals into leaves, as part of [`EXIT_TREE`].
+pub const LEAF_HASHING: &str = "leaf_hashing";
+/// Applying the withdrawals to the balance trees, and merging those of every batch.
+pub const BALANCE_MERGE: &str = "balance_merge";
+/// Hashing the merged balance trees.
+pub const BALANCE_HASHING: &str = "balance_hashing";
+
+/// Every tracked component.
+pub const COMPONENTS: [&str; 4] = [EXIT_TREE, LEAF_HASHING, BALANCE_MERGE, BALANCE_HASHING];
+
+/// Marks the start of an occurrence of `component`.
+#[inline]
+pub fn start(component: &str) {
+    #[cfg(target_os = "zkvm")]
+    println!("cycle-tracker-report-start: {component}");
+    #[cfg(not(target_os = "zkvm"))]
+    let _ = component;
+}
+
+/// Marks the end of an occurrence of `component`.
+#[inline]
+pub fn end(component: &str) {
+    #[cfg(target_os = "zkvm")]
+    println!("cycle-tracker-report-end: {component}");
+    #[cfg(not(target_os = "zkvm"))]
+    let _ = component;
+}
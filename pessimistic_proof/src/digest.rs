@@ -0,0 +1,56 @@
+//! The digest of the input data of each network, committed by the proof.
+//!
+//! The digest is a hash chain over the leaves of the withdrawals of the batch, in order, starting
+//! from the id of the network. L1 or a data availability layer can recompute it from the published
+//! data with [`input_digests`], and check it against the public values of the proof.
+//!
+//! Batches carry no imported claims yet, so the digest only covers the withdrawals.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    batch::Batch,
+    keccak::{keccak256, keccak256_combine, Digest},
+    NetworkId,
+};
+
+/// A hash chain, where each item is hashed along with the previous link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashChain(Digest);
+
+impl HashChain {
+    /// Starts the chain of the input data of `network`.
+    pub fn new(network: NetworkId) -> Self {
+        Self(keccak256(&network.to_be_bytes()))
+    }
+
+    /// Appends `item` to the chain.
+    pub fn push(&mut self, item: &Digest) {
+        self.0 = keccak256_combine([self.0.as_slice(), item.as_slice()]);
+    }
+
+    /// Returns the last link of the chain.
+    pub fn digest(&self) -> Digest {
+        self.0
+    }
+}
+
+/// Computes the input digest of `network` from the leaves of its withdrawals.
+pub fn input_digest(network: NetworkId, leaves: impl Iterator<Item = Digest>) -> Digest {
+    let mut chain = HashChain::new(network);
+    for leaf in leaves {
+        chain.push(&leaf);
+    }
+
+    chain.digest()
+}
+
+/// Computes the input digest of each network, as committed by the proof of `batches`.
+pub fn input_digests<'a>(
+    batches: impl IntoIterator<Item = &'a Batch>,
+) -> BTreeMap<NetworkId, Digest> {
+    batches
+        .into_iter()
+        .map(|batch| (batch.origin_network, batch.input_digest()))
+        .collect()
+}
@@ -0,0 +1,109 @@
+//! Minimal decoder for the ABI-encoded payloads emitted and accepted by the bridge contract.
+
+use std::fmt;
+
+use reth_primitives::{Address, U256};
+
+/// Represents all errors that can occur while decoding ABI-encoded data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiError {
+    /// The data is too short to contain the requested word.
+    OutOfBounds { offset: usize, len: usize },
+    /// The word does not hold a valid value of the expected type.
+    InvalidValue { offset: usize, ty: &'static str },
+}
+
+impl fmt::Display for AbiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiError::OutOfBounds { offset, len } => {
+                write!(f, "ABI data of {len} bytes is too short to read at offset {offset}")
+            }
+            AbiError::InvalidValue { offset, ty } => {
+                write!(f, "invalid ABI-encoded `{ty}` at offset {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AbiError {}
+
+/// Reads the head and tail of ABI-encoded data, rejecting values with dirty padding.
+pub(crate) struct AbiDecoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> AbiDecoder<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8], AbiError> {
+        offset.checked_add(len).and_then(|end| self.data.get(offset..end)).ok_or(
+            AbiError::OutOfBounds {
+                offset,
+                len: self.data.len(),
+            },
+        )
+    }
+
+    fn word_at(&self, offset: usize) -> Result<&'a [u8; 32], AbiError> {
+        Ok(self.slice(offset, 32)?.try_into().expect("slice of 32 bytes"))
+    }
+
+    /// Reads a value of type `ty` that is left-padded with `32 - size` zero bytes.
+    fn padded(&self, index: usize, size: usize, ty: &'static str) -> Result<&'a [u8], AbiError> {
+        let offset = index * 32;
+        let word = self.word_at(offset)?;
+
+        if word[..32 - size].iter().any(|byte| *byte != 0) {
+            return Err(AbiError::InvalidValue { offset, ty });
+        }
+
+        Ok(&word[32 - size..])
+    }
+
+    pub(crate) fn uint256(&self, index: usize) -> Result<U256, AbiError> {
+        Ok(U256::from_be_bytes(*self.word_at(index * 32)?))
+    }
+
+    pub(crate) fn uint8(&self, index: usize) -> Result<u8, AbiError> {
+        Ok(self.padded(index, 1, "uint8")?[0])
+    }
+
+    pub(crate) fn uint32(&self, index: usize) -> Result<u32, AbiError> {
+        let bytes = self.padded(index, 4, "uint32")?;
+
+        Ok(u32::from_be_bytes(bytes.try_into().expect("slice of 4 bytes")))
+    }
+
+    pub(crate) fn address(&self, index: usize) -> Result<Address, AbiError> {
+        Ok(Address::from_slice(self.padded(index, 20, "address")?))
+    }
+
+    pub(crate) fn bool(&self, index: usize) -> Result<bool, AbiError> {
+        match self.padded(index, 1, "bool")?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(AbiError::InvalidValue {
+                offset: index * 32,
+                ty: "bool",
+            }),
+        }
+    }
+
+    /// Reads the dynamic `bytes` value whose offset is stored in the head at `index`.
+    pub(crate) fn bytes(&self, index: usize) -> Result<&'a [u8], AbiError> {
+        let to_usize = |value: U256, offset: usize| {
+            usize::try_from(value).map_err(|_| AbiError::InvalidValue {
+                offset,
+                ty: "bytes",
+            })
+        };
+
+        let tail_offset = to_usize(self.uint256(index)?, index * 32)?;
+        let len = to_usize(U256::from_be_bytes(*self.word_at(tail_offset)?), tail_offset)?;
+
+        self.slice(tail_offset + 32, len)
+    }
+}
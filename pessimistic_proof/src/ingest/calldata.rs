@@ -0,0 +1,241 @@
+//! Decoding of the bridge contract calldata into [`Withdrawal`]s.
+//!
+//! This mirrors the leaf construction of `bridgeAsset`, `bridgeMessage` and `bridgeMessageWETH` in
+//! the bridge contract, so that pending transactions can be checked before they are included.
+
+use std::{collections::BTreeMap, fmt};
+
+use reth_primitives::{Address, U256};
+
+use super::abi::{AbiDecoder, AbiError};
+use crate::{LeafType, NetworkId, TokenInfo, Withdrawal};
+
+/// Selector of `bridgeAsset(uint32,address,uint256,address,bool,bytes)`.
+pub const BRIDGE_ASSET_SELECTOR: [u8; 4] = [0xcd, 0x58, 0x65, 0x79];
+/// Selector of `bridgeMessage(uint32,address,bool,bytes)`.
+pub const BRIDGE_MESSAGE_SELECTOR: [u8; 4] = [0x24, 0x0f, 0xf3, 0x78];
+/// Selector of `bridgeMessageWETH(uint32,address,uint256,bool,bytes)`.
+pub const BRIDGE_MESSAGE_WETH_SELECTOR: [u8; 4] = [0xb8, 0xb2, 0x84, 0xd0];
+
+/// The token used to pay for gas on a network.
+#[derive(Clone, Debug)]
+pub struct GasToken {
+    /// Unique ID of the gas token
+    pub token_info: TokenInfo,
+    /// Metadata attached to the leaves bridging the gas token
+    pub metadata: Vec<u8>,
+}
+
+/// The state of a bridge contract required to build the leaves of its calls.
+#[derive(Clone, Debug, Default)]
+pub struct BridgeContext {
+    /// Network of the bridge contract
+    pub network_id: NetworkId,
+    /// Gas token of the network, or `None` if it is ETH
+    pub gas_token: Option<GasToken>,
+    /// Address of the WETH token, which only exists on networks with a custom gas token
+    pub weth_token: Option<Address>,
+    /// Tokens wrapped by the bridge, by address on this network
+    pub wrapped_tokens: BTreeMap<Address, TokenInfo>,
+    /// `abi.encode(name, symbol, decimals)` of the tokens native to this network, by address
+    pub token_metadata: BTreeMap<Address, Vec<u8>>,
+}
+
+/// Represents all errors that can occur while decoding bridge calldata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CalldataError {
+    /// The calldata does not call one of the supported bridge functions.
+    UnknownSelector(Vec<u8>),
+    /// The arguments are not a valid encoding of the function parameters.
+    InvalidArguments(AbiError),
+    /// The destination network is the network of the bridge itself.
+    DestinationNetworkInvalid(NetworkId),
+    /// The ETH or gas token amount does not match the transaction value.
+    AmountDoesNotMatchMsgValue { amount: U256, value: U256 },
+    /// The transaction sends value along with a token which is not the gas token.
+    MsgValueNotZero(U256),
+    /// `bridgeMessage` was called on a network with a custom gas token.
+    NativeTokenIsNotEther,
+    /// `bridgeMessageWETH` was called on a network whose gas token is ETH.
+    NativeTokenIsEther,
+    /// The metadata of a token native to this network is unknown.
+    MissingTokenMetadata(Address),
+}
+
+impl fmt::Display for CalldataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalldataError::UnknownSelector(selector) => {
+                write!(f, "unknown bridge function selector {selector:02x?}")
+            }
+            CalldataError::InvalidArguments(e) => write!(f, "invalid arguments: {e}"),
+            CalldataError::DestinationNetworkInvalid(network) => {
+                write!(f, "invalid destination network {}", **network)
+            }
+            CalldataError::AmountDoesNotMatchMsgValue { amount, value } => {
+                write!(f, "amount {amount} does not match the transaction value {value}")
+            }
+            CalldataError::MsgValueNotZero(value) => {
+                write!(f, "unexpected transaction value {value}")
+            }
+            CalldataError::NativeTokenIsNotEther => {
+                write!(f, "`bridgeMessage` requires ETH as the gas token")
+            }
+            CalldataError::NativeTokenIsEther => {
+                write!(f, "`bridgeMessageWETH` requires a custom gas token")
+            }
+            CalldataError::MissingTokenMetadata(token) => {
+                write!(f, "missing metadata for token {token}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CalldataError {}
+
+impl From<AbiError> for CalldataError {
+    fn from(e: AbiError) -> Self {
+        CalldataError::InvalidArguments(e)
+    }
+}
+
+impl BridgeContext {
+    /// Creates a new [`BridgeContext`] for a network whose gas token is ETH.
+    pub fn new(network_id: NetworkId) -> Self {
+        Self {
+            network_id,
+            ..Default::default()
+        }
+    }
+
+    /// Builds the [`Withdrawal`] that a call to the bridge would append to the local exit tree.
+    ///
+    /// `sender` and `value` are the `from` and `value` fields of the transaction. For tokens native
+    /// to this network, the amount is the nominal one, which differs from the actual leaf amount
+    /// for tokens taking a fee on transfer.
+    pub fn decode_calldata(
+        &self,
+        sender: Address,
+        value: U256,
+        calldata: &[u8],
+    ) -> Result<Withdrawal, CalldataError> {
+        let (selector, arguments) = calldata
+            .split_first_chunk::<4>()
+            .ok_or_else(|| CalldataError::UnknownSelector(calldata.to_vec()))?;
+        let arguments = AbiDecoder::new(arguments);
+
+        match *selector {
+            BRIDGE_ASSET_SELECTOR => self.decode_bridge_asset(value, &arguments),
+            BRIDGE_MESSAGE_SELECTOR => {
+                if self.weth_token.is_some() {
+                    return Err(CalldataError::NativeTokenIsNotEther);
+                }
+
+                // bridgeMessage(destinationNetwork, destinationAddress, forceUpdate, metadata)
+                arguments.bool(2)?;
+                self.message(sender, value, &arguments, 3)
+            }
+            BRIDGE_MESSAGE_WETH_SELECTOR => {
+                if self.weth_token.is_none() {
+                    return Err(CalldataError::NativeTokenIsEther);
+                }
+                if value != U256::ZERO {
+                    return Err(CalldataError::MsgValueNotZero(value));
+                }
+
+                // bridgeMessageWETH(destinationNetwork, destinationAddress, amountWETH,
+                //                   forceUpdate, metadata)
+                arguments.bool(3)?;
+                self.message(sender, arguments.uint256(2)?, &arguments, 4)
+            }
+            _ => Err(CalldataError::UnknownSelector(selector.to_vec())),
+        }
+    }
+
+    fn decode_bridge_asset(
+        &self,
+        value: U256,
+        arguments: &AbiDecoder,
+    ) -> Result<Withdrawal, CalldataError> {
+        // bridgeAsset(destinationNetwork, destinationAddress, amount, token, forceUpdate, permit)
+        let dest_network = self.destination_network(arguments)?;
+        let dest_address = arguments.address(1)?;
+        let amount = arguments.uint256(2)?;
+        let token = arguments.address(3)?;
+        arguments.bool(4)?;
+        arguments.bytes(5)?;
+
+        let (token_info, metadata) = if token == Address::ZERO {
+            if value != amount {
+                return Err(CalldataError::AmountDoesNotMatchMsgValue { amount, value });
+            }
+
+            match &self.gas_token {
+                Some(gas_token) => (gas_token.token_info.clone(), gas_token.metadata.clone()),
+                None => (TokenInfo::ETH, Vec::new()),
+            }
+        } else {
+            if value != U256::ZERO {
+                return Err(CalldataError::MsgValueNotZero(value));
+            }
+
+            if Some(token) == self.weth_token {
+                (TokenInfo::ETH, Vec::new())
+            } else if let Some(token_info) = self.wrapped_tokens.get(&token) {
+                (token_info.clone(), Vec::new())
+            } else {
+                let metadata = self
+                    .token_metadata
+                    .get(&token)
+                    .ok_or(CalldataError::MissingTokenMetadata(token))?;
+
+                let token_info = TokenInfo {
+                    origin_network: self.network_id,
+                    origin_token_address: token,
+                };
+
+                (token_info, metadata.clone())
+            }
+        };
+
+        Ok(Withdrawal {
+            leaf_type: LeafType::Asset,
+            token_info,
+            dest_network,
+            dest_address,
+            amount,
+            metadata,
+        })
+    }
+
+    /// Builds a message leaf, whose metadata is the `bytes` argument at `metadata_index`.
+    fn message(
+        &self,
+        sender: Address,
+        amount: U256,
+        arguments: &AbiDecoder,
+        metadata_index: usize,
+    ) -> Result<Withdrawal, CalldataError> {
+        Ok(Withdrawal {
+            leaf_type: LeafType::Message,
+            token_info: TokenInfo {
+                origin_network: self.network_id,
+                origin_token_address: sender,
+            },
+            dest_network: self.destination_network(arguments)?,
+            dest_address: arguments.address(1)?,
+            amount,
+            metadata: arguments.bytes(metadata_index)?.to_vec(),
+        })
+    }
+
+    fn destination_network(&self, arguments: &AbiDecoder) -> Result<NetworkId, CalldataError> {
+        let dest_network: NetworkId = arguments.uint32(0)?.into();
+
+        if dest_network == self.network_id {
+            return Err(CalldataError::DestinationNetworkInvalid(dest_network));
+        }
+
+        Ok(dest_network)
+    }
+}
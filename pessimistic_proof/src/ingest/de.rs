@@ -0,0 +1,87 @@
+//! Lenient deserializers for the fields found in bridge event dumps.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reth_primitives::{B256, U256};
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::keccak::Digest;
+
+/// Deserializes an amount given as a JSON number of arbitrary precision, a decimal string or a
+/// `0x`-prefixed hex string.
+pub(crate) fn u256<'de, D>(deserializer: D) -> Result<U256, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Number(n) => parse_u256(n.as_str()).map_err(D::Error::custom),
+        Value::String(s) => parse_u256(&s).map_err(D::Error::custom),
+        other => Err(D::Error::custom(format!(
+            "expected an amount as a number or a string, got `{other}`"
+        ))),
+    }
+}
+
+/// Parses a decimal or `0x`-prefixed hex string into a [`U256`].
+pub(crate) fn parse_u256(s: &str) -> Result<U256, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => U256::from_str_radix(hex, 16),
+        None => U256::from_str_radix(s, 10),
+    };
+
+    parsed.map_err(|e| format!("invalid amount `{s}`: {e}"))
+}
+
+/// Deserializes a JSON-RPC quantity, given as a `0x`-prefixed hex string or a number.
+pub(crate) fn quantity<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = u256(deserializer)?;
+
+    u64::try_from(value).map_err(|_| D::Error::custom(format!("quantity {value} overflows u64")))
+}
+
+/// Deserializes a [`Digest`] given either as an array of 32 bytes or as a `0x`-prefixed hex
+/// string.
+pub(crate) fn digest<'de, D>(deserializer: D) -> Result<Digest, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => s
+            .parse::<B256>()
+            .map(|digest| digest.0)
+            .map_err(|e| D::Error::custom(format!("invalid digest `{s}`: {e}"))),
+        Value::Array(bytes) => {
+            let bytes = bytes
+                .iter()
+                .map(|byte| {
+                    byte.as_u64()
+                        .and_then(|byte| u8::try_from(byte).ok())
+                        .ok_or_else(|| D::Error::custom(format!("invalid digest byte `{byte}`")))
+                })
+                .collect::<Result<Vec<u8>, _>>()?;
+
+            let len = bytes.len();
+            bytes
+                .try_into()
+                .map_err(|_| D::Error::custom(format!("expected 32 digest bytes, got {len}")))
+        }
+        other => Err(D::Error::custom(format!(
+            "expected a digest as a hex string or a byte array, got `{other}`"
+        ))),
+    }
+}
+
+/// Deserializes base64-encoded bytes.
+pub(crate) fn base64_bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let encoded = String::deserialize(deserializer)?;
+
+    STANDARD
+        .decode(&encoded)
+        .map_err(|e| D::Error::custom(format!("invalid base64 metadata: {e}")))
+}
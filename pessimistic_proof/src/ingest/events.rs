@@ -0,0 +1,157 @@
+use reth_primitives::{Address, U256};
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+use super::de;
+use crate::{keccak::Digest, LeafType, TokenInfo, Withdrawal};
+
+/// The position of an event in the chain: (block number, tx index, log index).
+pub type EventPosition = (u64, u64, u64);
+
+/// A bridge event, along with its position in the chain.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BridgeEvent {
+    pub removed: bool,
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub transaction_hash: String,
+    pub event_type: u8,
+    pub event_data: EventData,
+}
+
+impl BridgeEvent {
+    /// Returns the position of the event in the chain.
+    pub fn position(&self) -> EventPosition {
+        (self.block_number, self.transaction_index, self.log_index)
+    }
+}
+
+/// The payload of a [`BridgeEvent`].
+///
+/// The variant is selected from the field which is unique to it (`mainnetExitRoot`,
+/// `depositCount`, `index` or `rollupId`), so that a malformed record reports the actual faulty
+/// field.
+#[derive(Clone, Debug)]
+pub enum EventData {
+    /// Mainnet exit root update event
+    UpdateL1InfoTree {
+        mainnet_exit_root: Digest,
+        rollup_exit_root: Digest,
+    },
+    /// Deposit event
+    Deposit(DepositEventData),
+    /// Claim event
+    Claim(ClaimEventData),
+    /// Rollup local exit root update, emitted by the rollup manager on batch verification
+    VerifyBatches { rollup_id: u32, exit_root: Digest },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateL1InfoTreeEventData {
+    #[serde(deserialize_with = "de::digest")]
+    mainnet_exit_root: Digest,
+    #[serde(deserialize_with = "de::digest")]
+    rollup_exit_root: Digest,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyBatchesEventData {
+    rollup_id: u32,
+    #[serde(deserialize_with = "de::digest")]
+    exit_root: Digest,
+}
+
+impl<'de> Deserialize<'de> for EventData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields = Map::<String, Value>::deserialize(deserializer)?;
+
+        let event_data = if fields.contains_key("mainnetExitRoot") {
+            let UpdateL1InfoTreeEventData {
+                mainnet_exit_root,
+                rollup_exit_root,
+            } = serde_json::from_value(Value::Object(fields)).map_err(D::Error::custom)?;
+
+            EventData::UpdateL1InfoTree {
+                mainnet_exit_root,
+                rollup_exit_root,
+            }
+        } else if fields.contains_key("depositCount") {
+            EventData::Deposit(
+                serde_json::from_value(Value::Object(fields)).map_err(D::Error::custom)?,
+            )
+        } else if fields.contains_key("index") {
+            EventData::Claim(
+                serde_json::from_value(Value::Object(fields)).map_err(D::Error::custom)?,
+            )
+        } else if fields.contains_key("rollupId") {
+            let VerifyBatchesEventData {
+                rollup_id,
+                exit_root,
+            } = serde_json::from_value(Value::Object(fields)).map_err(D::Error::custom)?;
+
+            EventData::VerifyBatches {
+                rollup_id,
+                exit_root,
+            }
+        } else {
+            return Err(D::Error::custom(
+                "unknown event data: expected a `mainnetExitRoot`, `depositCount`, `index` or \
+                 `rollupId` field",
+            ));
+        };
+
+        Ok(event_data)
+    }
+}
+
+/// The data of a `BridgeEvent` emitted by the bridge contract on deposit.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositEventData {
+    pub leaf_type: LeafType,
+    pub origin_network: u32,
+    pub origin_address: Address,
+    pub destination_network: u32,
+    pub destination_address: Address,
+    #[serde(deserialize_with = "de::u256")]
+    pub amount: U256,
+    #[serde(deserialize_with = "de::base64_bytes")]
+    pub metadata: Vec<u8>,
+    pub deposit_count: u32,
+}
+
+impl From<DepositEventData> for Withdrawal {
+    fn from(deposit_event_data: DepositEventData) -> Self {
+        Self {
+            leaf_type: deposit_event_data.leaf_type,
+            token_info: TokenInfo {
+                origin_network: deposit_event_data.origin_network.into(),
+                origin_token_address: deposit_event_data.origin_address,
+            },
+            dest_network: deposit_event_data.destination_network.into(),
+            dest_address: deposit_event_data.destination_address,
+            amount: deposit_event_data.amount,
+            metadata: deposit_event_data.metadata,
+        }
+    }
+}
+
+/// The data of a `ClaimEvent` emitted by the bridge contract on claim.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimEventData {
+    #[serde(deserialize_with = "de::u256")]
+    #[serde(rename = "index")]
+    pub global_index: U256,
+    pub origin_network: u32,
+    pub origin_address: Address,
+    pub destination_address: Address,
+    #[serde(deserialize_with = "de::u256")]
+    pub amount: U256,
+}
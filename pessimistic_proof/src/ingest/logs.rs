@@ -0,0 +1,254 @@
+//! Decoding of raw `eth_getLogs` output into [`BridgeEvent`]s.
+
+use std::{fmt, io::Read};
+
+use reth_primitives::{b256, Address, Bytes, B256, U256};
+use serde::Deserialize;
+
+use super::{
+    abi::{AbiDecoder, AbiError},
+    de, BridgeEvent, ClaimEventData, DepositEventData, EventData, IngestError,
+};
+use crate::{LeafType, UnknownLeafType};
+
+/// Topic of `BridgeEvent(uint8,uint32,address,uint32,address,uint256,bytes,uint32)`.
+pub const BRIDGE_EVENT_TOPIC: B256 =
+    b256!("501781209a1f8899323b96b4ef08b168df93e0a90c673d1e4cce39366cb62f9b");
+/// Topic of `ClaimEvent(uint256,uint32,address,address,uint256)`.
+pub const CLAIM_EVENT_TOPIC: B256 =
+    b256!("1df3f2a973a00d6635911755c260704e95e8a5876997546798770f76396fda4d");
+/// Topic of `ClaimEvent(uint32,uint32,address,address,uint256)`, emitted before the bridge
+/// switched to global indices.
+pub const LEGACY_CLAIM_EVENT_TOPIC: B256 =
+    b256!("25308c93ceeed162da955b3f7ce3e3f93606579e40fb92029faa9efe27545983");
+/// Topic of `UpdateL1InfoTree(bytes32,bytes32)`, emitted by the global exit root manager.
+pub const UPDATE_L1_INFO_TREE_TOPIC: B256 =
+    b256!("da61aa7823fcd807e37b95aabcbe17f03a6f3efd514176444dae191d27fd66b3");
+/// Topic of `VerifyBatches(uint32,uint64,bytes32,bytes32,address)`, emitted by the rollup manager.
+pub const VERIFY_BATCHES_TOPIC: B256 =
+    b256!("aac1e7a157b259544ebacd6e8a82ae5d6c8f174e12aa48696277bcc9a661f0b4");
+/// Topic of `VerifyBatchesTrustedAggregator(uint32,uint64,bytes32,bytes32,address)`, emitted by the
+/// rollup manager.
+pub const VERIFY_BATCHES_TRUSTED_AGGREGATOR_TOPIC: B256 =
+    b256!("d1ec3a1216f08b6eff72e169ceb548b782db18a6614852618d86bb19f3f9b0d3");
+
+/// [`BridgeEvent::event_type`] of the deposits decoded from logs.
+pub const DEPOSIT_EVENT_TYPE: u8 = 0;
+/// [`BridgeEvent::event_type`] of the exit root updates decoded from logs.
+pub const UPDATE_L1_INFO_TREE_EVENT_TYPE: u8 = 1;
+/// [`BridgeEvent::event_type`] of the claims decoded from logs.
+pub const CLAIM_EVENT_TYPE: u8 = 2;
+/// [`BridgeEvent::event_type`] of the rollup exit root updates decoded from logs.
+pub const VERIFY_BATCHES_EVENT_TYPE: u8 = 3;
+
+/// A log object as returned by `eth_getLogs`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+    #[serde(deserialize_with = "de::quantity")]
+    pub block_number: u64,
+    pub transaction_hash: B256,
+    #[serde(deserialize_with = "de::quantity")]
+    pub transaction_index: u64,
+    #[serde(deserialize_with = "de::quantity")]
+    pub log_index: u64,
+    #[serde(default)]
+    pub removed: bool,
+}
+
+/// Represents all errors that can occur while decoding a [`RawLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogDecodeError {
+    /// The log does not have the number of topics of its event.
+    InvalidTopicCount {
+        event: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    /// The log data is not a valid encoding of the event fields.
+    InvalidData {
+        event: &'static str,
+        source: AbiError,
+    },
+    /// The `BridgeEvent` has a leaf type other than asset or message.
+    UnknownLeafType(UnknownLeafType),
+}
+
+impl fmt::Display for LogDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogDecodeError::InvalidTopicCount {
+                event,
+                expected,
+                got,
+            } => write!(f, "expected {expected} topics for `{event}`, got {got}"),
+            LogDecodeError::InvalidData { event, source } => {
+                write!(f, "invalid data for `{event}`: {source}")
+            }
+            LogDecodeError::UnknownLeafType(error) => write!(f, "invalid `BridgeEvent`: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for LogDecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LogDecodeError::InvalidData { source, .. } => Some(source),
+            LogDecodeError::UnknownLeafType(error) => Some(error),
+            LogDecodeError::InvalidTopicCount { .. } => None,
+        }
+    }
+}
+
+/// Decodes a [`RawLog`] into a [`BridgeEvent`].
+///
+/// Returns `Ok(None)` for logs which are not one of the bridge events, so that the output of an
+/// unfiltered `eth_getLogs` call can be decoded as is. The emitting contract is not checked.
+pub fn decode_log(log: &RawLog) -> Result<Option<BridgeEvent>, LogDecodeError> {
+    let Some(topic) = log.topics.first() else {
+        return Ok(None);
+    };
+
+    let (event_type, event_data) = match *topic {
+        BRIDGE_EVENT_TOPIC => (DEPOSIT_EVENT_TYPE, decode_bridge_event(log)?),
+        CLAIM_EVENT_TOPIC => (CLAIM_EVENT_TYPE, decode_claim_event(log, false)?),
+        LEGACY_CLAIM_EVENT_TOPIC => (CLAIM_EVENT_TYPE, decode_claim_event(log, true)?),
+        UPDATE_L1_INFO_TREE_TOPIC => {
+            (UPDATE_L1_INFO_TREE_EVENT_TYPE, decode_update_l1_info_tree(log)?)
+        }
+        VERIFY_BATCHES_TOPIC | VERIFY_BATCHES_TRUSTED_AGGREGATOR_TOPIC => {
+            (VERIFY_BATCHES_EVENT_TYPE, decode_verify_batches(log)?)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(BridgeEvent {
+        removed: log.removed,
+        block_number: log.block_number,
+        transaction_index: log.transaction_index,
+        log_index: log.log_index,
+        transaction_hash: log.transaction_hash.to_string(),
+        event_type,
+        event_data,
+    }))
+}
+
+/// Reads a JSON array of raw logs, and decodes the bridge events among them.
+///
+/// Logs which are not bridge events are skipped. Errors report the index of the log in the array.
+pub fn read_logs<R: Read>(reader: R) -> Result<Vec<BridgeEvent>, IngestError> {
+    let logs: Vec<RawLog> = super::read_records(reader)?;
+
+    logs.iter()
+        .enumerate()
+        .filter_map(|(index, log)| {
+            decode_log(log)
+                .map_err(|source| IngestError::InvalidLog { index, source })
+                .transpose()
+        })
+        .collect()
+}
+
+fn check_topic_count(
+    log: &RawLog,
+    event: &'static str,
+    expected: usize,
+) -> Result<(), LogDecodeError> {
+    if log.topics.len() != expected {
+        return Err(LogDecodeError::InvalidTopicCount {
+            event,
+            expected,
+            got: log.topics.len(),
+        });
+    }
+
+    Ok(())
+}
+
+fn decode_bridge_event(log: &RawLog) -> Result<EventData, LogDecodeError> {
+    const EVENT: &str = "BridgeEvent";
+    check_topic_count(log, EVENT, 1)?;
+
+    let data = AbiDecoder::new(&log.data);
+    let invalid_data = |source| LogDecodeError::InvalidData {
+        event: EVENT,
+        source,
+    };
+    let leaf_type = data.uint8(0).map_err(invalid_data)?;
+    let leaf_type = LeafType::try_from(leaf_type).map_err(LogDecodeError::UnknownLeafType)?;
+
+    let decode = || -> Result<DepositEventData, AbiError> {
+        Ok(DepositEventData {
+            leaf_type,
+            origin_network: data.uint32(1)?,
+            origin_address: data.address(2)?,
+            destination_network: data.uint32(3)?,
+            destination_address: data.address(4)?,
+            amount: data.uint256(5)?,
+            metadata: data.bytes(6)?.to_vec(),
+            deposit_count: data.uint32(7)?,
+        })
+    };
+
+    decode().map(EventData::Deposit).map_err(invalid_data)
+}
+
+/// Decodes a `ClaimEvent`, whose index is a `uint32` in the `legacy` version of the event.
+fn decode_claim_event(log: &RawLog, legacy: bool) -> Result<EventData, LogDecodeError> {
+    const EVENT: &str = "ClaimEvent";
+    check_topic_count(log, EVENT, 1)?;
+
+    let data = AbiDecoder::new(&log.data);
+    let decode = || -> Result<ClaimEventData, AbiError> {
+        let global_index = if legacy {
+            U256::from(data.uint32(0)?)
+        } else {
+            data.uint256(0)?
+        };
+
+        Ok(ClaimEventData {
+            global_index,
+            origin_network: data.uint32(1)?,
+            origin_address: data.address(2)?,
+            destination_address: data.address(3)?,
+            amount: data.uint256(4)?,
+        })
+    };
+
+    decode().map(EventData::Claim).map_err(|source| LogDecodeError::InvalidData {
+        event: EVENT,
+        source,
+    })
+}
+
+fn decode_update_l1_info_tree(log: &RawLog) -> Result<EventData, LogDecodeError> {
+    // Both exit roots are indexed
+    check_topic_count(log, "UpdateL1InfoTree", 3)?;
+
+    Ok(EventData::UpdateL1InfoTree {
+        mainnet_exit_root: log.topics[1].0,
+        rollup_exit_root: log.topics[2].0,
+    })
+}
+
+fn decode_verify_batches(log: &RawLog) -> Result<EventData, LogDecodeError> {
+    const EVENT: &str = "VerifyBatches";
+    // The rollup ID and the aggregator are indexed
+    check_topic_count(log, EVENT, 3)?;
+
+    let decode = || -> Result<EventData, AbiError> {
+        Ok(EventData::VerifyBatches {
+            rollup_id: AbiDecoder::new(log.topics[1].as_slice()).uint32(0)?,
+            // (numBatch, stateRoot, exitRoot)
+            exit_root: AbiDecoder::new(&log.data).uint256(2)?.to_be_bytes(),
+        })
+    };
+
+    decode().map_err(|source| LogDecodeError::InvalidData {
+        event: EVENT,
+        source,
+    })
+}
@@ -0,0 +1,135 @@
+//! Fallible ingestion of bridge event dumps.
+//!
+//! Unlike [`crate::test_utils`], nothing in this module panics on malformed input: every failure
+//! is reported as an [`IngestError`], which carries the position of the offending record.
+//!
+//! The `read_*` functions load a whole dump at once; see [`stream`] for inputs which do not fit in
+//! memory.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+mod abi;
+pub use abi::AbiError;
+
+pub mod calldata;
+
+mod de;
+
+mod events;
+pub use events::{BridgeEvent, ClaimEventData, DepositEventData, EventData, EventPosition};
+
+pub mod logs;
+use logs::LogDecodeError;
+
+pub mod stream;
+
+#[cfg(test)]
+mod tests;
+
+/// Represents all errors that can occur while ingesting bridge events.
+#[derive(Debug)]
+pub enum IngestError {
+    /// The input could not be read.
+    Io(std::io::Error),
+    /// The input is not a well-formed JSON array.
+    Json(serde_json::Error),
+    /// The record at `index` (0-based) could not be decoded.
+    InvalidRecord {
+        index: usize,
+        source: serde_json::Error,
+    },
+    /// The input is not a JSON array nor JSON lines, as detected before the record at `index`.
+    InvalidFormat { index: usize, reason: &'static str },
+    /// The record at `index` of the chunk `chunk` is positioned before the previous one.
+    UnsortedInput { chunk: usize, index: usize },
+    /// The log at `index` (0-based) is a bridge event that could not be decoded.
+    InvalidLog {
+        index: usize,
+        source: LogDecodeError,
+    },
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::Io(e) => write!(f, "failed to read input: {e}"),
+            IngestError::Json(e) => write!(f, "malformed JSON input: {e}"),
+            IngestError::InvalidRecord { index, source } => {
+                write!(f, "invalid record at index {index}: {source}")
+            }
+            IngestError::InvalidFormat { index, reason } => {
+                write!(f, "malformed input before record {index}: {reason}")
+            }
+            IngestError::UnsortedInput { chunk, index } => {
+                write!(f, "record {index} of chunk {chunk} is out of order")
+            }
+            IngestError::InvalidLog { index, source } => {
+                write!(f, "invalid log at index {index}: {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IngestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IngestError::Io(e) => Some(e),
+            IngestError::Json(e) => Some(e),
+            IngestError::InvalidRecord { source, .. } => Some(source),
+            IngestError::InvalidLog { source, .. } => Some(source),
+            IngestError::InvalidFormat { .. } | IngestError::UnsortedInput { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for IngestError {
+    fn from(e: std::io::Error) -> Self {
+        IngestError::Io(e)
+    }
+}
+
+/// Reads a JSON array of records, decoding each of them independently.
+///
+/// The first record that fails to decode is reported along with its index in the array.
+pub fn read_records<T, R>(reader: R) -> Result<Vec<T>, IngestError>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    let values: Vec<Value> = serde_json::from_reader(reader).map_err(IngestError::Json)?;
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            serde_json::from_value(value)
+                .map_err(|source| IngestError::InvalidRecord { index, source })
+        })
+        .collect()
+}
+
+/// Reads a JSON array of records from the file at `path`. See [`read_records`].
+pub fn read_records_from_file<T>(path: impl AsRef<Path>) -> Result<Vec<T>, IngestError>
+where
+    T: DeserializeOwned,
+{
+    let file = File::open(path)?;
+
+    read_records(BufReader::new(file))
+}
+
+/// Reads the bridge events from the file at `path`, sorted by their position in the chain.
+pub fn read_sorted_bridge_events(path: impl AsRef<Path>) -> Result<Vec<BridgeEvent>, IngestError> {
+    let mut bridge_events: Vec<BridgeEvent> = read_records_from_file(path)?;
+    bridge_events.sort_unstable_by_key(BridgeEvent::position);
+
+    Ok(bridge_events)
+}
@@ -0,0 +1,338 @@
+//! Streaming ingestion of bridge event dumps too large to be held in memory.
+//!
+//! A [`RecordReader`] yields the records of a JSON array or of JSON-lines input one at a time, and
+//! [`MergeSorted`] merges several pre-sorted chunks by position in the chain, so that a full
+//! history can be fed to [`crate::local_exit_tree::LocalExitTree::add_leaf`] in constant memory.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufRead, BufReader},
+    marker::PhantomData,
+    path::Path,
+};
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    logs::{decode_log, RawLog},
+    BridgeEvent, EventPosition, IngestError,
+};
+
+/// The layout of the input, detected from its first non-whitespace byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// Records are the elements of a top-level JSON array.
+    Array,
+    /// Records are whitespace-separated JSON values, typically one per line.
+    Lines,
+}
+
+/// Iterator over the records of a JSON array or of JSON-lines input.
+///
+/// Only the bytes of the current record are buffered. Iteration stops after the first error.
+pub struct RecordReader<R, T> {
+    reader: R,
+    layout: Option<Layout>,
+    index: usize,
+    buffer: Vec<u8>,
+    done: bool,
+    _record: PhantomData<T>,
+}
+
+impl<R: BufRead, T: DeserializeOwned> RecordReader<R, T> {
+    /// Creates a new [`RecordReader`].
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            layout: None,
+            index: 0,
+            buffer: Vec::new(),
+            done: false,
+            _record: PhantomData,
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, IngestError> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, IngestError> {
+        while let Some(byte) = self.peek()? {
+            if !byte.is_ascii_whitespace() {
+                return Ok(Some(byte));
+            }
+            self.reader.consume(1);
+        }
+
+        Ok(None)
+    }
+
+    fn invalid_format(&self, reason: &'static str) -> IngestError {
+        IngestError::InvalidFormat {
+            index: self.index,
+            reason,
+        }
+    }
+
+    /// Copies the bytes of the next JSON value into the buffer.
+    fn read_value(&mut self) -> Result<(), IngestError> {
+        self.buffer.clear();
+
+        let mut depth = 0_usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        while let Some(byte) = self.peek()? {
+            if !in_string && depth == 0 && !self.buffer.is_empty() {
+                let is_delimiter = byte.is_ascii_whitespace() || byte == b',' || byte == b']';
+                let is_complete = matches!(self.buffer.last(), Some(b'}' | b']' | b'"'));
+
+                if is_delimiter || is_complete {
+                    return Ok(());
+                }
+            }
+
+            self.reader.consume(1);
+            self.buffer.push(byte);
+
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or_else(|| self.invalid_format("unbalanced brackets"))?;
+                }
+                _ => {}
+            }
+        }
+
+        if in_string || depth != 0 {
+            return Err(self.invalid_format("unexpected end of input"));
+        }
+
+        Ok(())
+    }
+
+    /// Positions the reader at the start of the next record, if any.
+    fn seek_record(&mut self) -> Result<bool, IngestError> {
+        let layout = match self.layout {
+            Some(layout) => layout,
+            None => {
+                let layout = match self.skip_whitespace()? {
+                    Some(b'[') => {
+                        self.reader.consume(1);
+                        Layout::Array
+                    }
+                    _ => Layout::Lines,
+                };
+                self.layout = Some(layout);
+                layout
+            }
+        };
+
+        let mut next = self.skip_whitespace()?;
+        if layout == Layout::Lines {
+            return Ok(next.is_some());
+        }
+
+        if next == Some(b']') {
+            self.reader.consume(1);
+            if self.skip_whitespace()?.is_some() {
+                return Err(self.invalid_format("trailing data after the array"));
+            }
+            return Ok(false);
+        }
+
+        if self.index > 0 {
+            if next != Some(b',') {
+                return Err(self.invalid_format("expected `,` or `]` after an array element"));
+            }
+            self.reader.consume(1);
+            next = self.skip_whitespace()?;
+        }
+
+        match next {
+            None => Err(self.invalid_format("unterminated array")),
+            Some(b',' | b']') => Err(self.invalid_format("missing array element")),
+            Some(_) => Ok(true),
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<T>, IngestError> {
+        if !self.seek_record()? {
+            return Ok(None);
+        }
+
+        self.read_value()?;
+        let record =
+            serde_json::from_slice(&self.buffer).map_err(|source| IngestError::InvalidRecord {
+                index: self.index,
+                source,
+            })?;
+        self.index += 1;
+
+        Ok(Some(record))
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for RecordReader<R, T> {
+    type Item = Result<T, IngestError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let next = self.next_record().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+
+        next
+    }
+}
+
+/// Streams the bridge events of the file at `path`.
+pub fn stream_bridge_events_from_file(
+    path: impl AsRef<Path>,
+) -> Result<RecordReader<BufReader<File>, BridgeEvent>, IngestError> {
+    let file = File::open(path)?;
+
+    Ok(RecordReader::new(BufReader::new(file)))
+}
+
+/// Streams the bridge events decoded from the raw `eth_getLogs` objects of the file at `path`.
+///
+/// Logs which are not bridge events are skipped.
+pub fn stream_bridge_events_from_log_file(
+    path: &Path,
+) -> Result<impl Iterator<Item = Result<BridgeEvent, IngestError>>, IngestError> {
+    let file = File::open(path)?;
+
+    let events = RecordReader::<_, RawLog>::new(BufReader::new(file)).enumerate().filter_map(
+        |(index, log)| match log {
+            Ok(log) => decode_log(&log)
+                .map_err(|source| IngestError::InvalidLog { index, source })
+                .transpose(),
+            Err(e) => Some(Err(e)),
+        },
+    );
+
+    Ok(events)
+}
+
+/// A stream of bridge events, whatever the layout of its source.
+pub type BridgeEvents = Box<dyn Iterator<Item = Result<BridgeEvent, IngestError>>>;
+
+/// Streams the bridge events of the file at `path`, which holds raw `eth_getLogs` objects if
+/// `raw_logs` is set. See [`stream_bridge_events_from_file`] and
+/// [`stream_bridge_events_from_log_file`].
+pub fn stream_bridge_events(path: &Path, raw_logs: bool) -> Result<BridgeEvents, IngestError> {
+    let events: BridgeEvents = if raw_logs {
+        Box::new(stream_bridge_events_from_log_file(path)?)
+    } else {
+        Box::new(stream_bridge_events_from_file(path)?)
+    };
+
+    Ok(events)
+}
+
+/// Merges chunks of bridge events, each sorted by [`BridgeEvent::position`], into a single sorted
+/// stream.
+///
+/// Only the head of each chunk is held in memory. A chunk which turns out not to be sorted yields an
+/// [`IngestError::UnsortedInput`], after which the iteration stops.
+pub struct MergeSorted<I> {
+    chunks: Vec<I>,
+    heads: Vec<Option<BridgeEvent>>,
+    /// Number of events read so far from each chunk
+    counts: Vec<usize>,
+    queue: BinaryHeap<Reverse<(EventPosition, usize)>>,
+    pending_error: Option<IngestError>,
+    started: bool,
+}
+
+impl<I> MergeSorted<I>
+where
+    I: Iterator<Item = Result<BridgeEvent, IngestError>>,
+{
+    /// Creates a new [`MergeSorted`] over the given chunks.
+    pub fn new(chunks: Vec<I>) -> Self {
+        let len = chunks.len();
+
+        Self {
+            chunks,
+            heads: (0..len).map(|_| None).collect(),
+            counts: vec![0; len],
+            queue: BinaryHeap::with_capacity(len),
+            pending_error: None,
+            started: false,
+        }
+    }
+
+    /// Reads the next event of `chunk`, checking that the chunk is sorted.
+    fn advance(&mut self, chunk: usize, previous: Option<EventPosition>) {
+        match self.chunks[chunk].next() {
+            None => {}
+            Some(Err(e)) => self.pending_error = Some(e),
+            Some(Ok(event)) => {
+                let position = event.position();
+                if previous.is_some_and(|previous| position < previous) {
+                    self.pending_error = Some(IngestError::UnsortedInput {
+                        chunk,
+                        index: self.counts[chunk],
+                    });
+                    return;
+                }
+
+                self.counts[chunk] += 1;
+                self.heads[chunk] = Some(event);
+                self.queue.push(Reverse((position, chunk)));
+            }
+        }
+    }
+}
+
+impl<I> Iterator for MergeSorted<I>
+where
+    I: Iterator<Item = Result<BridgeEvent, IngestError>>,
+{
+    type Item = Result<BridgeEvent, IngestError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            // Only the first error is reported, so the next chunks are not read after it
+            for chunk in 0..self.chunks.len() {
+                if self.pending_error.is_some() {
+                    break;
+                }
+                self.advance(chunk, None);
+            }
+        }
+
+        if let Some(e) = self.pending_error.take() {
+            self.queue.clear();
+            return Some(Err(e));
+        }
+
+        let Reverse((position, chunk)) = self.queue.pop()?;
+        let event = self.heads[chunk].take().expect("queued chunks have a head");
+        self.advance(chunk, Some(position));
+
+        Some(Ok(event))
+    }
+}
@@ -0,0 +1,631 @@
+use reth_primitives::{address, U256};
+
+use super::*;
+use crate::Withdrawal;
+
+const DEPOSIT: &str = r#"{"leafType":0,"originNetwork":0,"originAddress":"0x0000000000000000000000000000000000000000","amount":11400681687679304,"destinationNetwork":1,"destinationAddress":"0x31bc2a964c8cc585ef366e225ea3a5e2a352c287","depositCount":1853,"metadata":""}"#;
+
+fn deposit_with_amount(amount: &str) -> String {
+    DEPOSIT.replace("11400681687679304", amount)
+}
+
+#[test]
+fn test_amount_formats() {
+    let expected = U256::from(11400681687679304_u64);
+
+    for amount in ["11400681687679304", "\"11400681687679304\"", "\"0x2880dc0e310148\""] {
+        let json = format!("[{}]", deposit_with_amount(amount));
+        let deposits: Vec<DepositEventData> = read_records(json.as_bytes()).unwrap();
+
+        assert_eq!(deposits[0].amount, expected, "amount given as {amount}");
+    }
+
+    // Amounts beyond u128 do not lose precision
+    let json = format!("[{}]", deposit_with_amount(&U256::MAX.to_string()));
+    let deposits: Vec<DepositEventData> = read_records(json.as_bytes()).unwrap();
+    assert_eq!(deposits[0].amount, U256::MAX);
+}
+
+#[test]
+fn test_invalid_record_reports_index() {
+    let json = format!(
+        "[{DEPOSIT},{DEPOSIT},{}]",
+        DEPOSIT.replace("0x31bc2a964c8cc585ef366e225ea3a5e2a352c287", "0xnot-an-address")
+    );
+
+    let err = read_records::<DepositEventData, _>(json.as_bytes()).unwrap_err();
+    assert!(matches!(err, IngestError::InvalidRecord { index: 2, .. }), "{err}");
+
+    for amount in ["-1", "1.5", "\"0xzz\"", "null"] {
+        let json = format!("[{}]", deposit_with_amount(amount));
+        let err = read_records::<DepositEventData, _>(json.as_bytes()).unwrap_err();
+
+        assert!(matches!(err, IngestError::InvalidRecord { index: 0, .. }), "{amount}: {err}");
+    }
+
+    let json = format!("[{}]", DEPOSIT.replace(r#""leafType":0"#, r#""leafType":2"#));
+    let err = read_records::<DepositEventData, _>(json.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("unknown leaf type 2"), "{err}");
+
+    let err = read_records::<DepositEventData, _>(&b"[{"[..]).unwrap_err();
+    assert!(matches!(err, IngestError::Json(_)));
+}
+
+#[test]
+fn test_event_data_variants() {
+    let json = format!(
+        r#"[
+            {{"removed":false,"block_number":3,"transaction_index":0,"log_index":1,"transaction_hash":"0x01","event_type":0,"event_data":{DEPOSIT}}},
+            {{"removed":false,"block_number":2,"transaction_index":5,"log_index":0,"transaction_hash":"0x02","event_type":1,"event_data":{{"mainnetExitRoot":"0x0101010101010101010101010101010101010101010101010101010101010101","rollupExitRoot":[2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2,2]}}}},
+            {{"removed":false,"block_number":2,"transaction_index":1,"log_index":7,"transaction_hash":"0x03","event_type":2,"event_data":{{"index":"18446744073709551617","originNetwork":0,"originAddress":"0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48","destinationAddress":"0x31bc2a964c8cc585ef366e225ea3a5e2a352c287","amount":5}}}}
+        ]"#
+    );
+
+    let mut events: Vec<BridgeEvent> = read_records(json.as_bytes()).unwrap();
+    events.sort_unstable_by_key(BridgeEvent::position);
+
+    match &events[0].event_data {
+        EventData::Claim(claim) => {
+            assert_eq!(claim.global_index, U256::from(u64::MAX) + U256::from(2));
+            assert_eq!(claim.origin_address, address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"));
+        }
+        other => panic!("expected a claim, got {other:?}"),
+    }
+
+    match &events[1].event_data {
+        EventData::UpdateL1InfoTree {
+            mainnet_exit_root,
+            rollup_exit_root,
+        } => {
+            assert_eq!(*mainnet_exit_root, [1; 32]);
+            assert_eq!(*rollup_exit_root, [2; 32]);
+        }
+        other => panic!("expected an exit root update, got {other:?}"),
+    }
+
+    match events.pop().unwrap().event_data {
+        EventData::Deposit(deposit) => {
+            assert_eq!(deposit.deposit_count, 1853);
+
+            let withdrawal: Withdrawal = deposit.into();
+            assert_eq!(
+                withdrawal.dest_address,
+                address!("31bc2a964c8cc585ef366e225ea3a5e2a352c287")
+            );
+        }
+        other => panic!("expected a deposit, got {other:?}"),
+    }
+
+    // Malformed event data names the offending field instead of failing to match any variant
+    let json = r#"[{"removed":false,"block_number":2,"transaction_index":5,"log_index":0,"transaction_hash":"0x02","event_type":1,"event_data":{"mainnetExitRoot":"0x01","rollupExitRoot":"0x02"}}]"#;
+    let err = read_records::<BridgeEvent, _>(json.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("invalid digest `0x01`"), "{err}");
+}
+
+mod logs {
+    use reth_primitives::{hex, B256};
+
+    use super::*;
+    use crate::{
+        ingest::logs::{
+            decode_log, read_logs, LogDecodeError, RawLog, BRIDGE_EVENT_TOPIC, CLAIM_EVENT_TOPIC,
+            LEGACY_CLAIM_EVENT_TOPIC, UPDATE_L1_INFO_TREE_TOPIC, VERIFY_BATCHES_TOPIC,
+            VERIFY_BATCHES_TRUSTED_AGGREGATOR_TOPIC,
+        },
+        keccak::keccak256,
+        LeafType, TokenInfo, UnknownLeafType,
+    };
+
+    fn word(value: impl Into<U256>) -> String {
+        hex::encode(value.into().to_be_bytes::<32>())
+    }
+
+    fn address_word(address: &str) -> String {
+        format!("{:0>64}", address.trim_start_matches("0x"))
+    }
+
+    /// ABI-encodes the first deposit of `DEPOSIT`, with the given leaf type and metadata.
+    fn bridge_event_data(leaf_type: u8, metadata: &[u8]) -> String {
+        let padded_len = metadata.len().div_ceil(32) * 32;
+        let mut padded_metadata = metadata.to_vec();
+        padded_metadata.resize(padded_len, 0);
+
+        [
+            word(U256::from(leaf_type)),
+            word(U256::from(0)),
+            address_word("0x0000000000000000000000000000000000000000"),
+            word(U256::from(1)),
+            address_word("0x31bc2a964c8cc585ef366e225ea3a5e2a352c287"),
+            word(U256::from(11400681687679304_u64)),
+            word(U256::from(8 * 32)),
+            word(U256::from(1853)),
+            word(U256::from(metadata.len())),
+            hex::encode(padded_metadata),
+        ]
+        .concat()
+    }
+
+    fn raw_log(topics: &[B256], data: &str, log_index: u64) -> String {
+        let topics: Vec<String> = topics.iter().map(|topic| format!("\"{topic}\"")).collect();
+
+        format!(
+            r#"{{"address":"0x2a3dd3eb832af982ec71669e178424b10dca2ede","topics":[{}],"data":"0x{data}","blockNumber":"0x1234","transactionHash":"0x{}","transactionIndex":"0x5","logIndex":"{log_index:#x}","removed":false}}"#,
+            topics.join(","),
+            "ab".repeat(32),
+        )
+    }
+
+    #[test]
+    fn test_topics() {
+        for (topic, signature) in [
+            (
+                BRIDGE_EVENT_TOPIC,
+                "BridgeEvent(uint8,uint32,address,uint32,address,uint256,bytes,uint32)",
+            ),
+            (CLAIM_EVENT_TOPIC, "ClaimEvent(uint256,uint32,address,address,uint256)"),
+            (LEGACY_CLAIM_EVENT_TOPIC, "ClaimEvent(uint32,uint32,address,address,uint256)"),
+            (UPDATE_L1_INFO_TREE_TOPIC, "UpdateL1InfoTree(bytes32,bytes32)"),
+            (VERIFY_BATCHES_TOPIC, "VerifyBatches(uint32,uint64,bytes32,bytes32,address)"),
+            (
+                VERIFY_BATCHES_TRUSTED_AGGREGATOR_TOPIC,
+                "VerifyBatchesTrustedAggregator(uint32,uint64,bytes32,bytes32,address)",
+            ),
+        ] {
+            assert_eq!(topic.0, keccak256(signature.as_bytes()), "{signature}");
+        }
+    }
+
+    #[test]
+    fn test_decode_bridge_event() {
+        let json = format!("[{}]", DEPOSIT);
+        let expected: Withdrawal = read_records::<DepositEventData, _>(json.as_bytes())
+            .unwrap()
+            .pop()
+            .unwrap()
+            .into();
+
+        let log: RawLog =
+            serde_json::from_str(&raw_log(&[BRIDGE_EVENT_TOPIC], &bridge_event_data(0, &[]), 3))
+                .unwrap();
+        let event = decode_log(&log).unwrap().unwrap();
+
+        assert_eq!(event.position(), (0x1234, 5, 3));
+        match event.event_data {
+            EventData::Deposit(deposit) => {
+                assert_eq!(deposit.deposit_count, 1853);
+                assert_eq!(Withdrawal::from(deposit).hash(), expected.hash());
+            }
+            other => panic!("expected a deposit, got {other:?}"),
+        }
+
+        // Metadata spanning several words
+        let metadata: Vec<u8> = (0..70).collect();
+        let log: RawLog = serde_json::from_str(&raw_log(
+            &[BRIDGE_EVENT_TOPIC],
+            &bridge_event_data(0, &metadata),
+            3,
+        ))
+        .unwrap();
+        match decode_log(&log).unwrap().unwrap().event_data {
+            EventData::Deposit(deposit) => assert_eq!(deposit.metadata, metadata),
+            other => panic!("expected a deposit, got {other:?}"),
+        }
+
+        // Message, whose origin address is its sender
+        let log: RawLog = serde_json::from_str(&raw_log(
+            &[BRIDGE_EVENT_TOPIC],
+            &bridge_event_data(1, &metadata),
+            3,
+        ))
+        .unwrap();
+        match decode_log(&log).unwrap().unwrap().event_data {
+            EventData::Deposit(deposit) => {
+                assert_eq!(deposit.leaf_type, LeafType::Message);
+                assert_eq!(Withdrawal::from(deposit).balance_token(), TokenInfo::ETH);
+            }
+            other => panic!("expected a deposit, got {other:?}"),
+        }
+
+        let log: RawLog =
+            serde_json::from_str(&raw_log(&[BRIDGE_EVENT_TOPIC], &bridge_event_data(2, &[]), 3))
+                .unwrap();
+        assert!(matches!(
+            decode_log(&log),
+            Err(LogDecodeError::UnknownLeafType(UnknownLeafType(2)))
+        ));
+    }
+
+    #[test]
+    fn test_read_logs() {
+        let mainnet_exit_root = B256::repeat_byte(1);
+        let rollup_exit_root = B256::repeat_byte(2);
+        let claim_data = [
+            word(U256::from(7)),
+            word(U256::from(0)),
+            address_word("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            address_word("0x31bc2a964c8cc585ef366e225ea3a5e2a352c287"),
+            word(U256::from(5)),
+        ]
+        .concat();
+
+        let verify_batches_data = [word(U256::from(42)), "11".repeat(32), "22".repeat(32)].concat();
+        let rollup_id = B256::from(U256::from(3));
+
+        let json = format!(
+            "[{},{},{},{},{}]",
+            raw_log(&[B256::repeat_byte(0xff)], "", 0),
+            raw_log(&[BRIDGE_EVENT_TOPIC], &bridge_event_data(0, &[]), 1),
+            raw_log(&[LEGACY_CLAIM_EVENT_TOPIC], &claim_data, 2),
+            raw_log(&[UPDATE_L1_INFO_TREE_TOPIC, mainnet_exit_root, rollup_exit_root], "", 3),
+            raw_log(
+                &[VERIFY_BATCHES_TRUSTED_AGGREGATOR_TOPIC, rollup_id, B256::ZERO],
+                &verify_batches_data,
+                4
+            ),
+        );
+
+        let events = read_logs(json.as_bytes()).unwrap();
+        assert_eq!(events.len(), 4);
+        // Same numbering as the event dumps
+        let event_types: Vec<u8> = events.iter().map(|event| event.event_type).collect();
+        assert_eq!(event_types, [0, 2, 1, 3]);
+        assert!(matches!(events[0].event_data, EventData::Deposit(_)));
+        assert!(matches!(
+            &events[1].event_data,
+            EventData::Claim(claim) if claim.global_index == U256::from(7)
+        ));
+        assert!(matches!(
+            events[2].event_data,
+            EventData::UpdateL1InfoTree { mainnet_exit_root: m, rollup_exit_root: r }
+                if m == mainnet_exit_root.0 && r == rollup_exit_root.0
+        ));
+        assert!(matches!(
+            events[3].event_data,
+            EventData::VerifyBatches { rollup_id: 3, exit_root } if exit_root == [0x22; 32]
+        ));
+
+        // Dirty padding in the `uint32` destination network
+        let dirty = bridge_event_data(0, &[]).replacen(&word(U256::from(1)), &"11".repeat(32), 1);
+        let json = format!(
+            "[{},{}]",
+            raw_log(&[BRIDGE_EVENT_TOPIC], &bridge_event_data(0, &[]), 0),
+            raw_log(&[BRIDGE_EVENT_TOPIC], &dirty, 1),
+        );
+        let err = read_logs(json.as_bytes()).unwrap_err();
+        assert!(matches!(err, IngestError::InvalidLog { index: 1, .. }), "{err}");
+
+        // Missing indexed exit roots
+        let json = format!("[{}]", raw_log(&[UPDATE_L1_INFO_TREE_TOPIC], "", 0));
+        let err = read_logs(json.as_bytes()).unwrap_err();
+        assert!(matches!(err, IngestError::InvalidLog { index: 0, .. }), "{err}");
+    }
+}
+
+mod calldata {
+    use reth_primitives::{hex, Address};
+
+    use super::*;
+    use crate::{
+        ingest::calldata::{
+            BridgeContext, CalldataError, GasToken, BRIDGE_ASSET_SELECTOR, BRIDGE_MESSAGE_SELECTOR,
+            BRIDGE_MESSAGE_WETH_SELECTOR,
+        },
+        keccak::keccak256,
+        LeafType, TokenInfo,
+    };
+
+    const SENDER: Address = address!("5561134ff5b24700d1f8d45fc59f73d023effeb2");
+    const RECIPIENT: Address = address!("31bc2a964c8cc585ef366e225ea3a5e2a352c287");
+    const USDC: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    const WRAPPED: Address = address!("1111111111111111111111111111111111111111");
+    const WETH: Address = address!("2222222222222222222222222222222222222222");
+
+    fn word(value: impl Into<U256>) -> Vec<u8> {
+        value.into().to_be_bytes::<32>().to_vec()
+    }
+
+    fn address_word(address: Address) -> Vec<u8> {
+        U256::from_be_slice(address.as_slice()).to_be_bytes::<32>().to_vec()
+    }
+
+    /// Encodes `bytes` as the tail of an ABI-encoded call.
+    fn bytes_tail(bytes: &[u8]) -> Vec<u8> {
+        let mut tail = word(U256::from(bytes.len()));
+        tail.extend_from_slice(bytes);
+        tail.resize(32 + bytes.len().div_ceil(32) * 32, 0);
+        tail
+    }
+
+    fn bridge_asset(dest_network: u32, amount: u64, token: Address) -> Vec<u8> {
+        [
+            BRIDGE_ASSET_SELECTOR.to_vec(),
+            word(U256::from(dest_network)),
+            address_word(RECIPIENT),
+            word(U256::from(amount)),
+            address_word(token),
+            word(U256::from(1)),
+            word(U256::from(6 * 32)),
+            bytes_tail(&[]),
+        ]
+        .concat()
+    }
+
+    fn bridge_message(metadata: &[u8]) -> Vec<u8> {
+        [
+            BRIDGE_MESSAGE_SELECTOR.to_vec(),
+            word(U256::from(0)),
+            address_word(RECIPIENT),
+            word(U256::from(0)),
+            word(U256::from(4 * 32)),
+            bytes_tail(metadata),
+        ]
+        .concat()
+    }
+
+    fn context() -> BridgeContext {
+        let mut context = BridgeContext::new(1.into());
+        context.wrapped_tokens.insert(
+            WRAPPED,
+            TokenInfo {
+                origin_network: 0.into(),
+                origin_token_address: USDC,
+            },
+        );
+        context.token_metadata.insert(SENDER, vec![0xaa; 96]);
+
+        context
+    }
+
+    #[test]
+    fn test_selectors() {
+        for (selector, signature) in [
+            (BRIDGE_ASSET_SELECTOR, "bridgeAsset(uint32,address,uint256,address,bool,bytes)"),
+            (BRIDGE_MESSAGE_SELECTOR, "bridgeMessage(uint32,address,bool,bytes)"),
+            (
+                BRIDGE_MESSAGE_WETH_SELECTOR,
+                "bridgeMessageWETH(uint32,address,uint256,bool,bytes)",
+            ),
+        ] {
+            assert_eq!(selector, keccak256(signature.as_bytes())[..4], "{signature}");
+        }
+    }
+
+    #[test]
+    fn test_bridge_asset() {
+        let context = context();
+        let value = U256::from(10);
+
+        // ETH bridged back to mainnet
+        let eth = context
+            .decode_calldata(SENDER, value, &bridge_asset(0, 10, Address::ZERO))
+            .unwrap();
+        assert_eq!(eth.leaf_type, LeafType::Asset);
+        assert_eq!(*eth.token_info.origin_network, 0);
+        assert_eq!(eth.token_info.origin_token_address, Address::ZERO);
+        assert_eq!(eth.dest_address, RECIPIENT);
+        assert_eq!(eth.amount, value);
+        assert!(eth.metadata.is_empty());
+
+        // Wrapped tokens are bridged back with their origin info
+        let wrapped = context
+            .decode_calldata(SENDER, U256::ZERO, &bridge_asset(0, 7, WRAPPED))
+            .unwrap();
+        assert_eq!(wrapped.token_info, context.wrapped_tokens[&WRAPPED]);
+        assert!(wrapped.metadata.is_empty());
+
+        // Native tokens carry their metadata
+        let native = context
+            .decode_calldata(SENDER, U256::ZERO, &bridge_asset(0, 7, SENDER))
+            .unwrap();
+        assert_eq!(*native.token_info.origin_network, 1);
+        assert_eq!(native.metadata, vec![0xaa; 96]);
+
+        assert_eq!(
+            context
+                .decode_calldata(SENDER, U256::ZERO, &bridge_asset(0, 7, USDC))
+                .unwrap_err(),
+            CalldataError::MissingTokenMetadata(USDC)
+        );
+        assert_eq!(
+            context
+                .decode_calldata(SENDER, value, &bridge_asset(0, 7, WRAPPED))
+                .unwrap_err(),
+            CalldataError::MsgValueNotZero(value)
+        );
+        assert!(matches!(
+            context.decode_calldata(SENDER, value, &bridge_asset(0, 9, Address::ZERO)),
+            Err(CalldataError::AmountDoesNotMatchMsgValue { .. })
+        ));
+        assert_eq!(
+            context
+                .decode_calldata(SENDER, value, &bridge_asset(1, 10, Address::ZERO))
+                .unwrap_err(),
+            CalldataError::DestinationNetworkInvalid(1.into())
+        );
+    }
+
+    #[test]
+    fn test_custom_gas_token() {
+        let gas_token = TokenInfo {
+            origin_network: 0.into(),
+            origin_token_address: USDC,
+        };
+        let mut context = context();
+        context.gas_token = Some(GasToken {
+            token_info: gas_token.clone(),
+            metadata: vec![1, 2, 3],
+        });
+        context.weth_token = Some(WETH);
+
+        let native = context
+            .decode_calldata(SENDER, U256::from(10), &bridge_asset(0, 10, Address::ZERO))
+            .unwrap();
+        assert_eq!(native.token_info, gas_token);
+        assert_eq!(native.metadata, vec![1, 2, 3]);
+
+        let weth = context.decode_calldata(SENDER, U256::ZERO, &bridge_asset(0, 10, WETH)).unwrap();
+        assert_eq!(weth.token_info.origin_token_address, Address::ZERO);
+
+        assert_eq!(
+            context.decode_calldata(SENDER, U256::ZERO, &bridge_message(&[])).unwrap_err(),
+            CalldataError::NativeTokenIsNotEther
+        );
+    }
+
+    #[test]
+    fn test_bridge_message() {
+        let context = context();
+        let metadata = hex::decode("deadbeef").unwrap();
+
+        let message = context
+            .decode_calldata(SENDER, U256::from(3), &bridge_message(&metadata))
+            .unwrap();
+        assert_eq!(message.leaf_type, LeafType::Message);
+        assert_eq!(*message.token_info.origin_network, 1);
+        assert_eq!(message.token_info.origin_token_address, SENDER);
+        assert_eq!(message.amount, U256::from(3));
+        assert_eq!(message.metadata, metadata);
+
+        let mut truncated = bridge_message(&metadata);
+        truncated.truncate(4 + 3 * 32);
+        assert!(matches!(
+            context.decode_calldata(SENDER, U256::ZERO, &truncated),
+            Err(CalldataError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            context.decode_calldata(SENDER, U256::ZERO, &[0x12, 0x34]),
+            Err(CalldataError::UnknownSelector(_))
+        ));
+    }
+}
+
+mod stream {
+    use super::*;
+    use crate::{
+        ingest::stream::{MergeSorted, RecordReader},
+        local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    };
+
+    /// A deposit event at the given position, with a metadata containing JSON delimiters.
+    fn event(block_number: u64, log_index: u64, deposit_count: u32) -> String {
+        format!(
+            r#"{{"removed":false,"block_number":{block_number},"transaction_index":0,"log_index":{log_index},"transaction_hash":"0x\"]}},","event_type":0,"event_data":{}}}"#,
+            DEPOSIT.replace("1853", &deposit_count.to_string())
+        )
+    }
+
+    fn positions(events: impl Iterator<Item = Result<BridgeEvent, IngestError>>) -> Vec<u64> {
+        events.map(|event| event.unwrap().block_number).collect()
+    }
+
+    #[test]
+    fn test_layouts() {
+        let events: Vec<String> = (0..4).map(|i| event(i, 0, i as u32)).collect();
+
+        let array = format!("  [\n{}\n]\n", events.join(" ,\n"));
+        let lines = events.join("\n") + "\n";
+
+        for input in [array, lines] {
+            let reader = RecordReader::<_, BridgeEvent>::new(input.as_bytes());
+            assert_eq!(positions(reader), vec![0, 1, 2, 3]);
+        }
+
+        assert_eq!(RecordReader::<_, BridgeEvent>::new(&b" [ ] "[..]).count(), 0);
+        assert_eq!(RecordReader::<_, BridgeEvent>::new(&b""[..]).count(), 0);
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        let valid = event(0, 0, 0);
+        let invalid = event(1, 0, 1).replace("\"removed\":false", "\"removed\":0");
+
+        for (input, expected_index) in [
+            (format!("[{valid},{invalid}]"), 1),
+            (format!("[{valid} {valid}]"), 1),
+            (format!("[{valid},,{valid}]"), 1),
+            (format!("[{valid},"), 1),
+            (format!("[{valid}] {valid}"), 1),
+            (format!("{valid}\n{{\"removed\":"), 1),
+        ] {
+            let results: Vec<_> = RecordReader::<_, BridgeEvent>::new(input.as_bytes()).collect();
+
+            assert_eq!(results.len(), 2, "{input}");
+            match results[1].as_ref().unwrap_err() {
+                IngestError::InvalidRecord { index, .. }
+                | IngestError::InvalidFormat { index, .. } => {
+                    assert_eq!(*index, expected_index, "{input}")
+                }
+                other => panic!("unexpected error {other}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge_sorted() {
+        let chunk = |events: &[(u64, u64)]| -> String {
+            let events: Vec<String> = events.iter().map(|(b, l)| event(*b, *l, 0)).collect();
+            format!("[{}]", events.join(","))
+        };
+
+        let chunks =
+            [chunk(&[(1, 0), (4, 0), (4, 2)]), chunk(&[]), chunk(&[(0, 0), (4, 1), (9, 0)])];
+        let readers = chunks
+            .iter()
+            .map(|chunk| RecordReader::<_, BridgeEvent>::new(chunk.as_bytes()))
+            .collect();
+
+        let merged: Vec<_> =
+            MergeSorted::new(readers).map(|event| event.unwrap().position()).collect();
+        assert_eq!(merged, vec![(0, 0, 0), (1, 0, 0), (4, 0, 0), (4, 0, 1), (4, 0, 2), (9, 0, 0)]);
+
+        let chunks = [chunk(&[(1, 0), (3, 0)]), chunk(&[(2, 0), (0, 0)])];
+        let readers = chunks
+            .iter()
+            .map(|chunk| RecordReader::<_, BridgeEvent>::new(chunk.as_bytes()))
+            .collect();
+
+        let merged: Vec<_> = MergeSorted::new(readers).collect();
+        assert!(matches!(
+            merged.last(),
+            Some(Err(IngestError::UnsortedInput { chunk: 1, index: 1 }))
+        ));
+
+        // Only the error of the first failing chunk is reported
+        let failing = |reason: &'static str| {
+            vec![Err(IngestError::InvalidFormat { index: 0, reason })].into_iter()
+        };
+        let merged: Vec<_> = MergeSorted::new(vec![failing("first"), failing("second")]).collect();
+        assert!(matches!(
+            merged.as_slice(),
+            [Err(IngestError::InvalidFormat {
+                reason: "first",
+                ..
+            })]
+        ));
+    }
+
+    #[test]
+    fn test_streamed_local_exit_tree() {
+        let events: Vec<String> = (0..100).map(|i| event(i, 0, i as u32)).collect();
+        let input = events.join("\n");
+
+        let in_memory: LocalExitTree<Keccak256Hasher> = LocalExitTree::from_leaves(
+            read_records::<BridgeEvent, _>(format!("[{}]", events.join(",")).as_bytes())
+                .unwrap()
+                .into_iter()
+                .filter_map(|event| match event.event_data {
+                    EventData::Deposit(deposit) => Some(Withdrawal::from(deposit).hash()),
+                    _ => None,
+                }),
+        );
+
+        let mut streamed: LocalExitTree<Keccak256Hasher> = LocalExitTree::new();
+        for event in RecordReader::<_, BridgeEvent>::new(input.as_bytes()) {
+            if let EventData::Deposit(deposit) = event.unwrap().event_data {
+                streamed.add_leaf(Withdrawal::from(deposit).hash());
+            }
+        }
+
+        assert_eq!(streamed.get_root(), in_memory.get_root());
+    }
+}
@@ -0,0 +1,384 @@
+//! Reconciliation of the value in flight between networks.
+//!
+//! [`BalanceTreeByNetwork::transfer`](crate::local_balance_tree::BalanceTreeByNetwork::transfer)
+//! credits the destination network as soon as a withdrawal is proven, whether or not it is ever
+//! claimed there. The [`ReconciliationLedger`] records each proven withdrawal as a pending exit of
+//! its corridor, the `(origin, destination, token)` triple, and moves it to settled once the
+//! destination network imports the matching claim, so that operators can see the unclaimed value
+//! of each corridor.
+//!
+//! The proof commits the hash of the [`CorridorFlows`] of its batches, so that the exits recorded
+//! for an epoch can be checked against its proof, and the ledger itself has a root covering the
+//! pending and settled amounts of every corridor.
+
+use std::{collections::BTreeMap, fmt};
+
+use reth_primitives::U256;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::{
+    batch::Batch,
+    ingest::ClaimEventData,
+    keccak::{keccak256_combine, Digest},
+    registry::NetworkRegistry,
+    NetworkId, TokenInfo,
+};
+
+/// The network whose claims have the mainnet flag set in their global index.
+const MAINNET: NetworkId = NetworkId::new(0);
+
+/// Bit of the global index set on the claims of withdrawals from mainnet.
+const MAINNET_FLAG_BIT: usize = 64;
+
+/// The flow of a token from an origin network to a destination network.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Corridor {
+    pub origin_network: NetworkId,
+    pub dest_network: NetworkId,
+    pub token: TokenInfo,
+}
+
+impl Corridor {
+    /// Computes the Keccak digest of the [`Corridor`].
+    pub fn hash(&self) -> Digest {
+        keccak256_combine([
+            self.origin_network.to_be_bytes().as_slice(),
+            self.dest_network.to_be_bytes().as_slice(),
+            self.token.hash().as_slice(),
+        ])
+    }
+}
+
+/// The amount withdrawn through each corridor.
+///
+/// Serialized as a sequence of `(corridor, amount)` pairs, as [`Corridor`] is not a valid JSON
+/// key.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorridorFlows(#[serde_as(as = "Vec<(_, _)>")] BTreeMap<Corridor, U256>);
+
+impl CorridorFlows {
+    /// Sums the `(origin network, destination network, token, amount)` transfers per corridor.
+    pub fn from_transfers(
+        transfers: impl IntoIterator<Item = (NetworkId, NetworkId, TokenInfo, U256)>,
+    ) -> Self {
+        let mut flows = BTreeMap::new();
+        for (origin_network, dest_network, token, amount) in transfers {
+            let corridor = Corridor {
+                origin_network,
+                dest_network,
+                token,
+            };
+            let total: &mut U256 = flows.entry(corridor).or_default();
+            *total = total.saturating_add(amount);
+        }
+
+        Self(flows)
+    }
+
+    /// Sums the withdrawals of the batches per corridor.
+    pub fn from_batches<'a>(batches: impl IntoIterator<Item = &'a Batch>) -> Self {
+        Self::from_transfers(batches.into_iter().flat_map(|batch| {
+            batch.withdrawals.iter().map(|withdrawal| {
+                (
+                    batch.origin_network,
+                    withdrawal.dest_network,
+                    withdrawal.balance_token(),
+                    withdrawal.amount,
+                )
+            })
+        }))
+    }
+
+    /// Iterates over the flows, in corridor order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Corridor, U256)> {
+        self.0.iter().map(|(corridor, amount)| (corridor, *amount))
+    }
+
+    /// Computes the hash of the flows committed by the proof.
+    pub fn hash(&self) -> Digest {
+        let entries: Vec<[u8; 64]> = self
+            .iter()
+            .map(|(corridor, amount)| {
+                let mut entry = [0; 64];
+                entry[..32].copy_from_slice(&corridor.hash());
+                entry[32..].copy_from_slice(&amount.to_be_bytes::<32>());
+                entry
+            })
+            .collect();
+
+        keccak256_combine(entries.iter().map(|entry| entry.as_slice()))
+    }
+}
+
+/// A claim imported by its destination network, identifying the withdrawal it claims.
+///
+/// As in the leaves, the token of the claim of a message is its sender.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claim {
+    /// Network which emitted the claimed withdrawal
+    pub origin_network: NetworkId,
+    /// Index of the claimed withdrawal in the local exit tree of its origin network
+    pub deposit_count: u32,
+    /// Network on which the withdrawal is claimed
+    pub dest_network: NetworkId,
+    pub token: TokenInfo,
+    pub amount: U256,
+}
+
+impl Claim {
+    /// Creates the [`Claim`] of a `ClaimEvent` emitted by the bridge of `dest_network`.
+    ///
+    /// The global index holds the deposit count in its lowest 32 bits, and either the mainnet flag
+    /// or the rollup index above, which is resolved to the network registered with it in
+    /// `registry`. The index of legacy events is only a deposit count, so their claims are
+    /// attributed to the rollup index 0, unless that is `dest_network` itself: as a network never
+    /// claims its own withdrawals, a legacy event emitted on the rollup of index 0 claims a
+    /// withdrawal of mainnet.
+    pub fn from_event(
+        registry: &NetworkRegistry,
+        dest_network: NetworkId,
+        event: &ClaimEventData,
+    ) -> Result<Self, LedgerError> {
+        let limbs = event.global_index.as_limbs();
+        let deposit_count = limbs[0] as u32;
+        let origin_network = if event.global_index.bit(MAINNET_FLAG_BIT) {
+            MAINNET
+        } else {
+            let rollup_index = (limbs[0] >> 32) as u32;
+            match registry.network_of_rollup(rollup_index) {
+                Some(network) if network == dest_network => MAINNET,
+                Some(network) => network,
+                None => return Err(LedgerError::UnknownRollup { rollup_index }),
+            }
+        };
+
+        Ok(Self {
+            origin_network,
+            deposit_count,
+            dest_network,
+            token: TokenInfo {
+                origin_network: event.origin_network.into(),
+                origin_token_address: event.origin_address,
+            },
+            amount: event.amount,
+        })
+    }
+}
+
+/// Represents all the reasons for refusing a claim.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LedgerError {
+    /// No pending exit matches the claim, as it is not proven yet or was already claimed.
+    UnknownExit {
+        origin_network: NetworkId,
+        deposit_count: u32,
+    },
+    /// The claim does not match the destination, token or amount of the exit.
+    MismatchedClaim {
+        origin_network: NetworkId,
+        deposit_count: u32,
+    },
+    /// The global index of the claim points to a rollup index with no registered network.
+    UnknownRollup { rollup_index: u32 },
+    /// The exit was already recorded, whether or not it was claimed since.
+    AlreadyRecorded {
+        origin_network: NetworkId,
+        deposit_count: u32,
+    },
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::UnknownExit {
+                origin_network,
+                deposit_count,
+            } => write!(f, "no pending exit {deposit_count} from network {}", **origin_network),
+            LedgerError::MismatchedClaim {
+                origin_network,
+                deposit_count,
+            } => write!(
+                f,
+                "the claim does not match the exit {deposit_count} from network {}",
+                **origin_network
+            ),
+            LedgerError::UnknownRollup { rollup_index } => {
+                write!(f, "no network registered with the rollup index {rollup_index}")
+            }
+            LedgerError::AlreadyRecorded {
+                origin_network,
+                deposit_count,
+            } => write!(
+                f,
+                "the exit {deposit_count} from network {} is already recorded",
+                **origin_network
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+/// A proven withdrawal which is not claimed yet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingExit {
+    /// Corridor of the withdrawal, whose token is the one whose balance it moves
+    pub corridor: Corridor,
+    /// Token of the leaf, which is the sender of messages, as referred to by the claims
+    pub leaf_token: TokenInfo,
+    pub amount: U256,
+    /// Epoch in which the withdrawal was proven
+    pub epoch: u64,
+}
+
+/// The amounts in flight and claimed through a corridor.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorridorBalance {
+    /// Amount withdrawn and not claimed yet
+    pub pending: U256,
+    /// Amount withdrawn and claimed
+    pub settled: U256,
+    /// Number of withdrawals not claimed yet
+    pub pending_exits: u64,
+}
+
+/// The pending and settled exits of each corridor.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReconciliationLedger {
+    /// Pending exits, by origin network and deposit count
+    #[serde_as(as = "Vec<(_, _)>")]
+    exits: BTreeMap<(NetworkId, u32), PendingExit>,
+    #[serde_as(as = "Vec<(_, _)>")]
+    corridors: BTreeMap<Corridor, CorridorBalance>,
+    /// Deposit count following the last recorded exit of each network
+    #[serde(default)]
+    recorded: BTreeMap<NetworkId, u32>,
+}
+
+impl ReconciliationLedger {
+    /// Creates a new empty [`ReconciliationLedger`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the withdrawals of the batches proven in `epoch` as pending exits, and returns their
+    /// flows, whose hash the proof of the epoch commits.
+    ///
+    /// Fails without recording anything if an exit of the batches was already recorded, so that
+    /// its amount is not counted twice.
+    pub fn record<'a>(
+        &mut self,
+        epoch: u64,
+        batches: impl IntoIterator<Item = &'a Batch> + Clone,
+    ) -> Result<CorridorFlows, LedgerError> {
+        let mut recorded = self.recorded.clone();
+        for batch in batches.clone() {
+            if batch.withdrawals.is_empty() {
+                continue;
+            }
+
+            let first_deposit_count = batch.prev_local_exit_tree.leaf_count();
+            let next_deposit_count = recorded.entry(batch.origin_network).or_default();
+            if first_deposit_count < *next_deposit_count {
+                return Err(LedgerError::AlreadyRecorded {
+                    origin_network: batch.origin_network,
+                    deposit_count: first_deposit_count,
+                });
+            }
+            *next_deposit_count = first_deposit_count + batch.withdrawals.len() as u32;
+        }
+        self.recorded = recorded;
+
+        for batch in batches.clone() {
+            let first_deposit_count = batch.prev_local_exit_tree.leaf_count();
+            for (deposit_count, withdrawal) in (first_deposit_count..).zip(&batch.withdrawals) {
+                let corridor = Corridor {
+                    origin_network: batch.origin_network,
+                    dest_network: withdrawal.dest_network,
+                    token: withdrawal.balance_token(),
+                };
+
+                let balance = self.corridors.entry(corridor.clone()).or_default();
+                balance.pending = balance.pending.saturating_add(withdrawal.amount);
+                balance.pending_exits += 1;
+
+                self.exits.insert(
+                    (batch.origin_network, deposit_count),
+                    PendingExit {
+                        corridor,
+                        leaf_token: withdrawal.token_info.clone(),
+                        amount: withdrawal.amount,
+                        epoch,
+                    },
+                );
+            }
+        }
+
+        Ok(CorridorFlows::from_batches(batches))
+    }
+
+    /// Moves the exit matching `claim` from pending to settled.
+    pub fn settle(&mut self, claim: &Claim) -> Result<(), LedgerError> {
+        let id = (claim.origin_network, claim.deposit_count);
+        let exit = self.exits.get(&id).ok_or(LedgerError::UnknownExit {
+            origin_network: claim.origin_network,
+            deposit_count: claim.deposit_count,
+        })?;
+
+        if exit.corridor.dest_network != claim.dest_network
+            || exit.leaf_token != claim.token
+            || exit.amount != claim.amount
+        {
+            return Err(LedgerError::MismatchedClaim {
+                origin_network: claim.origin_network,
+                deposit_count: claim.deposit_count,
+            });
+        }
+
+        let exit = self.exits.remove(&id).expect("the exit was found above");
+        let balance = self
+            .corridors
+            .get_mut(&exit.corridor)
+            .expect("every pending exit has a corridor");
+        balance.pending = balance.pending.saturating_sub(exit.amount);
+        balance.settled = balance.settled.saturating_add(exit.amount);
+        balance.pending_exits -= 1;
+
+        Ok(())
+    }
+
+    /// Returns the pending exit of `origin_network` with the given deposit count, if any.
+    pub fn pending_exit(
+        &self,
+        origin_network: NetworkId,
+        deposit_count: u32,
+    ) -> Option<&PendingExit> {
+        self.exits.get(&(origin_network, deposit_count))
+    }
+
+    /// Iterates over the balance of every corridor, in corridor order.
+    pub fn corridors(&self) -> impl Iterator<Item = (&Corridor, &CorridorBalance)> {
+        self.corridors.iter()
+    }
+
+    /// Computes the root of the ledger, covering the pending and settled amounts of every
+    /// corridor.
+    pub fn root(&self) -> Digest {
+        let entries: Vec<[u8; 96]> = self
+            .corridors
+            .iter()
+            .map(|(corridor, balance)| {
+                let mut entry = [0; 96];
+                entry[..32].copy_from_slice(&corridor.hash());
+                entry[32..64].copy_from_slice(&balance.pending.to_be_bytes::<32>());
+                entry[64..].copy_from_slice(&balance.settled.to_be_bytes::<32>());
+                entry
+            })
+            .collect();
+
+        keccak256_combine(entries.iter().map(|entry| entry.as_slice()))
+    }
+}
@@ -1,13 +1,40 @@
+/// Version of this crate, recorded in proof artifacts.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub mod certificate;
+pub mod committee;
+pub mod compact;
+pub mod context;
+
+pub mod cost;
+pub use cost::estimate_cost;
+
+pub mod credit;
+pub mod cycles;
+pub mod digest;
+pub mod ingest;
 pub mod keccak;
+pub mod ledger;
 pub mod local_exit_tree;
+pub mod pause;
 
 mod proof;
-pub use proof::{generate_full_proof, ProofError};
+pub use proof::{
+    generate_certified_proof, generate_certified_proof_compact, generate_full_proof,
+    generate_full_proof_compact, BalanceRoot, ExitRoot, FullProofOutput, ProofError, ProofOutput,
+};
+
+pub mod rate_limit;
+pub mod registry;
+pub mod replay;
+pub mod split;
+pub mod supply;
+pub mod token_amounts;
 
 pub mod test_utils;
 
 mod withdrawal;
-pub use withdrawal::{NetworkId, TokenInfo, Withdrawal};
+pub use withdrawal::{LeafType, NetworkId, TokenInfo, UnknownLeafType, Withdrawal};
 
 pub mod batch;
 
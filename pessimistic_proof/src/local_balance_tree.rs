@@ -5,10 +5,13 @@ use std::{
 
 use reth_primitives::U256;
 use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 use tiny_keccak::{Hasher, Keccak};
 
 use crate::{
+    credit::CreditLines,
     keccak::Digest,
+    pause::{PauseError, PauseList},
     withdrawal::{NetworkId, TokenInfo},
     Withdrawal,
 };
@@ -29,19 +32,46 @@ impl BalanceTreeByNetwork {
         Self(BTreeMap::new())
     }
 
-    /// Updates the origin and destination network in the aggregate from a [`Withdrawal`].
-    pub fn insert(&mut self, origin_network: NetworkId, withdrawal: Withdrawal) {
+    /// Updates the origin and destination network in the aggregate from a [`Withdrawal`], moving
+    /// its [`Withdrawal::balance_token`].
+    ///
+    /// Fails if the origin network or the token is in `paused`.
+    pub fn insert(
+        &mut self,
+        origin_network: NetworkId,
+        withdrawal: Withdrawal,
+        paused: &PauseList,
+    ) -> Result<(), PauseError> {
+        self.transfer(
+            origin_network,
+            withdrawal.dest_network,
+            withdrawal.balance_token(),
+            withdrawal.amount,
+            paused,
+        )
+    }
+
+    /// Updates the origin and destination network in the aggregate from a transfer of `amount`
+    /// of `token_info`.
+    ///
+    /// Fails if the origin network or the token is in `paused`.
+    pub fn transfer(
+        &mut self,
+        origin_network: NetworkId,
+        dest_network: NetworkId,
+        token_info: TokenInfo,
+        amount: U256,
+        paused: &PauseList,
+    ) -> Result<(), PauseError> {
+        paused.check(origin_network, &token_info)?;
+
         // Withdraw the origin network
-        self.0
-            .entry(origin_network)
-            .or_default()
-            .withdraw(withdrawal.token_info.clone(), withdrawal.amount);
+        self.0.entry(origin_network).or_default().withdraw(token_info.clone(), amount);
 
         // Deposit the destination network
-        self.0
-            .entry(withdrawal.dest_network)
-            .or_default()
-            .deposit(withdrawal.token_info, withdrawal.amount);
+        self.0.entry(dest_network).or_default().deposit(token_info, amount);
+
+        Ok(())
     }
 
     /// Merge two [`BalanceTreeByNetwork`].
@@ -89,7 +119,7 @@ impl DerefMut for BalanceTreeByNetwork {
 }
 
 /// Record the balance as total deposit and total withdraw.
-#[derive(Default, Clone, Serialize, Deserialize, Debug)]
+#[derive(Default, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub struct Balance {
     deposit: U256,
     withdraw: U256,
@@ -117,10 +147,30 @@ impl From<Withdraw> for Balance {
 }
 
 impl Balance {
+    /// Creates a new [`Balance`] from the total deposit and the total withdraw.
+    pub fn new(deposit: U256, withdraw: U256) -> Self {
+        Self { deposit, withdraw }
+    }
+
+    /// Returns the total deposit.
+    pub fn deposited(&self) -> U256 {
+        self.deposit
+    }
+
+    /// Returns the total withdraw.
+    pub fn withdrawn(&self) -> U256 {
+        self.withdraw
+    }
+
     pub fn is_negative(&self) -> bool {
         self.withdraw > self.deposit
     }
 
+    /// Returns the amount withdrawn beyond the amount deposited, zero if not negative.
+    pub fn overdraft(&self) -> U256 {
+        self.withdraw.saturating_sub(self.deposit)
+    }
+
     pub fn deposit(&mut self, amount: U256) {
         self.deposit += amount;
     }
@@ -142,8 +192,11 @@ impl Balance {
 }
 
 /// Records the balances for each [`TokenInfo`].
+///
+/// Serialized as a sequence of `(token, balance)` pairs, as [`TokenInfo`] is not a valid JSON key.
+#[serde_as]
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
-pub struct BalanceTree(BTreeMap<TokenInfo, Balance>);
+pub struct BalanceTree(#[serde_as(as = "Vec<(_, _)>")] BTreeMap<TokenInfo, Balance>);
 
 impl From<Vec<(TokenInfo, Balance)>> for BalanceTree {
     fn from(initial_balance: Vec<(TokenInfo, Balance)>) -> Self {
@@ -151,6 +204,14 @@ impl From<Vec<(TokenInfo, Balance)>> for BalanceTree {
     }
 }
 
+impl Deref for BalanceTree {
+    type Target = BTreeMap<TokenInfo, Balance>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl BalanceTree {
     /// Apply deposit to the given [`TokenInfo`].
     pub fn deposit(&mut self, token: TokenInfo, amount: U256) {
@@ -170,10 +231,17 @@ impl BalanceTree {
         }
     }
 
-    /// Returns whether any token has debt.
+    /// Returns whether any token has debt, that is, is overdrawn by `network` beyond its credit
+    /// line.
     /// TODO: We may want to return the debtor (token, debt)
-    pub fn has_debt(&self) -> bool {
-        self.0.iter().any(|(_, balance)| balance.is_negative())
+    pub fn has_debt(&self, network: NetworkId, credit_lines: &CreditLines) -> bool {
+        if credit_lines.is_empty() {
+            return self.0.iter().any(|(_, balance)| balance.is_negative());
+        }
+
+        self.0.iter().any(|(token, balance)| {
+            balance.overdraft() > credit_lines.get(network, token).unwrap_or_default()
+        })
     }
 
     /// Returns the hash of [`BalanceTree`].
@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use super::{get_bit_at, hasher::Hasher, LocalExitTree};
+
+/// A Merkle path from a leaf to the root of a [`LocalExitTree`], as needed to claim the leaf.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleProof<H, const TREE_DEPTH: usize = 32>
+where
+    H: Hasher,
+    H::Digest: Serialize + for<'a> Deserialize<'a>,
+{
+    /// The index of the leaf.
+    pub leaf_index: u32,
+    /// The siblings of the nodes on the path, from the leaf up.
+    #[serde_as(as = "[_; TREE_DEPTH]")]
+    pub siblings: [H::Digest; TREE_DEPTH],
+}
+
+impl<H, const TREE_DEPTH: usize> MerkleProof<H, TREE_DEPTH>
+where
+    H: Hasher,
+    H::Digest: Copy + PartialEq + Serialize + for<'a> Deserialize<'a>,
+{
+    /// Computes the root of the tree holding `leaf` at the index of the path.
+    pub fn compute_root(&self, leaf: H::Digest) -> H::Digest {
+        let mut node = leaf;
+
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            node = if get_bit_at(self.leaf_index, height) == 1 {
+                H::merge(sibling, &node)
+            } else {
+                H::merge(&node, sibling)
+            };
+        }
+
+        node
+    }
+
+    /// Returns whether the path leads from `leaf` to `root`.
+    pub fn verify(&self, leaf: H::Digest, root: H::Digest) -> bool {
+        self.compute_root(leaf) == root
+    }
+}
+
+/// A [`LocalExitTree`] which keeps the leaves added to it, so as to provide their Merkle paths.
+///
+/// The tree may start from the frontier of an existing tree, in which case only the paths of the
+/// leaves added afterwards can be provided.
+///
+/// The internal nodes of the complete subtrees are kept as well, so that a path takes a number of
+/// hashes quadratic in the depth of the tree rather than linear in its leaf count. They are not
+/// serialized, but recomputed from the leaves.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(
+    bound(serialize = "", deserialize = "H::Digest: Copy + Default"),
+    from = "LocalExitTreeDataParts<H, TREE_DEPTH>"
+)]
+pub struct LocalExitTreeData<H, const TREE_DEPTH: usize = 32>
+where
+    H: Hasher,
+    H::Digest: Serialize + for<'a> Deserialize<'a>,
+{
+    /// The tree before the first kept leaf.
+    base: LocalExitTree<H, TREE_DEPTH>,
+    /// The leaves added after `base`.
+    leaves: Vec<H::Digest>,
+    /// The tree holding all the leaves.
+    tree: LocalExitTree<H, TREE_DEPTH>,
+    /// The nodes of the complete subtrees at each height from 1, which end after the leaves of
+    /// `base`, in index order.
+    #[serde(skip_serializing)]
+    nodes: Vec<Vec<H::Digest>>,
+}
+
+/// The serialized parts of a [`LocalExitTreeData`], from which it is rebuilt.
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct LocalExitTreeDataParts<H, const TREE_DEPTH: usize>
+where
+    H: Hasher,
+    H::Digest: Serialize + for<'a> Deserialize<'a>,
+{
+    base: LocalExitTree<H, TREE_DEPTH>,
+    leaves: Vec<H::Digest>,
+}
+
+impl<H, const TREE_DEPTH: usize> From<LocalExitTreeDataParts<H, TREE_DEPTH>>
+    for LocalExitTreeData<H, TREE_DEPTH>
+where
+    H: Hasher,
+    H::Digest: Copy + Default + Serialize + for<'a> Deserialize<'a>,
+{
+    fn from(parts: LocalExitTreeDataParts<H, TREE_DEPTH>) -> Self {
+        let mut data = Self::from_tree(parts.base);
+        for leaf in parts.leaves {
+            data.add_leaf(leaf);
+        }
+
+        data
+    }
+}
+
+impl<H, const TREE_DEPTH: usize> LocalExitTreeData<H, TREE_DEPTH>
+where
+    H: Hasher,
+    H::Digest: Copy + Default + Serialize + for<'a> Deserialize<'a>,
+{
+    /// Creates a new empty [`LocalExitTreeData`].
+    pub fn new() -> Self {
+        Self::from_tree(LocalExitTree::new())
+    }
+
+    /// Creates a new [`LocalExitTreeData`] extending `base`, whose leaves are unknown.
+    pub fn from_tree(base: LocalExitTree<H, TREE_DEPTH>) -> Self {
+        Self {
+            tree: LocalExitTree::from_parts(base.leaf_count(), *base.frontier()),
+            base,
+            leaves: Vec::new(),
+            nodes: vec![Vec::new(); TREE_DEPTH.saturating_sub(1)],
+        }
+    }
+
+    /// Creates a new [`LocalExitTreeData`] and populates its leaves.
+    pub fn from_leaves(leaves: impl Iterator<Item = H::Digest>) -> Self {
+        let mut tree = Self::new();
+
+        for leaf in leaves {
+            tree.add_leaf(leaf);
+        }
+
+        tree
+    }
+
+    /// Appends a leaf to the tree, and returns its index.
+    pub fn add_leaf(&mut self, leaf: H::Digest) -> u32 {
+        let index = self.tree.leaf_count();
+
+        self.leaves.push(leaf);
+        self.tree.add_leaf(leaf);
+
+        // Keep the nodes whose subtree the leaf completes
+        for height in 1..TREE_DEPTH {
+            if (u64::from(index) + 1) % (1 << height) != 0 {
+                break;
+            }
+
+            let node_index = u64::from(index) >> height;
+            let node = H::merge(
+                &self.complete_node(height - 1, 2 * node_index),
+                &self.complete_node(height - 1, 2 * node_index + 1),
+            );
+            self.nodes[height - 1].push(node);
+        }
+
+        index
+    }
+
+    /// Returns the node at `index` among the nodes at `height`, whose subtree must be complete.
+    ///
+    /// The subtrees ending before the known leaves are on the path of a known leaf only if they are
+    /// entries of the frontier of `base`.
+    fn complete_node(&self, height: usize, index: u64) -> H::Digest {
+        let first_index = u64::from(self.first_leaf_index()) >> height;
+        let Some(offset) = index.checked_sub(first_index) else {
+            return self.base.frontier()[height];
+        };
+
+        if height == 0 {
+            self.leaves[offset as usize]
+        } else {
+            self.nodes[height - 1][offset as usize]
+        }
+    }
+
+    /// Returns the number of inserted (non-empty) leaves.
+    pub fn leaf_count(&self) -> u32 {
+        self.tree.leaf_count()
+    }
+
+    /// Returns the index of the first leaf whose path can be provided.
+    pub fn first_leaf_index(&self) -> u32 {
+        self.base.leaf_count()
+    }
+
+    /// Returns the leaf at `index`, if known.
+    pub fn leaf(&self, index: u32) -> Option<H::Digest> {
+        let offset = index.checked_sub(self.first_leaf_index())?;
+
+        self.leaves.get(offset as usize).copied()
+    }
+
+    /// Returns the tree holding all the leaves.
+    pub fn tree(&self) -> &LocalExitTree<H, TREE_DEPTH> {
+        &self.tree
+    }
+
+    /// Computes and returns the root of the tree.
+    pub fn get_root(&self) -> H::Digest {
+        self.tree.get_root()
+    }
+
+    /// Returns the tree as it was when it held `leaf_count` leaves, if known.
+    pub fn tree_at(&self, leaf_count: u32) -> Option<LocalExitTree<H, TREE_DEPTH>> {
+        let offset = leaf_count.checked_sub(self.first_leaf_index())? as usize;
+        let leaves = self.leaves.get(..offset)?;
+
+        let mut tree = LocalExitTree::from_parts(self.base.leaf_count(), *self.base.frontier());
+        for leaf in leaves {
+            tree.add_leaf(*leaf);
+        }
+
+        Some(tree)
+    }
+
+    /// Returns the path of the leaf at `leaf_index` to the root of the tree as it was when it held
+    /// `leaf_count` leaves, if the leaf is known and among them.
+    pub fn get_proof(
+        &self,
+        leaf_index: u32,
+        leaf_count: u32,
+    ) -> Option<MerkleProof<H, TREE_DEPTH>> {
+        if leaf_index < self.first_leaf_index()
+            || leaf_index >= leaf_count
+            || leaf_count > self.leaf_count()
+        {
+            return None;
+        }
+
+        let mut empty_hashes = [H::Digest::default(); TREE_DEPTH];
+        for height in 1..TREE_DEPTH {
+            empty_hashes[height] = H::merge(&empty_hashes[height - 1], &empty_hashes[height - 1]);
+        }
+
+        let mut siblings = [H::Digest::default(); TREE_DEPTH];
+        for (height, sibling) in siblings.iter_mut().enumerate() {
+            let index = (u64::from(leaf_index) >> height) ^ 1;
+            *sibling = self.node(height, index, leaf_count, &empty_hashes);
+        }
+
+        Some(MerkleProof {
+            leaf_index,
+            siblings,
+        })
+    }
+
+    /// Computes the node at `index` among the nodes at `height`, in the tree of `leaf_count`
+    /// leaves.
+    ///
+    /// The nodes of complete subtrees are kept, so only the nodes on the right edge of the tree
+    /// are computed, each from a kept node and a node of the edge.
+    fn node(
+        &self,
+        height: usize,
+        index: u64,
+        leaf_count: u32,
+        empty_hashes: &[H::Digest; TREE_DEPTH],
+    ) -> H::Digest {
+        let start = index << height;
+        let end = (index + 1) << height;
+
+        if start >= u64::from(leaf_count) {
+            return empty_hashes[height];
+        }
+        if end <= u64::from(leaf_count) {
+            return self.complete_node(height, index);
+        }
+
+        H::merge(
+            &self.node(height - 1, 2 * index, leaf_count, empty_hashes),
+            &self.node(height - 1, 2 * index + 1, leaf_count, empty_hashes),
+        )
+    }
+}
+
+impl<H, const TREE_DEPTH: usize> Default for LocalExitTreeData<H, TREE_DEPTH>
+where
+    H: Hasher,
+    H::Digest: Copy + Default + Serialize + for<'a> Deserialize<'a>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
+pub mod data;
 pub mod hasher;
 use hasher::Hasher;
 
@@ -53,6 +54,16 @@ where
         }
     }
 
+    /// Returns the number of inserted (non-empty) leaves.
+    pub fn leaf_count(&self) -> u32 {
+        self.leaf_count
+    }
+
+    /// Returns the frontier of the tree.
+    pub fn frontier(&self) -> &[H::Digest; TREE_DEPTH] {
+        &self.frontier
+    }
+
     /// Appends a leaf to the tree.
     pub fn add_leaf(&mut self, leaf: H::Digest) {
         // the index at which the new entry will be inserted
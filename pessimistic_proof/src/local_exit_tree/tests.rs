@@ -2,7 +2,10 @@ use rs_merkle::{Hasher as MerkleHasher, MerkleTree};
 use tiny_keccak::{Hasher as _, Keccak};
 
 use super::*;
-use crate::local_exit_tree::hasher::Keccak256Hasher;
+use crate::{
+    keccak::keccak256,
+    local_exit_tree::{data::LocalExitTreeData, hasher::Keccak256Hasher},
+};
 
 #[test]
 fn test_local_exit_tree_basic() {
@@ -22,6 +25,58 @@ fn test_local_exit_tree_basic() {
     assert_eq!(ground_truth_tree.root().unwrap(), local_exit_tree.get_root());
 }
 
+fn leaf(index: u32) -> [u8; 32] {
+    keccak256(&index.to_be_bytes())
+}
+
+#[test]
+fn test_merkle_proofs() {
+    let data: LocalExitTreeData<Keccak256Hasher> =
+        LocalExitTreeData::from_leaves((0..13).map(leaf));
+
+    for leaf_count in 1..=13 {
+        let root =
+            LocalExitTree::<Keccak256Hasher>::from_leaves((0..leaf_count).map(leaf)).get_root();
+        assert_eq!(data.tree_at(leaf_count).unwrap().get_root(), root);
+
+        for index in 0..leaf_count {
+            let proof = data.get_proof(index, leaf_count).unwrap();
+            assert!(proof.verify(leaf(index), root));
+            assert!(!proof.verify(leaf(index + 1), root));
+        }
+        assert!(data.get_proof(leaf_count, leaf_count).is_none());
+    }
+    assert!(data.get_proof(0, 14).is_none());
+}
+
+#[test]
+fn test_merkle_proofs_from_frontier() {
+    let base: LocalExitTree<Keccak256Hasher> = LocalExitTree::from_leaves((0..5).map(leaf));
+    let mut data = LocalExitTreeData::from_tree(base);
+    for index in 5..21 {
+        assert_eq!(data.add_leaf(leaf(index)), index);
+    }
+
+    let full: LocalExitTree<Keccak256Hasher> = LocalExitTree::from_leaves((0..21).map(leaf));
+    assert_eq!(data.get_root(), full.get_root());
+
+    // The kept nodes are rebuilt when deserializing.
+    let data: LocalExitTreeData<Keccak256Hasher> =
+        serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+
+    for leaf_count in 6..=21 {
+        let root = data.tree_at(leaf_count).unwrap().get_root();
+        for index in 5..leaf_count {
+            assert!(data.get_proof(index, leaf_count).unwrap().verify(leaf(index), root));
+        }
+    }
+
+    // The leaves of the base tree are unknown.
+    assert!(data.get_proof(4, 21).is_none());
+    assert!(data.leaf(4).is_none());
+    assert!(data.tree_at(4).is_none());
+}
+
 #[derive(Clone, Debug)]
 pub struct TestKeccak256;
 
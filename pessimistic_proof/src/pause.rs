@@ -0,0 +1,149 @@
+//! The emergency pause list, which stops the outbound flows of a network or of a token.
+//!
+//! The pause list is part of the [`ProofContext`](crate::context::ProofContext): withdrawals from a
+//! paused network, or of a paused token from any network, are refused by
+//! [`BalanceTreeByNetwork::transfer`](crate::local_balance_tree::BalanceTreeByNetwork::transfer),
+//! while the other networks keep settling. Its hash is committed by the proof.
+
+use std::{collections::BTreeSet, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keccak::{keccak256_combine, Digest},
+    NetworkId, TokenInfo,
+};
+
+/// Represents the withdrawals refused by the pause list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PauseError {
+    PausedNetwork {
+        network: NetworkId,
+    },
+    PausedToken {
+        network: NetworkId,
+        token: TokenInfo,
+    },
+}
+
+impl fmt::Display for PauseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PauseError::PausedNetwork { network } => {
+                write!(f, "the withdrawals of network {} are paused", **network)
+            }
+            PauseError::PausedToken { network, token } => write!(
+                f,
+                "withdrawal from network {} of token {} of network {}, which is paused",
+                **network, token.origin_token_address, *token.origin_network
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PauseError {}
+
+/// An entry of the pause list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Paused {
+    Network(NetworkId),
+    Token(TokenInfo),
+}
+
+impl fmt::Display for Paused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Paused::Network(network) => write!(f, "network {}", **network),
+            Paused::Token(token) => write!(
+                f,
+                "token {} of network {}",
+                token.origin_token_address, *token.origin_network
+            ),
+        }
+    }
+}
+
+/// The networks and tokens whose withdrawals are paused.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PauseList {
+    /// Networks which may not withdraw any token
+    pub networks: BTreeSet<NetworkId>,
+    /// Tokens which no network may withdraw
+    pub tokens: BTreeSet<TokenInfo>,
+}
+
+impl PauseList {
+    /// Creates a new [`PauseList`] without any entry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pauses or resumes the withdrawals of `network`, and returns whether this changed the list.
+    pub fn set_network_paused(&mut self, network: NetworkId, paused: bool) -> bool {
+        if paused {
+            self.networks.insert(network)
+        } else {
+            self.networks.remove(&network)
+        }
+    }
+
+    /// Pauses or resumes the withdrawals of `token`, and returns whether this changed the list.
+    pub fn set_token_paused(&mut self, token: TokenInfo, paused: bool) -> bool {
+        if paused {
+            self.tokens.insert(token)
+        } else {
+            self.tokens.remove(&token)
+        }
+    }
+
+    /// Returns whether the withdrawals of `network` are paused.
+    pub fn is_network_paused(&self, network: NetworkId) -> bool {
+        self.networks.contains(&network)
+    }
+
+    /// Returns whether the withdrawals of `token` are paused.
+    pub fn is_token_paused(&self, token: &TokenInfo) -> bool {
+        self.tokens.contains(token)
+    }
+
+    /// Returns whether nothing is paused.
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty() && self.tokens.is_empty()
+    }
+
+    /// Checks that `network` may withdraw `token`.
+    pub fn check(&self, network: NetworkId, token: &TokenInfo) -> Result<(), PauseError> {
+        if self.is_network_paused(network) {
+            return Err(PauseError::PausedNetwork { network });
+        }
+        if self.is_token_paused(token) {
+            return Err(PauseError::PausedToken {
+                network,
+                token: token.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Iterates over the entries of the list, networks first.
+    pub fn iter(&self) -> impl Iterator<Item = Paused> + '_ {
+        self.networks
+            .iter()
+            .copied()
+            .map(Paused::Network)
+            .chain(self.tokens.iter().cloned().map(Paused::Token))
+    }
+
+    /// Computes the hash of the list committed by the proof.
+    pub fn hash(&self) -> Digest {
+        let networks: Vec<[u8; 4]> =
+            self.networks.iter().map(|network| network.to_be_bytes()).collect();
+        let tokens: Vec<Digest> = self.tokens.iter().map(TokenInfo::hash).collect();
+
+        keccak256_combine([
+            keccak256_combine(networks.iter().map(|network| network.as_slice())).as_slice(),
+            keccak256_combine(tokens.iter().map(|token| token.as_slice())).as_slice(),
+        ])
+    }
+}
@@ -1,27 +1,389 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use reth_primitives::U256;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     batch::Batch,
+    certificate::{
+        check_attestations, check_headers, check_new_roots, Certificate, CertificateError,
+        CertificateHeader, PrevRoots,
+    },
+    committee::withdrawals_hash,
+    compact::CompactBatches,
+    context::ProofContext,
+    credit::{CreditLines, CreditUsed},
+    cycles,
+    digest::input_digest,
     keccak::Digest,
+    ledger::CorridorFlows,
     local_balance_tree::{merge_balance_trees, BalanceTreeByNetwork},
+    pause::PauseError,
+    rate_limit::{EpochWithdrawn, RateLimits},
+    registry::NetworkRegistry,
+    supply::{SupplyChanges, SupplyError, TokenSupplies},
     withdrawal::NetworkId,
+    TokenInfo,
 };
 
 /// Represents all errors that can occur while generating the proof.
 #[derive(Debug)]
 pub enum ProofError {
-    InvalidLocalExitRoot { got: Digest, expected: Digest },
-    NotEnoughBalance { debtors: Vec<NetworkId> },
+    InvalidLocalExitRoot {
+        got: Digest,
+        expected: Digest,
+    },
+    NotEnoughBalance {
+        debtors: Vec<NetworkId>,
+    },
+    InvalidCertificate(CertificateError),
+    UnknownDestination {
+        origin_network: NetworkId,
+        dest_network: NetworkId,
+    },
+    DisabledDestination {
+        origin_network: NetworkId,
+        dest_network: NetworkId,
+    },
+    RateLimitExceeded {
+        network: NetworkId,
+        token: TokenInfo,
+        limit: U256,
+        attempted: U256,
+    },
+    Paused(PauseError),
+    SupplyNotConserved(SupplyError),
+}
+
+impl From<CertificateError> for ProofError {
+    fn from(error: CertificateError) -> Self {
+        ProofError::InvalidCertificate(error)
+    }
+}
+
+impl From<PauseError> for ProofError {
+    fn from(error: PauseError) -> Self {
+        ProofError::Paused(error)
+    }
+}
+
+impl From<SupplyError> for ProofError {
+    fn from(error: SupplyError) -> Self {
+        ProofError::SupplyNotConserved(error)
+    }
 }
 
 pub type ExitRoot = Digest;
 pub type BalanceRoot = Digest;
-pub type FullProofOutput = (HashMap<NetworkId, ExitRoot>, HashMap<NetworkId, BalanceRoot>);
+
+/// The new roots of each network, along with the amounts withdrawn in the epoch, the credit used by
+/// the networks in overdraft and the new token supplies if checked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FullProofOutput {
+    /// New local exit root of each network
+    pub exit_roots: HashMap<NetworkId, ExitRoot>,
+    /// New balance root of each network
+    pub balance_roots: HashMap<NetworkId, BalanceRoot>,
+    /// Amount of each capped token withdrawn by each network in the epoch, including this proof
+    pub withdrawn: EpochWithdrawn,
+    /// Overdrafts within the credit lines of the context, in `(network, token)` order
+    pub credit_used: Vec<CreditUsed>,
+    /// Totals of each token over every network, if the context checks the supply conservation
+    pub supplies: Option<TokenSupplies>,
+}
+
+/// The public values committed by the program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofOutput {
+    /// New local exit root of each network
+    pub exit_roots: BTreeMap<NetworkId, ExitRoot>,
+    /// New balance root of each network
+    pub balance_roots: BTreeMap<NetworkId, BalanceRoot>,
+    /// Hash of the [`ProofContext`] which the certificates were checked against
+    pub context_hash: Digest,
+    /// Root of the [`NetworkRegistry`] of the context
+    pub registry_root: Digest,
+    /// Hash of the [`RateLimits`] of the context
+    pub rate_limits: Digest,
+    /// Amount of each capped token withdrawn by each network in the epoch, including this proof,
+    /// which the context of the next proof of the epoch holds
+    pub withdrawn: EpochWithdrawn,
+    /// Hash of the [`PauseList`](crate::pause::PauseList) of the context
+    pub paused: Digest,
+    /// Hash of the [`CreditLines`] of the context
+    pub credit_lines: Digest,
+    /// Overdrafts within the credit lines, in `(network, token)` order
+    pub credit_used: Vec<CreditUsed>,
+    /// Hash of the [`SupplyCheck`](crate::supply::SupplyCheck) of the context, see
+    /// [`ProofContext::supply_hash`]
+    pub supply_check: Digest,
+    /// Hash of the totals of each token over every network, if the context checks the supply
+    /// conservation, see [`TokenSupplies::hash`]
+    pub supplies: Option<Digest>,
+    /// Hash of the committee which attested the certificate of each network which has one
+    pub committees: BTreeMap<NetworkId, Digest>,
+    /// Height of the proven certificate of each network
+    pub heights: BTreeMap<NetworkId, u64>,
+    /// Digest of the input data of each network, see [`crate::digest`]
+    pub input_digests: BTreeMap<NetworkId, Digest>,
+    /// Hash of the amount withdrawn through each corridor, see [`CorridorFlows`]
+    pub corridor_flows: Digest,
+}
+
+impl ProofOutput {
+    fn new(
+        context: &ProofContext,
+        heights: BTreeMap<NetworkId, u64>,
+        committees: BTreeMap<NetworkId, Digest>,
+        input_digests: BTreeMap<NetworkId, Digest>,
+        corridor_flows: Digest,
+        full_output: FullProofOutput,
+    ) -> Self {
+        Self {
+            exit_roots: full_output.exit_roots.into_iter().collect(),
+            balance_roots: full_output.balance_roots.into_iter().collect(),
+            context_hash: context.hash(),
+            registry_root: context.registry.root(),
+            rate_limits: context.rate_limits.hash(),
+            withdrawn: full_output.withdrawn,
+            paused: context.paused.hash(),
+            credit_lines: context.credit_lines.hash(),
+            credit_used: full_output.credit_used,
+            supply_check: context.supply_hash(),
+            supplies: full_output.supplies.as_ref().map(TokenSupplies::hash),
+            committees,
+            heights,
+            input_digests,
+            corridor_flows,
+        }
+    }
+}
 
 /// Returns the updated local balance and exit roots for each network.
-pub fn generate_full_proof(batches: &[Batch]) -> Result<FullProofOutput, ProofError> {
+///
+/// The amount of each token withdrawn by each network during the epoch, starting from the amounts
+/// withdrawn before the proof, must not exceed its cap in the rate limits of the context, and
+/// neither the network nor the token may be paused. A network is in debt when it
+/// withdrew more of a token than it was deposited, beyond its credit line on the token.
+///
+/// If the context has a supply check, the issuance is applied to the balance trees of the origin
+/// networks, and the totals of each token over every network must equal its previous totals plus
+/// its issuance and transfers. The other parts of the context are only checked by
+/// [`generate_certified_proof`].
+pub fn generate_full_proof(
+    context: &ProofContext,
+    batches: &[Batch],
+) -> Result<FullProofOutput, ProofError> {
+    prove_batches(context, batches)
+}
+
+/// Checks the certificates against the context, and returns the updated roots and heights.
+///
+/// Withdrawals must go to networks which are registered and enabled in the context, and the
+/// certificates of networks with a committee must be attested by enough of its members.
+pub fn generate_certified_proof(
+    context: &ProofContext,
+    certificates: &[Certificate],
+) -> Result<ProofOutput, ProofError> {
+    let heights = check_headers(
+        context,
+        certificates.iter().map(|certificate| {
+            let batch = &certificate.batch;
+            let prev_roots = PrevRoots {
+                origin_network: batch.origin_network,
+                local_exit_root: batch.prev_local_exit_root,
+                balance_root: batch.prev_local_balance_tree.hash(),
+            };
+            (&certificate.header, prev_roots)
+        }),
+    )?;
+
+    let committees = check_attestations(
+        context,
+        certificates
+            .iter()
+            .map(|certificate| (&certificate.header, || certificate.batch.withdrawals_hash())),
+    )?;
+
+    for certificate in certificates {
+        let batch = &certificate.batch;
+        for withdrawal in &batch.withdrawals {
+            check_destination(&context.registry, batch.origin_network, withdrawal.dest_network)?;
+        }
+    }
+
+    let new_roots =
+        prove_batches(context, certificates.iter().map(|certificate| &certificate.batch))?;
+    check_new_roots(
+        certificates.iter().map(|certificate| &certificate.header),
+        &new_roots.exit_roots,
+    )?;
+
+    let input_digests = certificates
+        .iter()
+        .map(|certificate| {
+            let batch = &certificate.batch;
+            (batch.origin_network, batch.input_digest())
+        })
+        .collect();
+    let corridor_flows =
+        CorridorFlows::from_batches(certificates.iter().map(|certificate| &certificate.batch))
+            .hash();
+
+    Ok(ProofOutput::new(
+        context,
+        heights,
+        committees,
+        input_digests,
+        corridor_flows,
+        new_roots,
+    ))
+}
+
+/// Same as [`generate_certified_proof`], reading the batches in place from their compact encoding.
+pub fn generate_certified_proof_compact(
+    context: &ProofContext,
+    headers: &[CertificateHeader],
+    batches: &CompactBatches,
+) -> Result<ProofOutput, ProofError> {
+    if headers.len() != batches.len() {
+        return Err(CertificateError::CountMismatch {
+            headers: headers.len(),
+            batches: batches.len(),
+        }
+        .into());
+    }
+
+    let heights = check_headers(
+        context,
+        headers.iter().zip(batches.iter()).map(|(header, batch)| {
+            let prev_roots = PrevRoots {
+                origin_network: batch.origin_network(),
+                local_exit_root: batch.prev_local_exit_root(),
+                balance_root: batch.prev_local_balance_tree().hash(),
+            };
+            (header, prev_roots)
+        }),
+    )?;
+
+    let committees = check_attestations(
+        context,
+        headers.iter().zip(batches.iter()).map(|(header, batch)| {
+            let withdrawals_hash =
+                move || withdrawals_hash(batch.withdrawals().map(|withdrawal| withdrawal.hash()));
+            (header, withdrawals_hash)
+        }),
+    )?;
+
+    for batch in batches.iter() {
+        for withdrawal in batch.withdrawals() {
+            check_destination(
+                &context.registry,
+                batch.origin_network(),
+                withdrawal.dest_network(),
+            )?;
+        }
+    }
+
+    let new_roots = generate_full_proof_compact(context, batches)?;
+    check_new_roots(headers.iter(), &new_roots.exit_roots)?;
+
+    let input_digests = batches
+        .iter()
+        .map(|batch| {
+            let leaves = batch.withdrawals().map(|withdrawal| withdrawal.hash());
+            (batch.origin_network(), input_digest(batch.origin_network(), leaves))
+        })
+        .collect();
+    let corridor_flows = CorridorFlows::from_transfers(batches.iter().flat_map(|batch| {
+        let origin_network = batch.origin_network();
+        batch.withdrawals().map(move |withdrawal| {
+            (
+                origin_network,
+                withdrawal.dest_network(),
+                withdrawal.balance_token(),
+                withdrawal.amount(),
+            )
+        })
+    }))
+    .hash();
+
+    Ok(ProofOutput::new(
+        context,
+        heights,
+        committees,
+        input_digests,
+        corridor_flows,
+        new_roots,
+    ))
+}
+
+/// Checks that a withdrawal goes to a registered and enabled network.
+fn check_destination(
+    registry: &NetworkRegistry,
+    origin_network: NetworkId,
+    dest_network: NetworkId,
+) -> Result<(), ProofError> {
+    match registry.get(dest_network) {
+        Some(config) if config.enabled => Ok(()),
+        Some(_) => Err(ProofError::DisabledDestination {
+            origin_network,
+            dest_network,
+        }),
+        None => Err(ProofError::UnknownDestination {
+            origin_network,
+            dest_network,
+        }),
+    }
+}
+
+/// Checks that the amount of each token withdrawn by each network during the epoch does not
+/// exceed its cap, and returns the amounts withdrawn including `withdrawals`.
+///
+/// Only the pairs withdrawn by the proof are checked, so that lowering a cap below the amount
+/// already withdrawn only blocks further withdrawals of that pair.
+fn check_rate_limits(
+    rate_limits: &RateLimits,
+    withdrawn: &EpochWithdrawn,
+    withdrawals: impl Iterator<Item = (NetworkId, TokenInfo, U256)>,
+) -> Result<EpochWithdrawn, ProofError> {
+    let mut withdrawn = withdrawn.clone();
+    if rate_limits.is_empty() {
+        return Ok(withdrawn);
+    }
+
+    let mut attempted: BTreeMap<(NetworkId, TokenInfo), U256> = BTreeMap::new();
+    for (network, token, amount) in withdrawals {
+        if rate_limits.get(network, &token).is_some() {
+            let total = withdrawn.add(network, token.clone(), amount);
+            attempted.insert((network, token), total);
+        }
+    }
+
+    for ((network, token), attempted) in attempted {
+        let limit = rate_limits.get(network, &token).unwrap_or(U256::MAX);
+        if attempted > limit {
+            return Err(ProofError::RateLimitExceeded {
+                network,
+                token,
+                limit,
+                attempted,
+            });
+        }
+    }
+
+    Ok(withdrawn)
+}
+
+fn prove_batches<'a, I>(context: &ProofContext, batches: I) -> Result<FullProofOutput, ProofError>
+where
+    I: IntoIterator<Item = &'a Batch>,
+    I::IntoIter: Clone,
+{
+    let batches = batches.into_iter();
+
     // Check the validity of the provided exit roots
-    for batch in batches {
+    for batch in batches.clone() {
         let computed_root = batch.prev_local_exit_tree.get_root();
 
         if computed_root != batch.prev_local_exit_root {
@@ -32,35 +394,192 @@ pub fn generate_full_proof(batches: &[Batch]) -> Result<FullProofOutput, ProofEr
         }
     }
 
+    let withdrawn = check_rate_limits(
+        &context.rate_limits,
+        &context.withdrawn,
+        batches.clone().flat_map(|batch| {
+            batch.withdrawals.iter().map(|withdrawal| {
+                (batch.origin_network, withdrawal.balance_token(), withdrawal.amount)
+            })
+        }),
+    )?;
+
+    let supply_changes = context.supply.is_some().then(|| {
+        let mut changes = SupplyChanges::new();
+        for batch in batches.clone() {
+            changes.add_prev_balance_tree(&batch.prev_local_balance_tree);
+            for withdrawal in &batch.withdrawals {
+                changes.add_transfer(withdrawal.balance_token(), withdrawal.amount);
+            }
+        }
+        changes
+    });
+
     // Compute the new exit root
+    cycles::start(cycles::EXIT_TREE);
     let exit_roots: HashMap<NetworkId, ExitRoot> = batches
-        .iter()
+        .clone()
         .map(|batch| (batch.origin_network, batch.compute_new_exit_root()))
         .collect();
+    cycles::end(cycles::EXIT_TREE);
 
     // Compute the new balance tree by network
+    cycles::start(cycles::BALANCE_MERGE);
     let balance_trees: HashMap<NetworkId, BalanceTreeByNetwork> = batches
-        .iter()
-        .map(|batch| (batch.origin_network, batch.compute_new_balance_tree()))
-        .collect();
+        .map(|batch| Ok((batch.origin_network, batch.compute_new_balance_tree(&context.paused)?)))
+        .collect::<Result<_, PauseError>>()?;
+    cycles::end(cycles::BALANCE_MERGE);
+
+    settle(context, exit_roots, balance_trees, withdrawn, supply_changes)
+}
+
+/// Same as [`generate_full_proof`], reading the batches in place from their compact encoding.
+pub fn generate_full_proof_compact(
+    context: &ProofContext,
+    batches: &CompactBatches,
+) -> Result<FullProofOutput, ProofError> {
+    let withdrawn = check_rate_limits(
+        &context.rate_limits,
+        &context.withdrawn,
+        batches.iter().flat_map(|batch| {
+            let origin_network = batch.origin_network();
+            batch.withdrawals().map(move |withdrawal| {
+                (origin_network, withdrawal.balance_token(), withdrawal.amount())
+            })
+        }),
+    )?;
+
+    let mut exit_roots: HashMap<NetworkId, ExitRoot> = HashMap::new();
+    let mut balance_trees: HashMap<NetworkId, BalanceTreeByNetwork> = HashMap::new();
+    let mut supply_changes = context.supply.is_some().then(SupplyChanges::new);
 
+    for batch in batches.iter() {
+        let origin_network = batch.origin_network();
+        let mut local_exit_tree = batch.prev_local_exit_tree();
+
+        // Check the validity of the provided exit root
+        let computed_root = local_exit_tree.get_root();
+        if computed_root != batch.prev_local_exit_root() {
+            return Err(ProofError::InvalidLocalExitRoot {
+                got: computed_root,
+                expected: batch.prev_local_exit_root(),
+            });
+        }
+
+        cycles::start(cycles::EXIT_TREE);
+        for withdrawal in batch.withdrawals() {
+            cycles::start(cycles::LEAF_HASHING);
+            let leaf = withdrawal.hash();
+            cycles::end(cycles::LEAF_HASHING);
+            local_exit_tree.add_leaf(leaf);
+        }
+        exit_roots.insert(origin_network, local_exit_tree.get_root());
+        cycles::end(cycles::EXIT_TREE);
+
+        cycles::start(cycles::BALANCE_MERGE);
+        let prev_local_balance_tree = batch.prev_local_balance_tree();
+        if let Some(changes) = &mut supply_changes {
+            changes.add_prev_balance_tree(&prev_local_balance_tree);
+            for withdrawal in batch.withdrawals() {
+                changes.add_transfer(withdrawal.balance_token(), withdrawal.amount());
+            }
+        }
+        let mut aggregate: BalanceTreeByNetwork =
+            BTreeMap::from([(origin_network, prev_local_balance_tree)]).into();
+        for withdrawal in batch.withdrawals() {
+            aggregate.transfer(
+                origin_network,
+                withdrawal.dest_network(),
+                withdrawal.balance_token(),
+                withdrawal.amount(),
+                &context.paused,
+            )?;
+        }
+        balance_trees.insert(origin_network, aggregate);
+        cycles::end(cycles::BALANCE_MERGE);
+    }
+
+    settle(context, exit_roots, balance_trees, withdrawn, supply_changes)
+}
+
+/// Merges the balance trees of every batch along with the issuance, checks that no network is in
+/// debt beyond its credit lines and that the token supplies are conserved, and returns the new
+/// roots along with the amounts `withdrawn` in the epoch and the credit used.
+///
+/// The `supply_changes` are collected from the batches if the context has a supply check.
+fn settle(
+    context: &ProofContext,
+    exit_roots: HashMap<NetworkId, ExitRoot>,
+    balance_trees: HashMap<NetworkId, BalanceTreeByNetwork>,
+    withdrawn: EpochWithdrawn,
+    supply_changes: Option<SupplyChanges>,
+) -> Result<FullProofOutput, ProofError> {
     // Merge the balance tree by network
-    let balance_tree_by_network: BalanceTreeByNetwork = merge_balance_trees(&balance_trees);
+    cycles::start(cycles::BALANCE_MERGE);
+    let mut balance_tree_by_network: BalanceTreeByNetwork = merge_balance_trees(&balance_trees);
+    cycles::end(cycles::BALANCE_MERGE);
+
+    if let Some(supply) = &context.supply {
+        supply.issue(&mut balance_tree_by_network)?;
+    }
 
     // Detect the debtors if any
     let debtors = balance_tree_by_network
         .iter()
-        .filter_map(|(network, balance_tree)| balance_tree.has_debt().then(|| *network))
+        .filter_map(|(network, balance_tree)| {
+            balance_tree.has_debt(*network, &context.credit_lines).then(|| *network)
+        })
         .collect::<Vec<_>>();
 
     if !debtors.is_empty() {
         return Err(ProofError::NotEnoughBalance { debtors });
     }
 
+    let credit_used = credit_used(&context.credit_lines, &balance_tree_by_network);
+
+    let supplies = match (&context.supply, &supply_changes) {
+        (Some(supply), Some(changes)) => Some(supply.check(changes, &balance_tree_by_network)?),
+        _ => None,
+    };
+
+    cycles::start(cycles::BALANCE_HASHING);
     let balance_roots: HashMap<NetworkId, BalanceRoot> = balance_tree_by_network
         .iter()
         .map(|(network, balance_tree)| (*network, balance_tree.hash()))
         .collect();
+    cycles::end(cycles::BALANCE_HASHING);
 
-    Ok((exit_roots, balance_roots))
+    Ok(FullProofOutput {
+        exit_roots,
+        balance_roots,
+        withdrawn,
+        credit_used,
+        supplies,
+    })
+}
+
+/// Returns the overdrafts of the networks, which are all within their credit lines once no network
+/// is in debt.
+fn credit_used(
+    credit_lines: &CreditLines,
+    balance_tree_by_network: &BalanceTreeByNetwork,
+) -> Vec<CreditUsed> {
+    // Without credit lines, no network may be in overdraft.
+    if credit_lines.is_empty() {
+        return Vec::new();
+    }
+
+    balance_tree_by_network
+        .iter()
+        .flat_map(|(network, balance_tree)| {
+            balance_tree.iter().filter_map(|(token, balance)| {
+                let amount = balance.overdraft();
+                (amount > U256::ZERO).then(|| CreditUsed {
+                    network: *network,
+                    token: token.clone(),
+                    amount,
+                })
+            })
+        })
+        .collect()
 }
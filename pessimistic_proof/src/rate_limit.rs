@@ -0,0 +1,18 @@
+//! Caps on the amount of each token which a network may withdraw per epoch.
+//!
+//! The caps are part of the [`ProofContext`](crate::context::ProofContext), and bound what a
+//! compromised sequencer can drain from the bridge within an epoch, on top of the balance check.
+//! Their hash is committed by the proof.
+//!
+//! An epoch may span several proofs, for instance when a batch is split, so the context also holds
+//! the [`EpochWithdrawn`] amounts of the capped tokens, which the proof adds its withdrawals to and
+//! commits. The next proof of the epoch starts from them, and whoever maintains the context resets
+//! them when a new epoch starts.
+
+use crate::token_amounts::TokenAmounts;
+
+/// The cap of each `(network, token)` pair which has one, on the amount withdrawn per epoch.
+pub type RateLimits = TokenAmounts;
+
+/// The amount of each capped token withdrawn by each network since the start of the epoch.
+pub type EpochWithdrawn = TokenAmounts;
@@ -0,0 +1,143 @@
+//! The registry of the networks connected to the bridge, and of their configuration.
+//!
+//! The registry is part of the [`ProofContext`](crate::context::ProofContext): it authorizes the
+//! signer of the certificates of each network, and withdrawals may only go to networks which are
+//! registered and enabled. Its root is committed by the proof, so that L1 can check it against its
+//! own registry.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use reth_primitives::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keccak::{keccak256_combine, Digest},
+    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    NetworkId, TokenInfo,
+};
+
+/// The kind of chain of a network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainType {
+    Mainnet,
+    Rollup,
+    Validium,
+}
+
+impl ChainType {
+    fn to_byte(self) -> u8 {
+        match self {
+            ChainType::Mainnet => 0,
+            ChainType::Rollup => 1,
+            ChainType::Validium => 2,
+        }
+    }
+}
+
+impl fmt::Display for ChainType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainType::Mainnet => write!(f, "mainnet"),
+            ChainType::Rollup => write!(f, "rollup"),
+            ChainType::Validium => write!(f, "validium"),
+        }
+    }
+}
+
+impl FromStr for ChainType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(ChainType::Mainnet),
+            "rollup" => Ok(ChainType::Rollup),
+            "validium" => Ok(ChainType::Validium),
+            _ => Err(format!("unknown chain type {s}, expected mainnet, rollup or validium")),
+        }
+    }
+}
+
+/// The configuration of a registered network.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Index of the network in the rollup exit tree, for rollups and validiums
+    pub rollup_index: Option<u32>,
+    pub chain_type: ChainType,
+    /// Address of the key authorized to sign the certificates of the network
+    pub signer: Address,
+    /// Token paying for gas on the network
+    pub gas_token: TokenInfo,
+    /// Whether the network may settle certificates and receive withdrawals
+    pub enabled: bool,
+}
+
+impl NetworkConfig {
+    /// Computes the leaf of the network in the registry tree.
+    fn hash(&self, network: NetworkId) -> Digest {
+        let rollup_index = self.rollup_index.map_or([0; 5], |index| {
+            let mut bytes = [1; 5];
+            bytes[1..].copy_from_slice(&index.to_be_bytes());
+            bytes
+        });
+
+        keccak256_combine([
+            network.to_be_bytes().as_slice(),
+            &rollup_index,
+            &[self.chain_type.to_byte(), self.enabled as u8],
+            self.signer.as_slice(),
+            &self.gas_token.hash(),
+        ])
+    }
+}
+
+/// The registered networks, by id.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkRegistry {
+    pub networks: BTreeMap<NetworkId, NetworkConfig>,
+}
+
+impl NetworkRegistry {
+    /// Creates a new empty [`NetworkRegistry`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `network`, replacing its previous configuration if any.
+    pub fn register(&mut self, network: NetworkId, config: NetworkConfig) {
+        self.networks.insert(network, config);
+    }
+
+    /// Returns the configuration of `network`, if registered.
+    pub fn get(&self, network: NetworkId) -> Option<&NetworkConfig> {
+        self.networks.get(&network)
+    }
+
+    /// Returns the network registered at `rollup_index` in the rollup exit tree, if any.
+    pub fn network_of_rollup(&self, rollup_index: u32) -> Option<NetworkId> {
+        self.networks
+            .iter()
+            .find(|(_, config)| config.rollup_index == Some(rollup_index))
+            .map(|(network, _)| *network)
+    }
+
+    /// Enables or disables `network`, and returns whether it is registered.
+    pub fn set_enabled(&mut self, network: NetworkId, enabled: bool) -> bool {
+        match self.networks.get_mut(&network) {
+            Some(config) => {
+                config.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Computes the root of the tree whose leaves are the registered networks, in id order.
+    pub fn root(&self) -> Digest {
+        let tree: LocalExitTree<Keccak256Hasher> = LocalExitTree::from_leaves(
+            self.networks.iter().map(|(network, config)| config.hash(*network)),
+        );
+
+        tree.get_root()
+    }
+}
@@ -0,0 +1,249 @@
+//! Replay of bridge event dumps against the exit roots checkpointed on-chain.
+//!
+//! The mainnet exit root is the root of the [`LocalExitTree`] built from the deposits. The rollup
+//! exit root is the root of the tree whose leaf `i` is the local exit root of the rollup `i + 1`,
+//! as reported by the `VerifyBatches` events. Every `UpdateL1InfoTree` event is a checkpoint at
+//! which both computed roots must match the emitted ones.
+
+use std::fmt;
+
+use reth_primitives::hex;
+
+use crate::{
+    ingest::{BridgeEvent, EventData, IngestError},
+    keccak::Digest,
+    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    Withdrawal,
+};
+
+/// The exit roots checked at each checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitRootKind {
+    Mainnet,
+    Rollup,
+}
+
+/// The first point at which the replayed exit roots differ from the on-chain ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub kind: ExitRootKind,
+    pub block_number: u64,
+    pub log_index: u64,
+    /// Number of deposits replayed before the checkpoint
+    pub leaf_count: u32,
+    /// Root emitted on-chain
+    pub expected: Digest,
+    /// Root computed from the replayed events
+    pub computed: Digest,
+}
+
+/// Represents all errors that can occur while replaying bridge events.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The events could not be read.
+    Ingest(IngestError),
+    /// A deposit does not have the next deposit count, meaning that events are missing.
+    DepositCountMismatch {
+        block_number: u64,
+        log_index: u64,
+        expected: u32,
+        got: u32,
+    },
+    /// A rollup exit root update refers to rollup 0, which does not exist.
+    InvalidRollupId { block_number: u64, log_index: u64 },
+    /// A replayed exit root differs from the one checkpointed on-chain.
+    Divergence(Divergence),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Ingest(e) => write!(f, "{e}"),
+            ReplayError::DepositCountMismatch {
+                block_number,
+                log_index,
+                expected,
+                got,
+            } => write!(
+                f,
+                "expected deposit count {expected}, got {got} (block {block_number}, log \
+                 {log_index})"
+            ),
+            ReplayError::InvalidRollupId {
+                block_number,
+                log_index,
+            } => write!(f, "invalid rollup ID 0 (block {block_number}, log {log_index})"),
+            ReplayError::Divergence(divergence) => write!(
+                f,
+                "{:?} exit root diverges at block {}, log {} after {} deposits: expected \
+                 0x{}, computed 0x{}",
+                divergence.kind,
+                divergence.block_number,
+                divergence.log_index,
+                divergence.leaf_count,
+                hex::encode(divergence.expected),
+                hex::encode(divergence.computed),
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<IngestError> for ReplayError {
+    fn from(e: IngestError) -> Self {
+        ReplayError::Ingest(e)
+    }
+}
+
+/// The outcome of a successful replay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplaySummary {
+    /// Number of deposits appended to the mainnet exit tree
+    pub deposits: u32,
+    /// Number of checkpoints at which the mainnet exit root was checked
+    pub mainnet_checkpoints: usize,
+    /// Number of checkpoints at which the rollup exit root was checked
+    pub rollup_checkpoints: usize,
+    /// Mainnet exit root after the last event
+    pub mainnet_exit_root: Digest,
+    /// Rollup exit root after the last event
+    pub rollup_exit_root: Digest,
+}
+
+/// Replays bridge events in order, checking every checkpoint along the way.
+///
+/// The rollup exit root is only checked once a rollup exit root update has been replayed, so that
+/// dumps without rollup manager events can still be checked against the mainnet exit root.
+#[derive(Debug, Default)]
+pub struct Replayer {
+    mainnet_exit_tree: LocalExitTree<Keccak256Hasher>,
+    /// Local exit root of each rollup, by rollup ID - 1
+    rollup_exit_roots: Vec<Digest>,
+    summary: ReplaySummary,
+}
+
+impl Replayer {
+    /// Creates a new [`Replayer`] starting from empty exit trees.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the mainnet exit tree built from the deposits replayed so far.
+    pub fn mainnet_exit_tree(&self) -> &LocalExitTree<Keccak256Hasher> {
+        &self.mainnet_exit_tree
+    }
+
+    /// Computes the current mainnet exit root.
+    pub fn mainnet_exit_root(&self) -> Digest {
+        self.mainnet_exit_tree.get_root()
+    }
+
+    /// Computes the current rollup exit root.
+    pub fn rollup_exit_root(&self) -> Digest {
+        LocalExitTree::<Keccak256Hasher>::from_leaves(self.rollup_exit_roots.iter().copied())
+            .get_root()
+    }
+
+    /// Applies the next event. Events removed by a reorg are ignored.
+    pub fn apply(&mut self, event: &BridgeEvent) -> Result<(), ReplayError> {
+        if event.removed {
+            return Ok(());
+        }
+
+        match &event.event_data {
+            EventData::Deposit(deposit_event_data) => {
+                if deposit_event_data.deposit_count != self.summary.deposits {
+                    return Err(ReplayError::DepositCountMismatch {
+                        block_number: event.block_number,
+                        log_index: event.log_index,
+                        expected: self.summary.deposits,
+                        got: deposit_event_data.deposit_count,
+                    });
+                }
+
+                let withdrawal: Withdrawal = deposit_event_data.clone().into();
+                self.mainnet_exit_tree.add_leaf(withdrawal.hash());
+                self.summary.deposits += 1;
+            }
+            EventData::VerifyBatches {
+                rollup_id,
+                exit_root,
+            } => {
+                let index = rollup_id.checked_sub(1).ok_or(ReplayError::InvalidRollupId {
+                    block_number: event.block_number,
+                    log_index: event.log_index,
+                })? as usize;
+
+                if self.rollup_exit_roots.len() <= index {
+                    self.rollup_exit_roots.resize(index + 1, Digest::default());
+                }
+                self.rollup_exit_roots[index] = *exit_root;
+            }
+            EventData::UpdateL1InfoTree {
+                mainnet_exit_root,
+                rollup_exit_root,
+            } => {
+                self.check(event, ExitRootKind::Mainnet, *mainnet_exit_root)?;
+                self.summary.mainnet_checkpoints += 1;
+
+                if !self.rollup_exit_roots.is_empty() {
+                    self.check(event, ExitRootKind::Rollup, *rollup_exit_root)?;
+                    self.summary.rollup_checkpoints += 1;
+                }
+            }
+            EventData::Claim(_) => {}
+        }
+
+        Ok(())
+    }
+
+    fn check(
+        &self,
+        event: &BridgeEvent,
+        kind: ExitRootKind,
+        expected: Digest,
+    ) -> Result<(), ReplayError> {
+        let computed = match kind {
+            ExitRootKind::Mainnet => self.mainnet_exit_root(),
+            ExitRootKind::Rollup => self.rollup_exit_root(),
+        };
+
+        if computed != expected {
+            return Err(ReplayError::Divergence(Divergence {
+                kind,
+                block_number: event.block_number,
+                log_index: event.log_index,
+                leaf_count: self.summary.deposits,
+                expected,
+                computed,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the summary of the events replayed so far.
+    pub fn summary(&self) -> ReplaySummary {
+        ReplaySummary {
+            mainnet_exit_root: self.mainnet_exit_root(),
+            rollup_exit_root: self.rollup_exit_root(),
+            ..self.summary.clone()
+        }
+    }
+}
+
+/// Replays the given events, which must be sorted by position in the chain, and stops at the
+/// first error.
+pub fn replay<I>(events: I) -> Result<ReplaySummary, ReplayError>
+where
+    I: IntoIterator<Item = Result<BridgeEvent, IngestError>>,
+{
+    let mut replayer = Replayer::new();
+
+    for event in events {
+        replayer.apply(&event?)?;
+    }
+
+    Ok(replayer.summary())
+}
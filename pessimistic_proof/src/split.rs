@@ -0,0 +1,178 @@
+//! Splitting of a batch into consecutive sub-batches which each fit a cycle budget.
+//!
+//! Each sub-batch starts from the local exit tree and balance tree left by the previous one, so
+//! that proving them in sequence chains the local exit roots, and the last one yields the same
+//! local exit root and balance tree for the origin network as the whole batch.
+//!
+//! Only the roots of the origin network are preserved. Each sub-proof credits the destination
+//! networks with the withdrawals of its own sub-batch, so their balance roots differ from those of
+//! the whole batch, whose deposits are the sum of the deposits of every sub-batch.
+
+use std::fmt;
+
+use crate::{
+    batch::Batch,
+    cost::{CostAccumulator, CostModel},
+    local_balance_tree::BalanceTree,
+    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    pause::PauseList,
+    withdrawal::NetworkId,
+    Withdrawal,
+};
+
+/// Represents all errors that can occur while splitting a batch or checking a split.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitError {
+    /// A sub-batch holding the given withdrawal alone does not fit the budget.
+    WithdrawalTooLarge { index: usize, cycles: u64 },
+    /// The sub-batches are not consecutive: the given sub-batch does not start from the state
+    /// left by the previous one.
+    BrokenChain { index: usize },
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::WithdrawalTooLarge { index, cycles } => write!(
+                f,
+                "withdrawal {index} alone takes an estimated {cycles} cycles, over the budget"
+            ),
+            SplitError::BrokenChain { index } => {
+                write!(f, "sub-batch {index} does not follow the previous one")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+/// The state of the origin network between two sub-batches.
+struct Checkpoint {
+    local_exit_tree: LocalExitTree<Keccak256Hasher>,
+    balance_tree: BalanceTree,
+}
+
+impl Checkpoint {
+    fn sub_batch(&self, origin_network: NetworkId, withdrawals: &[Withdrawal]) -> Batch {
+        Batch::new(
+            origin_network,
+            self.local_exit_tree.clone(),
+            self.local_exit_tree.get_root(),
+            self.balance_tree.clone(),
+            withdrawals.to_vec(),
+        )
+    }
+
+    /// Returns the state left by `sub_batch`.
+    fn after(sub_batch: &Batch) -> Self {
+        let mut local_exit_tree = sub_batch.prev_local_exit_tree.clone();
+        for withdrawal in &sub_batch.withdrawals {
+            local_exit_tree.add_leaf(withdrawal.hash());
+        }
+
+        let balance_tree = sub_batch
+            .compute_new_balance_tree(&PauseList::new())
+            .expect("nothing is paused")
+            .remove(&sub_batch.origin_network)
+            .unwrap_or_default();
+
+        Self {
+            local_exit_tree,
+            balance_tree,
+        }
+    }
+}
+
+impl From<&Batch> for Checkpoint {
+    fn from(batch: &Batch) -> Self {
+        Self {
+            local_exit_tree: batch.prev_local_exit_tree.clone(),
+            balance_tree: batch.prev_local_balance_tree.clone(),
+        }
+    }
+}
+
+/// Splits `batch` into consecutive sub-batches of at most `max_withdrawals` withdrawals.
+pub fn split_batch_by_len(batch: &Batch, max_withdrawals: usize) -> Vec<Batch> {
+    assert!(max_withdrawals > 0, "sub-batches must hold withdrawals");
+
+    if batch.withdrawals.is_empty() {
+        return vec![batch.clone()];
+    }
+
+    let mut checkpoint = Checkpoint::from(batch);
+    let mut sub_batches = Vec::new();
+    for withdrawals in batch.withdrawals.chunks(max_withdrawals) {
+        let sub_batch = checkpoint.sub_batch(batch.origin_network, withdrawals);
+        checkpoint = Checkpoint::after(&sub_batch);
+        sub_batches.push(sub_batch);
+    }
+
+    sub_batches
+}
+
+/// Splits `batch` into as few consecutive sub-batches as possible, each estimated by `model` to
+/// take at most `max_cycles` cycles when proven alone.
+pub fn split_batch(
+    batch: &Batch,
+    model: &CostModel,
+    max_cycles: u64,
+) -> Result<Vec<Batch>, SplitError> {
+    if batch.withdrawals.is_empty() {
+        return Ok(vec![batch.clone()]);
+    }
+
+    let mut checkpoint = Checkpoint::from(batch);
+    let mut sub_batches = Vec::new();
+    let mut start = 0;
+    while start < batch.withdrawals.len() {
+        let mut cost = CostAccumulator::new();
+        cost.add_batch(
+            batch.origin_network,
+            checkpoint.local_exit_tree.leaf_count(),
+            &checkpoint.balance_tree,
+        );
+
+        let mut end = start;
+        while let Some(withdrawal) = batch.withdrawals.get(end) {
+            cost.add_withdrawal(batch.origin_network, withdrawal);
+
+            let cycles = model.estimate(&cost.features());
+            if cycles > max_cycles {
+                if end == start {
+                    return Err(SplitError::WithdrawalTooLarge {
+                        index: start,
+                        cycles,
+                    });
+                }
+                break;
+            }
+            end += 1;
+        }
+
+        let sub_batch = checkpoint.sub_batch(batch.origin_network, &batch.withdrawals[start..end]);
+        checkpoint = Checkpoint::after(&sub_batch);
+        sub_batches.push(sub_batch);
+        start = end;
+    }
+
+    Ok(sub_batches)
+}
+
+/// Checks that each sub-batch starts from the local exit tree and balance tree left by the
+/// previous one.
+pub fn check_chain(sub_batches: &[Batch]) -> Result<(), SplitError> {
+    for (index, pair) in sub_batches.windows(2).enumerate() {
+        let previous = Checkpoint::after(&pair[0]);
+        let sub_batch = &pair[1];
+
+        let follows = sub_batch.prev_local_exit_root == previous.local_exit_tree.get_root()
+            && sub_batch.prev_local_exit_tree.get_root() == sub_batch.prev_local_exit_root
+            && sub_batch.prev_local_balance_tree.hash() == previous.balance_tree.hash();
+        if !follows {
+            return Err(SplitError::BrokenChain { index: index + 1 });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,283 @@
+//! Global supply conservation, which checks the accounting of each token across all networks.
+//!
+//! The optional [`SupplyCheck`] of the [`ProofContext`](crate::context::ProofContext) holds the
+//! totals of every network after the previous proof, whether or not they are part of this one,
+//! along with the amounts issued and burnt by the origin network of each token. The issuance is
+//! applied to the balance tree of the origin network, which must be part of the proof, so that it
+//! can back the withdrawals of the network.
+//!
+//! The proof then derives the new totals from the balance trees of its networks, before and after,
+//! and fails unless they differ from the previous totals by exactly the issuance plus the amounts
+//! transferred, which every transfer adds to both the total deposits and the total withdrawals.
+//! This catches accounting bugs which the per-network solvency checks miss, such as a balance tree
+//! which is dropped or merged twice. The new totals are committed, and become the previous totals
+//! of the next proof.
+//!
+//! The totals of the first proof are bootstrapped from the balance trees of every network with
+//! [`SupplyCheck::bootstrap`].
+
+use std::{collections::BTreeMap, fmt};
+
+use reth_primitives::U256;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::{
+    keccak::{keccak256_combine, Digest},
+    local_balance_tree::{Balance, BalanceTree, BalanceTreeByNetwork},
+    TokenInfo,
+};
+
+/// The total deposits and withdrawals of each token across the networks.
+///
+/// Serialized as a sequence of `(token, balance)` pairs, as [`TokenInfo`] is not a valid JSON key.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenSupplies(#[serde_as(as = "Vec<(_, _)>")] BTreeMap<TokenInfo, Balance>);
+
+impl TokenSupplies {
+    /// Creates a new [`TokenSupplies`] without any token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `balance` to the totals of `token`.
+    pub fn add(&mut self, token: TokenInfo, balance: &Balance) {
+        let total = self.0.entry(token).or_default();
+        total.deposit(balance.deposited());
+        total.withdraw(balance.withdrawn());
+    }
+
+    /// Returns the totals of `token`, if any.
+    pub fn get(&self, token: &TokenInfo) -> Option<&Balance> {
+        self.0.get(token)
+    }
+
+    /// Iterates over the totals, in token order.
+    pub fn iter(&self) -> impl Iterator<Item = (&TokenInfo, &Balance)> {
+        self.0.iter()
+    }
+
+    /// Adds `balance` to the totals of `token`, failing on overflow.
+    fn checked_add(&mut self, token: &TokenInfo, balance: &Balance) -> Result<(), SupplyError> {
+        let total = self.0.entry(token.clone()).or_default();
+        match (
+            total.deposited().checked_add(balance.deposited()),
+            total.withdrawn().checked_add(balance.withdrawn()),
+        ) {
+            (Some(deposited), Some(withdrawn)) => {
+                *total = Balance::new(deposited, withdrawn);
+                Ok(())
+            }
+            _ => Err(SupplyError::OutOfRange {
+                token: token.clone(),
+            }),
+        }
+    }
+
+    /// Subtracts `balance` from the totals of `token`, failing if they do not cover it.
+    fn checked_sub(&mut self, token: &TokenInfo, balance: &Balance) -> Result<(), SupplyError> {
+        let total = self.0.entry(token.clone()).or_default();
+        match (
+            total.deposited().checked_sub(balance.deposited()),
+            total.withdrawn().checked_sub(balance.withdrawn()),
+        ) {
+            (Some(deposited), Some(withdrawn)) => {
+                *total = Balance::new(deposited, withdrawn);
+                Ok(())
+            }
+            _ => Err(SupplyError::OutOfRange {
+                token: token.clone(),
+            }),
+        }
+    }
+
+    /// Computes the hash of the totals committed by the proof.
+    pub fn hash(&self) -> Digest {
+        let entries: Vec<[u8; 64]> = self
+            .0
+            .iter()
+            .map(|(token, balance)| {
+                let mut entry = [0; 64];
+                entry[..32].copy_from_slice(&token.hash());
+                entry[32..].copy_from_slice(&balance.hash());
+                entry
+            })
+            .collect();
+
+        keccak256_combine(entries.iter().map(|entry| entry.as_slice()))
+    }
+}
+
+/// The balance trees of the networks of a proof before their batches, and the amounts transferred
+/// by the batches, which the new totals are checked against.
+#[derive(Clone, Debug, Default)]
+pub struct SupplyChanges {
+    prev_balances: TokenSupplies,
+    transferred: TokenSupplies,
+}
+
+impl SupplyChanges {
+    /// Creates a new [`SupplyChanges`] without any batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the balance tree of a network before its batch.
+    pub fn add_prev_balance_tree(&mut self, balance_tree: &BalanceTree) {
+        for (token, balance) in balance_tree.iter() {
+            self.prev_balances.add(token.clone(), balance);
+        }
+    }
+
+    /// Adds a transfer of `amount` of `token`, which is both withdrawn and deposited.
+    pub fn add_transfer(&mut self, token: TokenInfo, amount: U256) {
+        self.transferred.add(token, &Balance::new(amount, amount));
+    }
+}
+
+/// The previous totals and the issuance which the new totals of a proof are checked against.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupplyCheck {
+    /// Totals of each token over every network after the previous proof
+    pub prev_totals: TokenSupplies,
+    /// Amounts of each token issued, as deposits, and burnt, as withdrawals, by its origin network
+    /// since the previous proof
+    #[serde(default)]
+    pub issuance: TokenSupplies,
+}
+
+/// Represents all the ways in which the supply of a token is not conserved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SupplyError {
+    /// The token is issued or burnt by its origin network, which is not part of the proof.
+    IssuerNotProven { token: TokenInfo },
+    /// The totals of the token overflow, or the previous totals do not cover the balances of the
+    /// networks of the proof.
+    OutOfRange { token: TokenInfo },
+    /// The new totals of the token are not its previous totals plus its issuance and transfers.
+    NotConserved {
+        token: TokenInfo,
+        expected: Balance,
+        got: Balance,
+    },
+}
+
+impl fmt::Display for SupplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SupplyError::IssuerNotProven { token } => write!(
+                f,
+                "token {} is issued by network {}, which is not part of the proof",
+                token.origin_token_address, *token.origin_network
+            ),
+            SupplyError::OutOfRange { token } => write!(
+                f,
+                "the totals of token {} of network {} are out of range",
+                token.origin_token_address, *token.origin_network
+            ),
+            SupplyError::NotConserved {
+                token,
+                expected,
+                got,
+            } => write!(
+                f,
+                "the totals of token {} of network {} are {got:?}, expected {expected:?}",
+                token.origin_token_address, *token.origin_network
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SupplyError {}
+
+impl SupplyCheck {
+    /// Creates the check of the first proof, whose previous totals are the sums of
+    /// `balance_trees`, the current balance trees of every network, without any issuance.
+    pub fn bootstrap<'a>(
+        balance_trees: impl IntoIterator<Item = &'a BalanceTree>,
+    ) -> Result<Self, SupplyError> {
+        let mut prev_totals = TokenSupplies::new();
+        for balance_tree in balance_trees {
+            for (token, balance) in balance_tree.iter() {
+                prev_totals.checked_add(token, balance)?;
+            }
+        }
+
+        Ok(Self {
+            prev_totals,
+            issuance: TokenSupplies::new(),
+        })
+    }
+
+    /// Applies the issuance of each token to the balance tree of its origin network in
+    /// `balance_trees`, the merged balance trees of the proof.
+    pub fn issue(&self, balance_trees: &mut BalanceTreeByNetwork) -> Result<(), SupplyError> {
+        for (token, issued) in self.issuance.iter() {
+            let balance_tree = balance_trees.get_mut(&token.origin_network).ok_or_else(|| {
+                SupplyError::IssuerNotProven {
+                    token: token.clone(),
+                }
+            })?;
+            balance_tree.deposit(token.clone(), issued.deposited());
+            balance_tree.withdraw(token.clone(), issued.withdrawn());
+        }
+
+        Ok(())
+    }
+
+    /// Computes the new totals over every network, from the merged balance trees of the proof
+    /// with the issuance applied, and checks that they are the previous totals plus the issuance
+    /// and the transfers of `changes`.
+    ///
+    /// The deposits and withdrawals are checked separately: as every transfer adds to both, their
+    /// difference would not catch a transfer recorded on one side only.
+    pub fn check(
+        &self,
+        changes: &SupplyChanges,
+        balance_trees: &BalanceTreeByNetwork,
+    ) -> Result<TokenSupplies, SupplyError> {
+        // The previous totals must cover the networks of the proof before they are replaced
+        let mut new_totals = self.prev_totals.clone();
+        for (token, balance) in changes.prev_balances.iter() {
+            new_totals.checked_sub(token, balance)?;
+        }
+        for balance_tree in balance_trees.values() {
+            for (token, balance) in balance_tree.iter() {
+                new_totals.checked_add(token, balance)?;
+            }
+        }
+
+        let mut expected = self.prev_totals.clone();
+        for (token, balance) in self.issuance.iter().chain(changes.transferred.iter()) {
+            expected.checked_add(token, balance)?;
+        }
+
+        let tokens = expected.0.keys().chain(new_totals.0.keys());
+        for token in tokens {
+            let expected = expected.get(token).cloned().unwrap_or_default();
+            let got = new_totals.get(token).cloned().unwrap_or_default();
+            if expected != got {
+                return Err(SupplyError::NotConserved {
+                    token: token.clone(),
+                    expected,
+                    got,
+                });
+            }
+        }
+
+        Ok(new_totals)
+    }
+
+    /// Starts the check of the next proof from the new `totals` of this one, which applied the
+    /// issuance.
+    pub fn advance(&mut self, totals: TokenSupplies) {
+        self.prev_totals = totals;
+        self.issuance = TokenSupplies::new();
+    }
+
+    /// Computes the hash of the check, part of the hash of the context.
+    pub fn hash(&self) -> Digest {
+        keccak256_combine([self.prev_totals.hash().as_slice(), self.issuance.hash().as_slice()])
+    }
+}
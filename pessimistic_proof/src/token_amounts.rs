@@ -0,0 +1,84 @@
+//! Amounts of tokens attached to networks, as held by the
+//! [`ProofContext`](crate::context::ProofContext): the caps of the
+//! [`RateLimits`](crate::rate_limit::RateLimits), the amounts withdrawn during the epoch which they
+//! apply to, and the [`CreditLines`](crate::credit::CreditLines).
+
+use std::collections::BTreeMap;
+
+use reth_primitives::U256;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::{
+    keccak::{keccak256_combine, Digest},
+    NetworkId, TokenInfo,
+};
+
+/// The amount of each `(network, token)` pair which has one.
+///
+/// Serialized as a sequence of `((network, token), amount)` pairs, as the keys are not valid JSON
+/// keys.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenAmounts(#[serde_as(as = "Vec<(_, _)>")] BTreeMap<(NetworkId, TokenInfo), U256>);
+
+impl TokenAmounts {
+    /// Creates a new [`TokenAmounts`] without any amount.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the amount of `token` for `network`, replacing its previous amount if any.
+    pub fn set(&mut self, network: NetworkId, token: TokenInfo, amount: U256) {
+        self.0.insert((network, token), amount);
+    }
+
+    /// Adds `amount` to the amount of `token` for `network`, and returns the new amount, saturated
+    /// at [`U256::MAX`].
+    pub fn add(&mut self, network: NetworkId, token: TokenInfo, amount: U256) -> U256 {
+        let total = self.0.entry((network, token)).or_default();
+        *total = total.saturating_add(amount);
+        *total
+    }
+
+    /// Removes the amount of `token` for `network`, and returns it if any.
+    pub fn remove(&mut self, network: NetworkId, token: &TokenInfo) -> Option<U256> {
+        self.0.remove(&(network, token.clone()))
+    }
+
+    /// Returns the amount of `token` for `network`, if any.
+    pub fn get(&self, network: NetworkId, token: &TokenInfo) -> Option<U256> {
+        // Avoids cloning the token for every lookup when no amount is set.
+        if self.0.is_empty() {
+            return None;
+        }
+
+        self.0.get(&(network, token.clone())).copied()
+    }
+
+    /// Returns whether no amount is set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the amounts, in `(network, token)` order.
+    pub fn iter(&self) -> impl Iterator<Item = (NetworkId, &TokenInfo, U256)> {
+        self.0.iter().map(|((network, token), amount)| (*network, token, *amount))
+    }
+
+    /// Computes the hash of the amounts committed by the proof.
+    pub fn hash(&self) -> Digest {
+        let entries: Vec<[u8; 68]> = self
+            .iter()
+            .map(|(network, token, amount)| {
+                let mut entry = [0; 68];
+                entry[..4].copy_from_slice(&network.to_be_bytes());
+                entry[4..36].copy_from_slice(&token.hash());
+                entry[36..].copy_from_slice(&amount.to_be_bytes::<32>());
+                entry
+            })
+            .collect();
+
+        keccak256_combine(entries.iter().map(|entry| entry.as_slice()))
+    }
+}
@@ -1,6 +1,6 @@
-use std::ops::Deref;
+use std::{fmt, ops::Deref};
 
-use reth_primitives::{revm_primitives::bitvec::view::BitViewSized, Address, U256};
+use reth_primitives::{Address, U256};
 use serde::{Deserialize, Serialize};
 
 use crate::keccak::{keccak256, keccak256_combine, Digest as KeccakDigest};
@@ -15,6 +15,12 @@ pub struct TokenInfo {
 }
 
 impl TokenInfo {
+    /// ETH, which is identified as the zero address on mainnet.
+    pub const ETH: TokenInfo = TokenInfo {
+        origin_network: NetworkId::new(0),
+        origin_token_address: Address::ZERO,
+    };
+
     /// Computes the Keccak digest of [`TokenInfo`].
     pub fn hash(&self) -> KeccakDigest {
         keccak256_combine([
@@ -24,10 +30,54 @@ impl TokenInfo {
     }
 }
 
+/// The type of a leaf of the local exit tree, serialized as its `u8` value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum LeafType {
+    /// Transfer of `amount` of the token
+    #[default]
+    Asset,
+    /// Message from the `origin_token_address` of the origin network, carrying `amount` of ETH
+    Message,
+}
+
+/// A leaf type other than the ones of [`LeafType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownLeafType(pub u8);
+
+impl fmt::Display for UnknownLeafType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown leaf type {}, expected 0 (asset) or 1 (message)", self.0)
+    }
+}
+
+impl std::error::Error for UnknownLeafType {}
+
+impl TryFrom<u8> for LeafType {
+    type Error = UnknownLeafType;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(LeafType::Asset),
+            1 => Ok(LeafType::Message),
+            _ => Err(UnknownLeafType(value)),
+        }
+    }
+}
+
+impl From<LeafType> for u8 {
+    fn from(value: LeafType) -> Self {
+        match value {
+            LeafType::Asset => 0,
+            LeafType::Message => 1,
+        }
+    }
+}
+
 /// Represents a token withdrawal from the network.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Withdrawal {
-    pub leaf_type: u8,
+    pub leaf_type: LeafType,
 
     /// Unique ID for the token being transferred.
     pub token_info: TokenInfo,
@@ -46,7 +96,7 @@ pub struct Withdrawal {
 impl Withdrawal {
     /// Creates a new [`Withdrawal`].
     pub fn new(
-        leaf_type: u8,
+        leaf_type: LeafType,
         origin_network: NetworkId,
         origin_token_address: Address,
         dest_network: NetworkId,
@@ -67,10 +117,23 @@ impl Withdrawal {
         }
     }
 
+    /// Returns the token whose balance the withdrawal moves from the origin network to the
+    /// destination network.
+    ///
+    /// This is the bridged token for asset transfers. Messages are identified by their sender
+    /// rather than by a token, and their amount is the ETH sent along, which is WETH on networks
+    /// with a custom gas token, so they move ETH.
+    pub fn balance_token(&self) -> TokenInfo {
+        match self.leaf_type {
+            LeafType::Asset => self.token_info.clone(),
+            LeafType::Message => TokenInfo::ETH,
+        }
+    }
+
     /// Hashes the [`Withdrawal`] to be inserted in a [`crate::local_exit_tree::LocalExitTree`].
     pub fn hash(&self) -> KeccakDigest {
         keccak256_combine([
-            self.leaf_type.as_raw_slice(),
+            [u8::from(self.leaf_type)].as_slice(),
             &u32::to_be_bytes(self.token_info.origin_network.into()),
             self.token_info.origin_token_address.as_slice(),
             &u32::to_be_bytes(self.dest_network.into()),
@@ -87,7 +150,7 @@ impl Withdrawal {
 pub struct NetworkId(u32);
 
 impl NetworkId {
-    pub fn new(value: u32) -> Self {
+    pub const fn new(value: u32) -> Self {
         Self(value)
     }
 }
@@ -120,7 +183,7 @@ mod tests {
     #[test]
     fn test_deposit_hash() {
         let mut deposit = Withdrawal::new(
-            0,
+            LeafType::Asset,
             0.into(),
             Address::default(),
             1.into(),
@@ -0,0 +1,414 @@
+mod common;
+
+use poly_pessimistic_proof::{
+    batch::Batch,
+    certificate::{signer_address, Certificate, CertificateError, SigningKey},
+    committee::Committee,
+    compact::{encode_batches, CompactBatches},
+    context::ProofContext,
+    digest::input_digests,
+    generate_certified_proof, generate_certified_proof_compact,
+    local_balance_tree::{Balance, Deposit},
+    pause::{PauseError, Paused},
+    registry::{ChainType, NetworkConfig},
+    NetworkId, ProofError, ProofOutput, TokenInfo, Withdrawal,
+};
+use reth_primitives::U256;
+
+use crate::common::{eth, make_batch, make_withdrawal};
+
+fn signing_key(network: u32) -> SigningKey {
+    SigningKey::from_slice(&[network as u8 + 1; 32]).unwrap()
+}
+
+/// The 100 ETH held by a network before its batch.
+fn balances() -> Vec<(TokenInfo, Balance)> {
+    vec![(eth(), Deposit(U256::from(100)).into())]
+}
+
+/// The withdrawal of 10 ETH to `dest_network`.
+fn send(dest_network: u32) -> Vec<Withdrawal> {
+    vec![make_withdrawal(&eth(), dest_network, 10, Vec::new())]
+}
+
+/// The context in which networks 0 to 2 are registered and no certificate is settled.
+fn context() -> ProofContext {
+    let mut context = ProofContext::new();
+    for network in 0..3_u32 {
+        let config = NetworkConfig {
+            rollup_index: network.checked_sub(1),
+            chain_type: if network == 0 {
+                ChainType::Mainnet
+            } else {
+                ChainType::Rollup
+            },
+            signer: signer_address(&signing_key(network)),
+            gas_token: eth(),
+            enabled: true,
+        };
+        context.registry.register(network.into(), config);
+    }
+    context
+}
+
+fn certify(batch: Batch, height: u64) -> Certificate {
+    let key = signing_key(*batch.origin_network);
+    Certificate::new(batch, height, &key)
+}
+
+/// Generates the proof from both input formats, checking that they agree.
+fn prove(context: &ProofContext, certificates: &[Certificate]) -> Result<ProofOutput, ProofError> {
+    let batches: Vec<Batch> = certificates.iter().map(|c| c.batch.clone()).collect();
+    let headers: Vec<_> = certificates.iter().map(|c| c.header.clone()).collect();
+    let bytes = encode_batches(&batches);
+    let compact = CompactBatches::new(&bytes).unwrap();
+
+    let output = generate_certified_proof(context, certificates);
+    let compact_output = generate_certified_proof_compact(context, &headers, &compact);
+    assert_eq!(format!("{compact_output:?}"), format!("{output:?}"));
+
+    output
+}
+
+fn certificate_error(result: Result<ProofOutput, ProofError>) -> CertificateError {
+    match result {
+        Err(ProofError::InvalidCertificate(error)) => error,
+        other => panic!("expected a certificate error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_valid_certificates() {
+    let context = context();
+    let certificates = vec![
+        certify(make_batch(0, 3, balances(), send(1)), 0),
+        certify(make_batch(1, 3, balances(), send(0)), 0),
+    ];
+
+    let output = prove(&context, &certificates).unwrap();
+    assert_eq!(output.context_hash, context.hash());
+    assert_eq!(output.registry_root, context.registry.root());
+    assert_eq!(output.rate_limits, context.rate_limits.hash());
+    assert_eq!(output.credit_lines, context.credit_lines.hash());
+    assert!(output.credit_used.is_empty());
+    assert_eq!(output.heights.len(), 2);
+    assert_eq!(
+        output.exit_roots.get(&NetworkId::new(0)),
+        Some(&certificates[0].header.new_local_exit_root)
+    );
+
+    // The next certificate of each network follows the settled one.
+    let mut settled = context.clone();
+    settled.settle(&output.heights);
+    assert_ne!(settled.hash(), context.hash());
+    assert_eq!(settled.next_height(0.into()), 1);
+    assert!(prove(&settled, &[certify(make_batch(0, 3, balances(), send(1)), 1)]).is_ok());
+}
+
+#[test]
+fn test_input_digests() {
+    let context = context();
+    let certificates = vec![
+        certify(make_batch(0, 3, balances(), send(1)), 0),
+        certify(make_batch(1, 3, balances(), send(2)), 0),
+    ];
+    let batches: Vec<Batch> = certificates.iter().map(|c| c.batch.clone()).collect();
+
+    let output = prove(&context, &certificates).unwrap();
+    assert_eq!(output.input_digests, input_digests(&batches));
+
+    // The digest depends on the network and on the withdrawals.
+    let digests = &output.input_digests;
+    assert_ne!(digests[&0.into()], digests[&1.into()]);
+    let mut other = make_batch(0, 3, balances(), send(1));
+    other.withdrawals[0].amount = U256::from(20);
+    assert_ne!(other.input_digest(), digests[&0.into()]);
+    other.withdrawals.clear();
+    assert_ne!(other.input_digest(), digests[&0.into()]);
+}
+
+#[test]
+fn test_replayed_certificate() {
+    let mut context = context();
+    context.heights.insert(0.into(), 0);
+
+    for height in [0, 2] {
+        assert_eq!(
+            certificate_error(prove(
+                &context,
+                &[certify(make_batch(0, 3, balances(), send(1)), height)]
+            )),
+            CertificateError::UnexpectedHeight {
+                network: 0.into(),
+                got: height,
+                expected: 1,
+            }
+        );
+    }
+}
+
+#[test]
+fn test_wrong_signer() {
+    let context = context();
+
+    let certificate = Certificate::new(make_batch(0, 3, balances(), send(1)), 0, &signing_key(1));
+    assert_eq!(
+        certificate_error(prove(&context, &[certificate])),
+        CertificateError::InvalidSignature { network: 0.into() }
+    );
+
+    assert_eq!(
+        certificate_error(prove(&context, &[certify(make_batch(3, 3, balances(), send(1)), 0)])),
+        CertificateError::UnknownNetwork { network: 3.into() }
+    );
+}
+
+#[test]
+fn test_tampered_certificate() {
+    let context = context();
+
+    // The batch no longer leads to the signed exit root.
+    let mut certificate = certify(make_batch(0, 3, balances(), send(1)), 0);
+    certificate.batch.withdrawals[0].amount = U256::from(20);
+    assert_eq!(
+        certificate_error(prove(&context, &[certificate])),
+        CertificateError::HeaderMismatch { network: 0.into() }
+    );
+
+    // The header no longer matches its signature.
+    let mut certificate = certify(make_batch(0, 3, balances(), send(1)), 0);
+    certificate.header.new_local_exit_root = [0; 32];
+    assert_eq!(
+        certificate_error(prove(&context, &[certificate])),
+        CertificateError::InvalidSignature { network: 0.into() }
+    );
+
+    // The height cannot be bumped to replay a settled certificate.
+    let mut settled = context.clone();
+    settled.heights.insert(0.into(), 0);
+    let mut certificate = certify(make_batch(0, 3, balances(), send(1)), 0);
+    certificate.header.height = 1;
+    assert_eq!(
+        certificate_error(prove(&settled, &[certificate])),
+        CertificateError::InvalidSignature { network: 0.into() }
+    );
+}
+
+#[test]
+fn test_duplicate_network() {
+    let context = context();
+    let certificates = vec![
+        certify(make_batch(0, 3, balances(), send(1)), 0),
+        certify(make_batch(0, 3, balances(), send(1)), 0),
+    ];
+
+    assert_eq!(
+        certificate_error(prove(&context, &certificates)),
+        CertificateError::DuplicateNetwork { network: 0.into() }
+    );
+}
+
+#[test]
+fn test_unknown_destination() {
+    let context = context();
+
+    assert!(matches!(
+        prove(&context, &[certify(make_batch(0, 3, balances(), send(3)), 0)]),
+        Err(ProofError::UnknownDestination { dest_network, .. }) if *dest_network == 3
+    ));
+}
+
+#[test]
+fn test_disabled_network() {
+    let mut context = context();
+    let root = context.registry.root();
+    assert!(context.registry.set_enabled(2.into(), false));
+    assert!(!context.registry.set_enabled(3.into(), false));
+    assert_ne!(context.registry.root(), root);
+
+    assert!(matches!(
+        prove(&context, &[certify(make_batch(0, 3, balances(), send(2)), 0)]),
+        Err(ProofError::DisabledDestination { dest_network, .. }) if *dest_network == 2
+    ));
+    assert_eq!(
+        certificate_error(prove(&context, &[certify(make_batch(2, 3, balances(), send(1)), 0)])),
+        CertificateError::DisabledNetwork { network: 2.into() }
+    );
+
+    // The other networks still settle among themselves.
+    assert!(prove(&context, &[certify(make_batch(0, 3, balances(), send(1)), 0)]).is_ok());
+}
+
+#[test]
+fn test_paused_withdrawals() {
+    let mut context = context();
+    let eth = make_batch(0, 3, balances(), send(1)).withdrawals[0].token_info.clone();
+    let hash = context.hash();
+
+    // Pausing network 1 stops its withdrawals only.
+    assert!(context.paused.set_network_paused(1.into(), true));
+    assert!(!context.paused.set_network_paused(1.into(), true));
+    assert_ne!(context.hash(), hash);
+    assert!(matches!(
+        prove(&context, &[certify(make_batch(1, 3, balances(), send(0)), 0)]),
+        Err(ProofError::Paused(PauseError::PausedNetwork { network })) if *network == 1
+    ));
+    let output = prove(&context, &[certify(make_batch(0, 3, balances(), send(1)), 0)]).unwrap();
+    assert_eq!(output.paused, context.paused.hash());
+
+    // Pausing a token stops its withdrawals from every network.
+    assert!(context.paused.set_token_paused(eth.clone(), true));
+    assert!(matches!(
+        prove(&context, &[certify(make_batch(0, 3, balances(), send(1)), 0)]),
+        Err(ProofError::Paused(PauseError::PausedToken { network, token }))
+            if *network == 0 && token == eth
+    ));
+    assert_eq!(
+        context.paused.iter().collect::<Vec<_>>(),
+        vec![Paused::Network(1.into()), Paused::Token(eth.clone())]
+    );
+
+    assert!(context.paused.set_network_paused(1.into(), false));
+    assert!(context.paused.set_token_paused(eth, false));
+    assert!(context.paused.is_empty());
+    assert_eq!(context.hash(), hash);
+    assert!(prove(&context, &[certify(make_batch(1, 3, balances(), send(0)), 0)]).is_ok());
+}
+
+/// Signing key of the member `index` of the committee.
+fn member_key(index: u8) -> SigningKey {
+    SigningKey::from_slice(&[0x80 + index; 32]).unwrap()
+}
+
+/// The context in which network 1 is a validium with a 2-of-3 committee.
+fn validium_context() -> ProofContext {
+    let mut context = context();
+    context.registry.networks.get_mut(&1.into()).unwrap().chain_type = ChainType::Validium;
+    let members = (0..3).map(|index| signer_address(&member_key(index))).collect();
+    context.committees.insert(1.into(), Committee::new(members, 2));
+    context
+}
+
+#[test]
+fn test_committee_attestation() {
+    let context = validium_context();
+    let committee = &context.committees[&1.into()];
+
+    let mut certificate = certify(make_batch(1, 3, balances(), send(0)), 0);
+    certificate.attest(&member_key(0));
+    certificate.attest(&member_key(2));
+    let output =
+        prove(&context, &[certify(make_batch(0, 3, balances(), send(1)), 0), certificate]).unwrap();
+    assert_eq!(output.committees.len(), 1);
+    assert_eq!(output.committees.get(&1.into()), Some(&committee.hash()));
+
+    // Other committees yield other context hashes.
+    let mut other = context.clone();
+    other.committees.get_mut(&1.into()).unwrap().threshold = 1;
+    assert_ne!(other.hash(), context.hash());
+}
+
+#[test]
+fn test_insufficient_attestations() {
+    let context = validium_context();
+
+    let mut certificate = certify(make_batch(1, 3, balances(), send(0)), 0);
+    certificate.attest(&member_key(1));
+    assert_eq!(
+        certificate_error(prove(&context, &[certificate.clone()])),
+        CertificateError::InsufficientAttestations {
+            network: 1.into(),
+            got: 1,
+            threshold: 2
+        }
+    );
+
+    // The same member cannot sign twice.
+    certificate.attest(&member_key(1));
+    assert_eq!(
+        certificate_error(prove(&context, &[certificate])),
+        CertificateError::InvalidAttestation { network: 1.into() }
+    );
+}
+
+#[test]
+fn test_invalid_attestations() {
+    let context = validium_context();
+
+    // Signed by a key outside of the committee.
+    let mut certificate = certify(make_batch(1, 3, balances(), send(0)), 0);
+    certificate.attest(&member_key(0));
+    certificate.attest(&member_key(3));
+    assert_eq!(
+        certificate_error(prove(&context, &[certificate])),
+        CertificateError::InvalidAttestation { network: 1.into() }
+    );
+
+    // Attesting other withdrawals.
+    let mut certificate = certify(make_batch(1, 3, balances(), send(0)), 0);
+    certificate.attest(&member_key(0));
+    certificate.attest(&member_key(1));
+    let mut other = certify(make_batch(1, 3, balances(), send(2)), 0);
+    other.header.committee_signatures = certificate.header.committee_signatures;
+    assert_eq!(
+        certificate_error(prove(&context, &[other])),
+        CertificateError::InvalidAttestation { network: 1.into() }
+    );
+}
+
+#[test]
+fn test_invalid_threshold() {
+    let mut context = validium_context();
+
+    // A threshold of zero would accept a certificate without any attestation.
+    context.committees.get_mut(&1.into()).unwrap().threshold = 0;
+    assert_eq!(
+        certificate_error(prove(&context, &[certify(make_batch(1, 3, balances(), send(0)), 0)])),
+        CertificateError::InvalidThreshold {
+            network: 1.into(),
+            threshold: 0,
+            members: 3
+        }
+    );
+
+    // A threshold above the number of members cannot be reached.
+    context.committees.get_mut(&1.into()).unwrap().threshold = 4;
+    let mut certificate = certify(make_batch(1, 3, balances(), send(0)), 0);
+    for index in 0..3 {
+        certificate.attest(&member_key(index));
+    }
+    assert_eq!(
+        certificate_error(prove(&context, &[certificate])),
+        CertificateError::InvalidThreshold {
+            network: 1.into(),
+            threshold: 4,
+            members: 3
+        }
+    );
+}
+
+#[test]
+fn test_replayed_attestations() {
+    let mut context = validium_context();
+    let mut empty_batch = make_batch(1, 3, balances(), send(0));
+    empty_batch.withdrawals.clear();
+
+    let mut certificate = certify(empty_batch.clone(), 0);
+    certificate.attest(&member_key(0));
+    certificate.attest(&member_key(1));
+    let output = prove(&context, &[certificate.clone()]).unwrap();
+    context.settle(&output.heights);
+
+    // The next empty certificate has the same withdrawals hash, but another height.
+    let mut next = certify(empty_batch, 1);
+    next.header.committee_signatures = certificate.header.committee_signatures;
+    assert_eq!(
+        certificate_error(prove(&context, &[next.clone()])),
+        CertificateError::InvalidAttestation { network: 1.into() }
+    );
+
+    next.header.committee_signatures.clear();
+    next.attest(&member_key(0));
+    next.attest(&member_key(1));
+    assert!(prove(&context, &[next]).is_ok());
+}
@@ -0,0 +1,60 @@
+//! Fixtures shared by the integration tests, each of which only uses some of them.
+#![allow(dead_code)]
+
+use poly_pessimistic_proof::{
+    batch::Batch,
+    local_balance_tree::Balance,
+    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    LeafType, TokenInfo, Withdrawal,
+};
+use reth_primitives::{address, U256};
+
+pub fn eth() -> TokenInfo {
+    TokenInfo::ETH
+}
+
+pub fn usdc() -> TokenInfo {
+    TokenInfo {
+        origin_network: 0.into(),
+        origin_token_address: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+    }
+}
+
+/// An asset withdrawal of `amount` of `token` to `dest_network`, along with `metadata`.
+pub fn make_withdrawal(
+    token: &TokenInfo,
+    dest_network: u32,
+    amount: u32,
+    metadata: Vec<u8>,
+) -> Withdrawal {
+    Withdrawal::new(
+        LeafType::Asset,
+        token.origin_network,
+        token.origin_token_address,
+        dest_network.into(),
+        address!("a8da6bf26964af9d7eed9e03e53415d37aa96045"),
+        U256::from(amount),
+        metadata,
+    )
+}
+
+/// A batch of `origin_network` after `prev_leaf_count` withdrawals, holding `balances` and making
+/// `withdrawals`.
+pub fn make_batch(
+    origin_network: u32,
+    prev_leaf_count: u8,
+    balances: Vec<(TokenInfo, Balance)>,
+    withdrawals: Vec<Withdrawal>,
+) -> Batch {
+    let prev_local_exit_tree: LocalExitTree<Keccak256Hasher> =
+        LocalExitTree::from_leaves((0..prev_leaf_count).map(|i| [i; 32]));
+    let prev_local_exit_root = prev_local_exit_tree.get_root();
+
+    Batch::new(
+        origin_network.into(),
+        prev_local_exit_tree,
+        prev_local_exit_root,
+        balances.into(),
+        withdrawals,
+    )
+}
@@ -1,15 +1,30 @@
+mod common;
+
 use poly_pessimistic_proof::{
     batch::Batch,
-    generate_full_proof,
+    compact::{encode_batches, CompactBatches},
+    context::ProofContext,
+    credit::CreditUsed,
+    generate_full_proof, generate_full_proof_compact,
+    ingest::{
+        calldata::{BridgeContext, GasToken},
+        logs::read_logs,
+        EventData,
+    },
     local_balance_tree::{Balance, BalanceTree, Deposit},
     local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
-    ProofError, TokenInfo, Withdrawal,
+    pause::PauseList,
+    rate_limit::EpochWithdrawn,
+    supply::{SupplyCheck, SupplyError, TokenSupplies},
+    LeafType, NetworkId, ProofError, TokenInfo, Withdrawal,
 };
-use reth_primitives::{address, U256};
+use reth_primitives::{address, b256, hex, U256};
+
+use crate::common::{eth, usdc};
 
 fn make_tx(_from: u32, to: u32, token: &TokenInfo, amount: u32) -> Withdrawal {
     Withdrawal::new(
-        0,
+        LeafType::Asset,
         token.origin_network,
         token.origin_token_address,
         to.into(),
@@ -71,7 +86,7 @@ fn test_full_proof() {
 
         // Compute the full proof
         assert!(matches!(
-            generate_full_proof(&batches),
+            generate_full_proof(&ProofContext::new(), &batches),
             Err(ProofError::NotEnoughBalance { .. })
         ));
     }
@@ -94,12 +109,491 @@ fn test_full_proof() {
         ];
 
         // Compute the full proof
-        assert!(generate_full_proof(&batches).is_ok());
+        assert!(generate_full_proof(&ProofContext::new(), &batches).is_ok());
     }
 }
 
+/// The batches of [`test_full_proof`], networks 0 and 1 starting with the given ETH and USDC
+/// deposits.
+fn exchange_batches(deposits_0: [u32; 2], deposits_1: [u32; 2]) -> Vec<Batch> {
+    let dummy: LocalExitTree<Keccak256Hasher> =
+        LocalExitTree::from_leaves([[0_u8; 32], [1_u8; 32], [2_u8; 32]].into_iter());
+    let dummy_root = dummy.get_root();
+    let balance_tree = |[eth_amount, usdc_amount]: [u32; 2]| {
+        BalanceTree::from(vec![
+            (eth(), Deposit(U256::from(eth_amount)).into()),
+            (usdc(), Deposit(U256::from(usdc_amount)).into()),
+        ])
+    };
+
+    vec![
+        Batch::new(
+            0.into(),
+            dummy.clone(),
+            dummy_root,
+            balance_tree(deposits_0),
+            vec![make_tx(0, 1, &eth(), 10), make_tx(0, 1, &usdc(), 100)],
+        ),
+        Batch::new(
+            1.into(),
+            dummy,
+            dummy_root,
+            balance_tree(deposits_1),
+            vec![make_tx(1, 0, &eth(), 20), make_tx(1, 0, &usdc(), 200)],
+        ),
+    ]
+}
+
+#[test]
+fn test_credit_lines() {
+    let batches = exchange_batches([10, 10], [1, 200]);
+
+    // Network 1 overdraws 9 ETH, which only a credit line of at least 9 ETH allows
+    let mut context = ProofContext::new();
+    context.credit_lines.set(1.into(), eth(), U256::from(8));
+    context.credit_lines.set(1.into(), usdc(), U256::from(100));
+    assert!(matches!(
+        generate_full_proof(&context, &batches),
+        Err(ProofError::NotEnoughBalance { debtors }) if debtors == vec![1.into()]
+    ));
+
+    context.credit_lines.set(1.into(), eth(), U256::from(9));
+    let output = generate_full_proof(&context, &batches).unwrap();
+    assert_eq!(
+        output.credit_used,
+        vec![CreditUsed {
+            network: 1.into(),
+            token: eth(),
+            amount: U256::from(9),
+        }]
+    );
+}
+
+#[test]
+fn test_rate_limits() {
+    let batches = exchange_batches([12, 102], [20, 201]);
+
+    // Capping the USDC withdrawn by network 1 below its 200 withdrawn
+    let mut context = ProofContext::new();
+    context.rate_limits.set(1.into(), usdc(), U256::from(150));
+    context.rate_limits.set(0.into(), usdc(), U256::from(100));
+    assert!(matches!(
+        generate_full_proof(&context, &batches),
+        Err(ProofError::RateLimitExceeded { network, token, limit, attempted })
+            if *network == 1 && token == usdc() && limit == U256::from(150)
+                && attempted == U256::from(200)
+    ));
+
+    context.rate_limits.set(1.into(), usdc(), U256::from(200));
+    assert!(generate_full_proof(&context, &batches).is_ok());
+}
+
+#[test]
+fn test_rate_limits_span_epoch() {
+    let usdc = usdc();
+
+    let dummy: LocalExitTree<Keccak256Hasher> = LocalExitTree::new();
+    let dummy_root = dummy.get_root();
+    let batch = |amount: u32| {
+        let balance_tree =
+            BalanceTree::from(vec![(usdc.clone(), Deposit(U256::from(1000)).into())]);
+        let withdrawals = vec![make_tx(0, 1, &usdc, amount)];
+        vec![Batch::new(0.into(), dummy.clone(), dummy_root, balance_tree, withdrawals)]
+    };
+
+    let mut context = ProofContext::new();
+    context.rate_limits.set(0.into(), usdc.clone(), U256::from(150));
+
+    // The first proof of the epoch withdraws 100 USDC, within the cap
+    let output = generate_full_proof(&context, &batch(100)).unwrap();
+    assert_eq!(output.withdrawn.get(0.into(), &usdc), Some(U256::from(100)));
+    assert_compact_equivalent(&context, &batch(100));
+
+    // The next proof of the epoch starts from there, so another 100 USDC go over the cap
+    context.withdrawn = output.withdrawn;
+    assert!(matches!(
+        generate_full_proof(&context, &batch(100)),
+        Err(ProofError::RateLimitExceeded { attempted, .. }) if attempted == U256::from(200)
+    ));
+    assert_compact_equivalent(&context, &batch(100));
+    let output = generate_full_proof(&context, &batch(50)).unwrap();
+    assert_eq!(output.withdrawn.get(0.into(), &usdc), Some(U256::from(150)));
+
+    // The amounts withdrawn are part of the context, and reset with the epoch
+    let hash = context.hash();
+    context.withdrawn = EpochWithdrawn::new();
+    assert_ne!(context.hash(), hash);
+    assert!(generate_full_proof(&context, &batch(100)).is_ok());
+}
+
+#[test]
+fn test_supply_conservation() {
+    let dummy: LocalExitTree<Keccak256Hasher> = LocalExitTree::new();
+    let dummy_root = dummy.get_root();
+    let batch = |network: u32, balance: u32, withdrawals: Vec<Withdrawal>| {
+        let balance_tree = BalanceTree::from(vec![(eth(), Deposit(U256::from(balance)).into())]);
+        Batch::new(network.into(), dummy.clone(), dummy_root, balance_tree, withdrawals)
+    };
+    let totals = |deposited: u32, withdrawn: u32| {
+        let mut totals = TokenSupplies::new();
+        totals.add(eth(), &Balance::new(U256::from(deposited), U256::from(withdrawn)));
+        totals
+    };
+    let check = |prev_totals: TokenSupplies, issuance: TokenSupplies| {
+        let mut context = ProofContext::new();
+        context.supply = Some(SupplyCheck {
+            prev_totals,
+            issuance,
+        });
+        context
+    };
+
+    // 130 ETH in total, of which 100 on network 2 which is not part of the proof, and 5 move from
+    // network 0 to network 1
+    let batches = vec![batch(0, 10, vec![make_tx(0, 1, &eth(), 5)]), batch(1, 20, Vec::new())];
+    let context = check(totals(130, 0), TokenSupplies::new());
+    let output = generate_full_proof(&context, &batches).unwrap();
+    assert_eq!(output.supplies, Some(totals(135, 5)));
+    assert_compact_equivalent(&context, &batches);
+
+    // The 4 ETH issued by network 0 back its withdrawals beyond its deposits
+    let batches = vec![batch(0, 10, vec![make_tx(0, 1, &eth(), 14)]), batch(1, 20, Vec::new())];
+    assert!(matches!(
+        generate_full_proof(&check(totals(130, 0), TokenSupplies::new()), &batches),
+        Err(ProofError::NotEnoughBalance { debtors }) if debtors == vec![0.into()]
+    ));
+    let context = check(totals(130, 0), totals(4, 0));
+    let output = generate_full_proof(&context, &batches).unwrap();
+    assert_eq!(output.supplies, Some(totals(148, 14)));
+    assert_compact_equivalent(&context, &batches);
+
+    // Burning 4 ETH leaves network 0 with 1 ETH after its transfer
+    let batches = vec![batch(0, 10, vec![make_tx(0, 1, &eth(), 5)]), batch(1, 20, Vec::new())];
+    let context = check(totals(130, 0), totals(0, 4));
+    let output = generate_full_proof(&context, &batches).unwrap();
+    assert_eq!(output.supplies, Some(totals(135, 9)));
+    assert_compact_equivalent(&context, &batches);
+
+    // Only the origin network of a token may issue it, and it must be part of the proof
+    let foreign = TokenInfo {
+        origin_network: 2.into(),
+        origin_token_address: eth().origin_token_address,
+    };
+    let mut issuance = TokenSupplies::new();
+    issuance.add(foreign.clone(), &Deposit(U256::from(4)).into());
+    let context = check(totals(130, 0), issuance);
+    assert!(matches!(
+        generate_full_proof(&context, &batches),
+        Err(ProofError::SupplyNotConserved(SupplyError::IssuerNotProven { token }))
+            if token == foreign
+    ));
+    assert_compact_equivalent(&context, &batches);
+
+    // The previous totals must cover the networks of the proof
+    let context = check(totals(20, 0), TokenSupplies::new());
+    assert!(matches!(
+        generate_full_proof(&context, &batches),
+        Err(ProofError::SupplyNotConserved(SupplyError::OutOfRange { token })) if token == eth()
+    ));
+    assert_compact_equivalent(&context, &batches);
+
+    // Two batches of network 0 in one proof keep only the balance tree of the last one, which
+    // drops the 5 ETH moved to network 1 unnoticed unless the supply is checked
+    let batches = vec![batch(0, 10, vec![make_tx(0, 1, &eth(), 5)]), batch(0, 10, Vec::new())];
+    let output = generate_full_proof(&ProofContext::new(), &batches).unwrap();
+    assert_eq!(output.supplies, None);
+    let context = check(totals(130, 0), TokenSupplies::new());
+    assert!(matches!(
+        generate_full_proof(&context, &batches),
+        Err(ProofError::SupplyNotConserved(SupplyError::NotConserved { token, expected, got }))
+            if token == eth()
+                && expected == Balance::new(U256::from(135), U256::from(5))
+                && got == Balance::from(Deposit(U256::from(120)))
+    ));
+    assert_compact_equivalent(&context, &batches);
+}
+
+#[test]
+fn test_supply_bootstrap() {
+    let dummy: LocalExitTree<Keccak256Hasher> = LocalExitTree::new();
+    let dummy_root = dummy.get_root();
+    let balance_tree =
+        |balance: u32| BalanceTree::from(vec![(eth(), Deposit(U256::from(balance)).into())]);
+    let batches = vec![
+        Batch::new(
+            0.into(),
+            dummy.clone(),
+            dummy_root,
+            balance_tree(10),
+            vec![make_tx(0, 1, &eth(), 5)],
+        ),
+        Batch::new(1.into(), dummy.clone(), dummy_root, balance_tree(20), Vec::new()),
+    ];
+
+    // Without any totals, the check fails at the first proof
+    let mut context = ProofContext::new();
+    context.supply = Some(SupplyCheck::default());
+    assert!(matches!(
+        generate_full_proof(&context, &batches),
+        Err(ProofError::SupplyNotConserved(SupplyError::OutOfRange { token })) if token == eth()
+    ));
+
+    // The totals are bootstrapped from every network, including network 2 which is not part of
+    // the proof
+    let supply =
+        SupplyCheck::bootstrap([&balance_tree(10), &balance_tree(20), &balance_tree(100)]).unwrap();
+    assert_eq!(supply.prev_totals.get(&eth()), Some(&Balance::from(Deposit(U256::from(130)))));
+    context.supply = Some(supply);
+    let supplies = generate_full_proof(&context, &batches).unwrap().supplies.unwrap();
+    assert_eq!(supplies.get(&eth()), Some(&Balance::new(U256::from(135), U256::from(5))));
+    assert_compact_equivalent(&context, &batches);
+
+    let full = BalanceTree::from(vec![(eth(), Deposit(U256::MAX).into())]);
+    assert_eq!(
+        SupplyCheck::bootstrap([&full, &balance_tree(1)]),
+        Err(SupplyError::OutOfRange { token: eth() })
+    );
+}
+
+/// The `BridgeEvent` logs, as returned by `eth_getLogs`, of a `bridgeMessage` call on network 1
+/// which sends 5 wei to mainnet, and of a `bridgeMessageWETH` call on network 2, whose gas token is
+/// not ETH, which sends 7 WETH to network 1.
+const MESSAGE_LOGS: &str = concat!(
+    "[",
+    r#"{"address":"0x2a3dd3eb832af982ec71669e178424b10dca2ede","topics":["0x501781209a1f8899323b96b4ef08b168df93e0a90c673d1e4cce39366cb62f9b"],"data":"0x"#,
+    "0000000000000000000000000000000000000000000000000000000000000001", // leafType
+    "0000000000000000000000000000000000000000000000000000000000000001", // originNetwork
+    "0000000000000000000000005fbdb2315678afecb367f032d93f642f64180aa3", // originAddress
+    "0000000000000000000000000000000000000000000000000000000000000000", // destinationNetwork
+    "00000000000000000000000031bc2a964c8cc585ef366e225ea3a5e2a352c287", // destinationAddress
+    "0000000000000000000000000000000000000000000000000000000000000005", // amount
+    "0000000000000000000000000000000000000000000000000000000000000100", // metadata offset
+    "0000000000000000000000000000000000000000000000000000000000000000", // depositCount
+    "0000000000000000000000000000000000000000000000000000000000000004", // metadata length
+    "deadbeef00000000000000000000000000000000000000000000000000000000", // metadata
+    r#"","blockNumber":"0x4a1","transactionHash":"0x0101010101010101010101010101010101010101010101010101010101010101","transactionIndex":"0x0","logIndex":"0x1","removed":false}"#,
+    ",",
+    r#"{"address":"0x2a3dd3eb832af982ec71669e178424b10dca2ede","topics":["0x501781209a1f8899323b96b4ef08b168df93e0a90c673d1e4cce39366cb62f9b"],"data":"0x"#,
+    "0000000000000000000000000000000000000000000000000000000000000001", // leafType
+    "0000000000000000000000000000000000000000000000000000000000000002", // originNetwork
+    "0000000000000000000000005561134ff5b24700d1f8d45fc59f73d023effeb2", // originAddress
+    "0000000000000000000000000000000000000000000000000000000000000001", // destinationNetwork
+    "000000000000000000000000a8da6bf26964af9d7eed9e03e53415d37aa96045", // destinationAddress
+    "0000000000000000000000000000000000000000000000000000000000000007", // amount
+    "0000000000000000000000000000000000000000000000000000000000000100", // metadata offset
+    "0000000000000000000000000000000000000000000000000000000000000003", // depositCount
+    "0000000000000000000000000000000000000000000000000000000000000020", // metadata length
+    "000000000000000000000000000000000000000000000000000000000000002a", // metadata
+    r#"","blockNumber":"0x2d7","transactionHash":"0x0202020202020202020202020202020202020202020202020202020202020202","transactionIndex":"0x3","logIndex":"0x5","removed":false}"#,
+    "]"
+);
+
+/// The calldata of the `bridgeMessage` call, whose value is the 5 wei.
+const BRIDGE_MESSAGE_CALLDATA: &str = concat!(
+    "240ff378",                                                         // selector
+    "0000000000000000000000000000000000000000000000000000000000000000", // destinationNetwork
+    "00000000000000000000000031bc2a964c8cc585ef366e225ea3a5e2a352c287", // destinationAddress
+    "0000000000000000000000000000000000000000000000000000000000000001", // forceUpdateGlobalExitRoot
+    "0000000000000000000000000000000000000000000000000000000000000080", // metadata offset
+    "0000000000000000000000000000000000000000000000000000000000000004", // metadata length
+    "deadbeef00000000000000000000000000000000000000000000000000000000", // metadata
+);
+
+/// The calldata of the `bridgeMessageWETH` call, whose value is zero.
+const BRIDGE_MESSAGE_WETH_CALLDATA: &str = concat!(
+    "b8b284d0",                                                         // selector
+    "0000000000000000000000000000000000000000000000000000000000000001", // destinationNetwork
+    "000000000000000000000000a8da6bf26964af9d7eed9e03e53415d37aa96045", // destinationAddress
+    "0000000000000000000000000000000000000000000000000000000000000007", // amountWETH
+    "0000000000000000000000000000000000000000000000000000000000000000", // forceUpdateGlobalExitRoot
+    "00000000000000000000000000000000000000000000000000000000000000a0", // metadata offset
+    "0000000000000000000000000000000000000000000000000000000000000020", // metadata length
+    "000000000000000000000000000000000000000000000000000000000000002a", // metadata
+);
+
+#[test]
+fn test_message_leaves() {
+    let senders = [
+        TokenInfo {
+            origin_network: 1.into(),
+            origin_token_address: address!("5fbdb2315678afecb367f032d93f642f64180aa3"),
+        },
+        TokenInfo {
+            origin_network: 2.into(),
+            origin_token_address: address!("5561134ff5b24700d1f8d45fc59f73d023effeb2"),
+        },
+    ];
+
+    let messages: Vec<Withdrawal> = read_logs(MESSAGE_LOGS.as_bytes())
+        .unwrap()
+        .into_iter()
+        .map(|event| match event.event_data {
+            EventData::Deposit(deposit) => Withdrawal::from(deposit),
+            other => panic!("expected a deposit, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(messages.len(), 2);
+    for (message, sender) in messages.iter().zip(&senders) {
+        assert_eq!((message.leaf_type, &message.token_info), (LeafType::Message, sender));
+        assert_eq!(message.balance_token(), eth());
+    }
+
+    // The leaves commit to the sender in place of the token, as `getLeafValue` of the bridge
+    let leaves: Vec<_> = messages.iter().map(Withdrawal::hash).collect();
+    assert_eq!(
+        leaves,
+        vec![
+            b256!("8534aeed6a8de750162d50f45e86cea8a4d02c8c9fcddc27b7dbc4e3f6e9c2f8").0,
+            b256!("cff41ea0fe3f559547ff398b0d866f20f62a2c579778b4c55c9109252b42a448").0,
+        ]
+    );
+
+    // The calls which emitted the events build the same leaves
+    let mut weth_network = BridgeContext::new(2.into());
+    weth_network.gas_token = Some(GasToken {
+        token_info: TokenInfo {
+            origin_network: 0.into(),
+            origin_token_address: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+        },
+        metadata: Vec::new(),
+    });
+    weth_network.weth_token = Some(address!("2222222222222222222222222222222222222222"));
+    let calls = [
+        BridgeContext::new(1.into()).decode_calldata(
+            senders[0].origin_token_address,
+            U256::from(5),
+            &hex::decode(BRIDGE_MESSAGE_CALLDATA).unwrap(),
+        ),
+        weth_network.decode_calldata(
+            senders[1].origin_token_address,
+            U256::ZERO,
+            &hex::decode(BRIDGE_MESSAGE_WETH_CALLDATA).unwrap(),
+        ),
+    ];
+    let call_leaves: Vec<_> = calls.iter().map(|call| call.as_ref().unwrap().hash()).collect();
+    assert_eq!(call_leaves, leaves);
+
+    let dummy: LocalExitTree<Keccak256Hasher> = LocalExitTree::new();
+    let dummy_root = dummy.get_root();
+    let batch = |network: u32, balances: Vec<(TokenInfo, Balance)>, message: &Withdrawal| {
+        Batch::new(
+            network.into(),
+            dummy.clone(),
+            dummy_root,
+            balances.into(),
+            vec![message.clone()],
+        )
+    };
+
+    // Each message debits the ETH of its network, and never the balance of its sender nor the gas
+    // token of the network
+    let batches = vec![
+        batch(1, vec![(eth(), Deposit(U256::from(5)).into())], &messages[0]),
+        batch(2, vec![(eth(), Deposit(U256::from(7)).into())], &messages[1]),
+    ];
+    let balance_trees = batches[1].compute_new_balance_tree(&PauseList::new()).unwrap();
+    let debited = &balance_trees[&NetworkId::new(2)];
+    assert_eq!(debited.get(&eth()), Some(&Balance::new(U256::from(7), U256::from(7))));
+    assert_eq!(debited.len(), 1);
+    let credited = &balance_trees[&NetworkId::new(1)];
+    assert_eq!(credited.get(&eth()), Some(&Balance::from(Deposit(U256::from(7)))));
+
+    let mut totals = TokenSupplies::new();
+    totals.add(eth(), &Balance::from(Deposit(U256::from(12))));
+    let mut context = ProofContext::new();
+    context.supply = Some(SupplyCheck {
+        prev_totals: totals,
+        issuance: TokenSupplies::new(),
+    });
+    let supplies = generate_full_proof(&context, &batches).unwrap().supplies.unwrap();
+    assert_eq!(supplies.get(&eth()), Some(&Balance::new(U256::from(24), U256::from(12))));
+    assert!(senders.iter().all(|sender| supplies.get(sender).is_none()));
+    assert_compact_equivalent(&context, &batches);
+
+    // Holding the sender token does not pay for the ETH carried by the message
+    let batches = vec![batch(
+        1,
+        vec![(senders[0].clone(), Deposit(U256::from(5)).into())],
+        &messages[0],
+    )];
+    assert!(matches!(
+        generate_full_proof(&ProofContext::new(), &batches),
+        Err(ProofError::NotEnoughBalance { debtors }) if debtors == vec![1.into()]
+    ));
+    assert_compact_equivalent(&ProofContext::new(), &batches);
+}
+
 #[test]
 #[ignore = "not implemented yet"]
 fn test_full_proof_mainnet_data() {
     // from data fetched from mainnet
 }
+
+fn assert_compact_equivalent(context: &ProofContext, batches: &[Batch]) {
+    let bytes = encode_batches(batches);
+    let compact = CompactBatches::new(&bytes).unwrap();
+
+    match (
+        generate_full_proof(context, batches),
+        generate_full_proof_compact(context, &compact),
+    ) {
+        (Ok(expected), Ok(got)) => assert_eq!(got, expected),
+        (Err(expected), Err(got)) => assert_eq!(format!("{got:?}"), format!("{expected:?}")),
+        (expected, got) => panic!("expected {expected:?}, got {got:?}"),
+    }
+}
+
+#[test]
+fn test_compact_input_equivalence() {
+    let tokens: Vec<TokenInfo> = (0..4_u8)
+        .map(|i| TokenInfo {
+            origin_network: (i as u32 % 2).into(),
+            origin_token_address: [i; 20].into(),
+        })
+        .collect();
+
+    let make_batch = |origin_network: u32, withdrawal_count: u32, initial_balance: u32| {
+        let prev_local_exit_tree: LocalExitTree<Keccak256Hasher> =
+            LocalExitTree::from_leaves((0..origin_network + 5).map(|i| [i as u8; 32]));
+        let prev_local_exit_root = prev_local_exit_tree.get_root();
+
+        let balances = tokens
+            .iter()
+            .map(|token| (token.clone(), Deposit(U256::from(initial_balance)).into()))
+            .collect::<Vec<(TokenInfo, Balance)>>();
+
+        let withdrawals = (0..withdrawal_count)
+            .map(|i| {
+                let token = &tokens[i as usize % tokens.len()];
+                let mut withdrawal = make_tx(origin_network, (origin_network + i) % 3, token, i);
+                withdrawal.leaf_type = if i % 2 == 0 { LeafType::Asset } else { LeafType::Message };
+                withdrawal.metadata = vec![i as u8; i as usize % 70];
+                withdrawal
+            })
+            .collect();
+
+        Batch::new(
+            origin_network.into(),
+            prev_local_exit_tree,
+            prev_local_exit_root,
+            balances.into(),
+            withdrawals,
+        )
+    };
+
+    let context = ProofContext::new();
+    assert_compact_equivalent(&context, &[]);
+    assert_compact_equivalent(&context, &[make_batch(0, 0, 0)]);
+    assert_compact_equivalent(&context, &[make_batch(0, 50, 10_000), make_batch(1, 20, 10_000)]);
+    assert_compact_equivalent(&context, &[make_batch(0, 50, 10), make_batch(2, 80, 10)]);
+
+    let mut invalid_root = make_batch(1, 3, 100);
+    invalid_root.prev_local_exit_root = [0xff; 32];
+    assert_compact_equivalent(&context, &[make_batch(0, 3, 100), invalid_root]);
+
+    let mut limited = context.clone();
+    limited.rate_limits.set(0.into(), tokens[1].clone(), U256::from(600));
+    assert_compact_equivalent(&limited, &[make_batch(0, 50, 10_000), make_batch(1, 20, 10_000)]);
+    limited.rate_limits.set(0.into(), tokens[1].clone(), U256::from(300));
+    assert_compact_equivalent(&limited, &[make_batch(0, 50, 10_000), make_batch(1, 20, 10_000)]);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use poly_pessimistic_proof::{
+    ingest::ClaimEventData,
+    ledger::{Claim, Corridor, CorridorFlows, LedgerError, ReconciliationLedger},
+    local_balance_tree::{Balance, Deposit},
+    registry::{ChainType, NetworkConfig, NetworkRegistry},
+    TokenInfo, Withdrawal,
+};
+use reth_primitives::{Address, U256};
+
+use crate::common::{make_batch, make_withdrawal, usdc};
+
+/// The 1000 USDC held by a network before its batch.
+fn balances() -> Vec<(TokenInfo, Balance)> {
+    vec![(usdc(), Deposit(U256::from(1000)).into())]
+}
+
+/// The withdrawals of `amounts` of USDC to `dest_network`.
+fn send(dest_network: u32, amounts: &[u32]) -> Vec<Withdrawal> {
+    amounts
+        .iter()
+        .map(|amount| make_withdrawal(&usdc(), dest_network, *amount, Vec::new()))
+        .collect()
+}
+
+/// The registry in which the network 2 has the rollup index 0, and the network 1 the index 1.
+fn registry() -> NetworkRegistry {
+    let mut registry = NetworkRegistry::new();
+    for (network, rollup_index) in [(0, None), (1, Some(1)), (2, Some(0))] {
+        let config = NetworkConfig {
+            rollup_index,
+            chain_type: if network == 0 {
+                ChainType::Mainnet
+            } else {
+                ChainType::Rollup
+            },
+            signer: Address::ZERO,
+            gas_token: usdc(),
+            enabled: true,
+        };
+        registry.register(network.into(), config);
+    }
+    registry
+}
+
+/// The claim of a `ClaimEvent` emitted on `dest_network`, as read from an event dump.
+fn claim(dest_network: u32, global_index: &str, amount: u32) -> Result<Claim, LedgerError> {
+    let event: ClaimEventData = serde_json::from_str(&format!(
+        r#"{{"index":"{global_index}","originNetwork":0,"originAddress":"0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48","destinationAddress":"0x31bc2a964c8cc585ef366e225ea3a5e2a352c287","amount":{amount}}}"#
+    ))
+    .unwrap();
+
+    Claim::from_event(&registry(), dest_network.into(), &event)
+}
+
+fn corridor(origin_network: u32, dest_network: u32) -> Corridor {
+    Corridor {
+        origin_network: origin_network.into(),
+        dest_network: dest_network.into(),
+        token: usdc(),
+    }
+}
+
+#[test]
+fn test_claims_settle_pending_exits() {
+    let batches = [
+        make_batch(0, 3, balances(), send(2, &[10, 20])),
+        make_batch(2, 3, balances(), send(1, &[5])),
+    ];
+
+    let mut ledger = ReconciliationLedger::new();
+    let flows = ledger.record(7, &batches).unwrap();
+    assert_eq!(flows, CorridorFlows::from_batches(&batches));
+    assert_eq!(
+        flows.iter().collect::<Vec<_>>(),
+        vec![(&corridor(0, 2), U256::from(30)), (&corridor(2, 1), U256::from(5))]
+    );
+
+    let pending = ledger.pending_exit(0.into(), 4).unwrap();
+    assert_eq!((pending.amount, pending.epoch), (U256::from(20), 7));
+    let root = ledger.root();
+
+    // Deposit 4 of mainnet, claimed on network 2.
+    let from_mainnet = claim(2, "18446744073709551620", 20).unwrap();
+    assert_eq!((*from_mainnet.origin_network, from_mainnet.deposit_count), (0, 4));
+    ledger.settle(&from_mainnet).unwrap();
+    assert_ne!(ledger.root(), root);
+    assert_eq!(
+        ledger.settle(&from_mainnet),
+        Err(LedgerError::UnknownExit {
+            origin_network: 0.into(),
+            deposit_count: 4,
+        })
+    );
+
+    // Deposit 3 of the rollup 0, that is network 2, claimed on the wrong network then on network 1.
+    let global_index = 3_u64.to_string();
+    assert_eq!(
+        ledger.settle(&claim(0, &global_index, 5).unwrap()),
+        Err(LedgerError::MismatchedClaim {
+            origin_network: 2.into(),
+            deposit_count: 3,
+        })
+    );
+    ledger.settle(&claim(1, &global_index, 5).unwrap()).unwrap();
+
+    // The same legacy index emitted on network 2 itself claims the deposit 3 of mainnet.
+    let legacy = claim(2, &global_index, 10).unwrap();
+    assert_eq!((*legacy.origin_network, legacy.deposit_count), (0, 3));
+    ledger.settle(&legacy).unwrap();
+
+    // No network is registered with the rollup index 2.
+    assert_eq!(
+        claim(1, &((2_u64 << 32) | 3).to_string(), 5),
+        Err(LedgerError::UnknownRollup { rollup_index: 2 })
+    );
+
+    let corridors: Vec<_> = ledger
+        .corridors()
+        .map(|(corridor, balance)| {
+            (corridor.clone(), balance.pending, balance.settled, balance.pending_exits)
+        })
+        .collect();
+    assert_eq!(
+        corridors,
+        vec![
+            (corridor(0, 2), U256::ZERO, U256::from(30), 0),
+            (corridor(2, 1), U256::ZERO, U256::from(5), 0),
+        ]
+    );
+}
+
+#[test]
+fn test_exits_recorded_once() {
+    let batches = [make_batch(0, 3, balances(), send(2, &[10, 20]))];
+    let mut ledger = ReconciliationLedger::new();
+    ledger.record(7, &batches).unwrap();
+    ledger.settle(&claim(2, "18446744073709551620", 20).unwrap()).unwrap();
+    let recorded = ledger.clone();
+
+    // Recording the batch again, even along with a new one, counts none of its exits twice,
+    // including the settled one.
+    let next = make_batch(2, 3, balances(), send(1, &[5]));
+    assert_eq!(
+        ledger.record(8, [&next, &batches[0]]),
+        Err(LedgerError::AlreadyRecorded {
+            origin_network: 0.into(),
+            deposit_count: 3,
+        })
+    );
+    assert_eq!(ledger, recorded);
+
+    // The following withdrawals of the network are recorded.
+    let following = make_batch(0, 5, balances(), send(2, &[7]));
+    ledger.record(8, [&next, &following]).unwrap();
+    assert_eq!(ledger.pending_exit(0.into(), 5).unwrap().amount, U256::from(7));
+    assert_eq!(ledger.pending_exit(2.into(), 3).unwrap().epoch, 8);
+}
@@ -1,54 +1,114 @@
 use poly_pessimistic_proof::{
+    ingest::{read_sorted_bridge_events, BridgeEvent, DepositEventData, EventData},
+    keccak::Digest,
     local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
-    test_utils::{parse_json_file, BridgeEvent, EventData},
-    Withdrawal,
+    replay::{replay, ExitRootKind, ReplayError, ReplaySummary},
+    LeafType, Withdrawal,
 };
+use reth_primitives::{Address, U256};
 const JSON_FILE_PATH: &str = "tests/data/bridge_events_10k.json";
 
 #[test]
 fn test_local_exit_root() {
-    let mut local_exit_tree: LocalExitTree<Keccak256Hasher> = LocalExitTree::new();
-
-    let bridge_events: Vec<BridgeEvent> = read_sorted_bridge_events();
-
-    let mut deposit_count: u32 = 0;
-    for event in bridge_events {
-        match event.event_data {
-            EventData::UpdateL1InfoTree {
-                mainnet_exit_root,
-                rollup_exit_root: _,
-            } => {
-                let computed_root = local_exit_tree.get_root();
-
-                assert_eq!(computed_root, mainnet_exit_root);
-            }
-            EventData::Deposit(deposit_event_data) => {
-                assert_eq!(deposit_event_data.deposit_count, deposit_count);
-                deposit_count += 1;
-
-                let withdrawal: Withdrawal = deposit_event_data.into();
-                local_exit_tree.add_leaf(withdrawal.hash());
-            }
-            EventData::Claim(_) => {
-                // do nothing
-            }
-        }
+    let bridge_events: Vec<BridgeEvent> =
+        read_sorted_bridge_events(JSON_FILE_PATH).expect("failed to read the bridge events");
+
+    let summary = replay(bridge_events.into_iter().map(Ok)).unwrap();
+    assert!(summary.mainnet_checkpoints > 0);
+}
+
+fn event(block_number: u64, event_data: EventData) -> BridgeEvent {
+    BridgeEvent {
+        removed: false,
+        block_number,
+        transaction_index: 0,
+        log_index: 0,
+        transaction_hash: String::new(),
+        event_type: 0,
+        event_data,
     }
 }
 
-/// Reads the bridge events from disk, and sorts by (block number, tx index, log index).
-fn read_sorted_bridge_events() -> Vec<BridgeEvent> {
-    let mut bridge_events: Vec<BridgeEvent> = parse_json_file(JSON_FILE_PATH);
-    bridge_events.sort_unstable_by(|a, b| {
-        use std::cmp::Ordering;
-        match a.block_number.cmp(&b.block_number) {
-            Ordering::Equal => match a.transaction_index.cmp(&b.transaction_index) {
-                Ordering::Equal => a.log_index.cmp(&b.log_index),
-                not_eq => not_eq,
-            },
-            not_eq => not_eq,
+fn replay_events(events: &[BridgeEvent]) -> Result<ReplaySummary, ReplayError> {
+    replay(events.iter().cloned().map(Ok))
+}
+
+fn deposit(deposit_count: u32) -> EventData {
+    EventData::Deposit(DepositEventData {
+        leaf_type: LeafType::Asset,
+        origin_network: 0,
+        origin_address: Address::ZERO,
+        destination_network: 1,
+        destination_address: Address::repeat_byte(0xaa),
+        amount: U256::from(deposit_count + 1),
+        metadata: Vec::new(),
+        deposit_count,
+    })
+}
+
+fn leaf(deposit_count: u32) -> Digest {
+    match deposit(deposit_count) {
+        EventData::Deposit(deposit) => Withdrawal::from(deposit).hash(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_replay_checkpoints() {
+    let mainnet_exit_root = |leaf_count: u32| {
+        LocalExitTree::<Keccak256Hasher>::from_leaves((0..leaf_count).map(leaf)).get_root()
+    };
+    let rollup_exit_root = LocalExitTree::<Keccak256Hasher>::from_leaves(
+        [[1; 32], Digest::default(), [3; 32]].into_iter(),
+    )
+    .get_root();
+
+    let checkpoint = |leaf_count, rollup_exit_root| EventData::UpdateL1InfoTree {
+        mainnet_exit_root: mainnet_exit_root(leaf_count),
+        rollup_exit_root,
+    };
+    let verify_batches = |rollup_id: u32| EventData::VerifyBatches {
+        rollup_id,
+        exit_root: [rollup_id as u8; 32],
+    };
+
+    let events = vec![
+        event(1, deposit(0)),
+        event(2, checkpoint(1, [0xff; 32])),
+        event(3, deposit(1)),
+        event(3, verify_batches(3)),
+        event(4, verify_batches(1)),
+        event(5, checkpoint(2, rollup_exit_root)),
+    ];
+
+    let summary = replay_events(&events).unwrap();
+    assert_eq!(summary.deposits, 2);
+    assert_eq!(summary.mainnet_checkpoints, 2);
+    assert_eq!(summary.rollup_checkpoints, 1);
+    assert_eq!(summary.rollup_exit_root, rollup_exit_root);
+
+    // Missing rollup exit root update
+    let mut diverging = events.clone();
+    diverging.remove(4);
+    match replay_events(&diverging) {
+        Err(ReplayError::Divergence(divergence)) => {
+            assert_eq!(divergence.kind, ExitRootKind::Rollup);
+            assert_eq!(divergence.block_number, 5);
+            assert_eq!(divergence.leaf_count, 2);
         }
-    });
+        other => panic!("expected a divergence, got {other:?}"),
+    }
 
-    bridge_events
+    // Missing deposit
+    let mut missing_deposit = events.clone();
+    missing_deposit[2] = event(3, deposit(2));
+    assert!(matches!(
+        replay_events(&missing_deposit),
+        Err(ReplayError::DepositCountMismatch {
+            block_number: 3,
+            expected: 1,
+            got: 2,
+            ..
+        })
+    ));
 }
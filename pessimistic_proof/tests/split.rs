@@ -0,0 +1,141 @@
+mod common;
+
+use poly_pessimistic_proof::{
+    batch::Batch,
+    context::ProofContext,
+    cost::{CostFeatures, CostModel},
+    estimate_cost, generate_full_proof,
+    local_balance_tree::{Balance, BalanceTreeByNetwork, Deposit},
+    pause::PauseList,
+    split::{check_chain, split_batch, split_batch_by_len, SplitError},
+    TokenInfo, Withdrawal,
+};
+use reth_primitives::U256;
+
+use crate::common::{eth, make_batch, make_withdrawal, usdc};
+
+/// The ETH and USDC held by network 1 before its batch.
+fn balances() -> Vec<(TokenInfo, Balance)> {
+    vec![
+        (eth(), Deposit(U256::from(1_000_000)).into()),
+        (usdc(), Deposit(U256::from(1_000_000)).into()),
+    ]
+}
+
+/// `count` withdrawals of ETH and USDC, with metadata of various sizes.
+fn withdrawals(count: u32) -> Vec<Withdrawal> {
+    (0..count)
+        .map(|i| {
+            let token = if i % 3 == 0 { usdc() } else { eth() };
+            // Some withdrawals go back to the origin network
+            make_withdrawal(&token, i % 4, 100 + i, vec![i as u8; (i % 5) as usize])
+        })
+        .collect()
+}
+
+/// Proves the sub-batches in sequence, checking that each exit root is the start of the next
+/// sub-batch, and returns the roots of the origin network after the last one.
+fn prove_chain(sub_batches: &[Batch]) -> ([u8; 32], [u8; 32]) {
+    let origin_network = sub_batches[0].origin_network;
+    let mut roots = None;
+
+    for sub_batch in sub_batches {
+        if let Some((exit_root, _)) = roots {
+            assert_eq!(sub_batch.prev_local_exit_root, exit_root);
+        }
+
+        let output =
+            generate_full_proof(&ProofContext::new(), std::slice::from_ref(sub_batch)).unwrap();
+        roots = Some((output.exit_roots[&origin_network], output.balance_roots[&origin_network]));
+    }
+
+    roots.unwrap()
+}
+
+#[test]
+fn test_split_by_len_preserves_final_roots() {
+    let batch = make_batch(1, 5, balances(), withdrawals(23));
+    let expected = prove_chain(std::slice::from_ref(&batch));
+
+    for max_withdrawals in [1, 2, 5, 22, 23, 100] {
+        let sub_batches = split_batch_by_len(&batch, max_withdrawals);
+
+        assert_eq!(sub_batches.len(), 23_usize.div_ceil(max_withdrawals));
+        assert!(sub_batches
+            .iter()
+            .all(|sub_batch| sub_batch.withdrawals.len() <= max_withdrawals));
+        check_chain(&sub_batches).unwrap();
+        assert_eq!(prove_chain(&sub_batches), expected);
+    }
+}
+
+#[test]
+fn test_split_destination_balances() {
+    let batch = make_batch(1, 5, balances(), withdrawals(23));
+    let origin_network = batch.origin_network;
+    let balance_trees = |batch: &Batch| {
+        let mut balance_trees = batch.compute_new_balance_tree(&PauseList::new()).unwrap();
+        balance_trees.remove(&origin_network);
+        balance_trees
+    };
+    let expected = balance_trees(&batch);
+
+    let sub_batches = split_batch_by_len(&batch, 5);
+    let mut merged = BalanceTreeByNetwork::new();
+    for sub_batch in &sub_batches {
+        merged.merge(&balance_trees(sub_batch));
+    }
+
+    // The deposits of the sub-batches add up to those of the whole batch, while the last sub-proof
+    // only covers its own.
+    let last_output =
+        generate_full_proof(&ProofContext::new(), &sub_batches[sub_batches.len() - 1..]).unwrap();
+    assert_eq!(merged.len(), expected.len());
+    for (network, balance_tree) in expected.iter() {
+        assert_eq!(merged[network].hash(), balance_tree.hash());
+        assert_ne!(last_output.balance_roots.get(network), Some(&balance_tree.hash()));
+    }
+}
+
+#[test]
+fn test_split_fits_budget() {
+    let batch = make_batch(1, 5, balances(), withdrawals(50));
+    let expected = prove_chain(std::slice::from_ref(&batch));
+    let model = CostModel::CALIBRATED;
+    // Each sub-batch pays for its certificate, and for about a third of the withdrawals.
+    let certificate_cycles = estimate_cost(&[Batch {
+        withdrawals: Vec::new(),
+        ..batch.clone()
+    }]);
+    let max_cycles =
+        certificate_cycles + (estimate_cost(std::slice::from_ref(&batch)) - certificate_cycles) / 3;
+
+    let sub_batches = split_batch(&batch, &model, max_cycles).unwrap();
+
+    assert!(sub_batches.len() >= 3);
+    for sub_batch in &sub_batches {
+        assert!(model.estimate(&CostFeatures::of(std::slice::from_ref(sub_batch))) <= max_cycles);
+    }
+    check_chain(&sub_batches).unwrap();
+    assert_eq!(prove_chain(&sub_batches), expected);
+
+    // A batch within the budget is left whole.
+    let sub_batches =
+        split_batch(&batch, &model, estimate_cost(std::slice::from_ref(&batch))).unwrap();
+    assert_eq!(sub_batches.len(), 1);
+}
+
+#[test]
+fn test_split_errors() {
+    let batch = make_batch(1, 5, balances(), withdrawals(10));
+    let model = CostModel::CALIBRATED;
+
+    assert!(matches!(
+        split_batch(&batch, &model, 1),
+        Err(SplitError::WithdrawalTooLarge { index: 0, .. })
+    ));
+
+    let mut sub_batches = split_batch_by_len(&batch, 3);
+    sub_batches.swap(1, 2);
+    assert_eq!(check_chain(&sub_batches), Err(SplitError::BrokenChain { index: 1 }));
+}
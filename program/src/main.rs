@@ -1,13 +1,32 @@
 #![no_main]
 
-use poly_pessimistic_proof::{batch::Batch, generate_full_proof};
+use poly_pessimistic_proof::{
+    certificate::{Certificate, CertificateHeader},
+    compact::{CompactBatches, InputFormat},
+    context::ProofContext,
+    generate_certified_proof, generate_certified_proof_compact,
+};
 
 sp1_zkvm::entrypoint!(main);
 
 pub fn main() {
-    let batches = sp1_zkvm::io::read::<Vec<Batch>>();
+    let context = sp1_zkvm::io::read::<ProofContext>();
 
-    let new_roots = generate_full_proof(&batches).unwrap();
+    let output = match sp1_zkvm::io::read::<InputFormat>() {
+        InputFormat::Serde => {
+            let certificates = sp1_zkvm::io::read::<Vec<Certificate>>();
 
-    sp1_zkvm::io::commit(&new_roots);
+            generate_certified_proof(&context, &certificates)
+        }
+        InputFormat::Compact => {
+            let headers = sp1_zkvm::io::read::<Vec<CertificateHeader>>();
+            let input = sp1_zkvm::io::read_vec();
+            let batches = CompactBatches::new(&input).unwrap();
+
+            generate_certified_proof_compact(&context, &headers, &batches)
+        }
+    }
+    .unwrap();
+
+    sp1_zkvm::io::commit(&output);
 }
@@ -0,0 +1,375 @@
+//! Aggregation of the certificates submitted by networks into epochs, each proven at once.
+//!
+//! A certificate is the batch of a network for the open epoch, signed by its sequencer at the
+//! height following its last proven certificate. It is validated on submission by running
+//! [`generate_certified_proof`] natively on the whole epoch, so that invalid certificates are
+//! refused before reaching the prover. Closing the epoch proves its certificates together and
+//! records the new local exit and balance tree roots, which the next certificates of each network
+//! must start from, along with their heights. The withdrawals of the proven epochs are pending
+//! exits of the [`ReconciliationLedger`] until their destination network imports their claims.
+//!
+//! The state is saved to a JSON file after every change, so that the aggregator resumes where it
+//! stopped after a restart.
+
+use std::{collections::BTreeMap, fmt, fs, path::PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use poly_pessimistic_proof::{
+    certificate::{Certificate, CertificateError},
+    context::ProofContext,
+    generate_certified_proof,
+    keccak::Digest,
+    ledger::{Claim, ReconciliationLedger},
+    rate_limit::EpochWithdrawn,
+    BalanceRoot, ExitRoot, NetworkId, ProofError,
+};
+use serde::{Deserialize, Serialize};
+use sp1_sdk::ProverClient;
+
+use crate::{
+    exits::{ExitIndex, ExitProof, ExitProofError},
+    input::{read_json, write_json},
+    keys::KeyCache,
+    prover,
+};
+
+/// Represents all the reasons for refusing a certificate.
+#[derive(Debug)]
+pub enum Rejection {
+    /// The network already has a certificate in the open epoch.
+    AlreadyQueued { network: NetworkId },
+    /// The certificate does not start from the local exit root of the last proven epoch.
+    UnexpectedExitRoot {
+        network: NetworkId,
+        got: ExitRoot,
+        expected: ExitRoot,
+    },
+    /// The certificate does not start from the balance tree of the last proven epoch.
+    UnexpectedBalanceRoot {
+        network: NetworkId,
+        got: BalanceRoot,
+        expected: BalanceRoot,
+    },
+    /// The certificate is not signed by the sequencer of the network at the expected height.
+    InvalidCertificate(CertificateError),
+    /// The open epoch would not be provable with the certificate.
+    InvalidEpoch(ProofError),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::AlreadyQueued { network } => write!(
+                f,
+                "network {} already has a certificate in the open epoch",
+                **network
+            ),
+            Rejection::UnexpectedExitRoot {
+                network,
+                got,
+                expected,
+            } => write!(
+                f,
+                "network {} starts from the local exit root 0x{}, expected 0x{}",
+                **network,
+                hex::encode(got),
+                hex::encode(expected)
+            ),
+            Rejection::UnexpectedBalanceRoot {
+                network,
+                got,
+                expected,
+            } => write!(
+                f,
+                "network {} starts from the balance tree root 0x{}, expected 0x{}",
+                **network,
+                hex::encode(got),
+                hex::encode(expected)
+            ),
+            Rejection::InvalidCertificate(error) => write!(f, "invalid certificate: {error}"),
+            Rejection::InvalidEpoch(error) => write!(f, "invalid certificate: {error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// The position of an accepted certificate.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// Epoch which the certificate is queued in
+    pub epoch: u64,
+    /// Position of the certificate in the epoch
+    pub position: usize,
+}
+
+/// An epoch whose certificates are proven.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvenEpoch {
+    pub epoch: u64,
+    /// Networks of the certificates, in the order in which they were proven
+    pub networks: Vec<NetworkId>,
+    /// File of the proof artifact
+    pub proof: PathBuf,
+    pub exit_roots: BTreeMap<NetworkId, ExitRoot>,
+    pub balance_roots: BTreeMap<NetworkId, BalanceRoot>,
+    /// Height of the proven certificate of each network
+    #[serde(default)]
+    pub heights: BTreeMap<NetworkId, u64>,
+    /// Root of the reconciliation ledger once the withdrawals of the epoch are recorded
+    #[serde(default)]
+    pub ledger_root: Digest,
+}
+
+/// The state of the aggregator, as saved between restarts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AggregatorState {
+    /// Number of the open epoch
+    pub epoch: u64,
+    /// Certificates queued in the open epoch
+    pub pending: Vec<Certificate>,
+    /// Local exit root of each network after the last proven epoch
+    pub exit_roots: BTreeMap<NetworkId, ExitRoot>,
+    /// Balance tree root of each network after the last proven epoch
+    pub balance_roots: BTreeMap<NetworkId, BalanceRoot>,
+    /// Proven epochs, oldest first
+    pub proven: Vec<ProvenEpoch>,
+    /// Withdrawals of the proven epochs
+    #[serde(default)]
+    pub exits: ExitIndex,
+    /// Pending and settled exits of each corridor
+    #[serde(default)]
+    pub ledger: ReconciliationLedger,
+    /// Registered networks, and height of the last proven certificate of each network
+    #[serde(default)]
+    pub context: ProofContext,
+}
+
+/// Queues certificates per epoch and proves the epochs.
+pub struct Aggregator {
+    state: AggregatorState,
+    state_path: PathBuf,
+    proof_dir: PathBuf,
+    client: ProverClient,
+    keys: KeyCache,
+}
+
+impl Aggregator {
+    /// Opens the aggregator whose state is saved at `state_path`, starting from an empty state if
+    /// the file does not exist. Proof artifacts are saved in `proof_dir`.
+    ///
+    /// The networks, their committees, rate limits and pause list are the ones of `context`, while
+    /// their heights and the totals of the supply check, if `context` has one, are resumed from the
+    /// saved state.
+    pub fn open(
+        state_path: impl Into<PathBuf>,
+        context: ProofContext,
+        proof_dir: impl Into<PathBuf>,
+        client: ProverClient,
+        keys: KeyCache,
+    ) -> Result<Self> {
+        let state_path = state_path.into();
+        let mut state = if state_path.exists() {
+            read_json(&state_path)?
+        } else {
+            AggregatorState {
+                context: context.clone(),
+                ..Default::default()
+            }
+        };
+        // A supply check is only resumed if the context still has one, whose totals the saved
+        // state has advanced since.
+        let supply = context
+            .supply
+            .map(|supply| state.context.supply.take().unwrap_or(supply));
+        state.context = ProofContext {
+            heights: std::mem::take(&mut state.context.heights),
+            supply,
+            ..context
+        };
+
+        Ok(Self {
+            state,
+            state_path,
+            proof_dir: proof_dir.into(),
+            client,
+            keys,
+        })
+    }
+
+    pub fn state(&self) -> &AggregatorState {
+        &self.state
+    }
+
+    /// Validates the certificate and queues it in the open epoch.
+    ///
+    /// Refused certificates yield a [`Rejection`], other errors are failures to save the state.
+    pub fn submit(&mut self, certificate: Certificate) -> Result<Receipt> {
+        self.validate(&certificate)?;
+
+        self.state.pending.push(certificate);
+        self.save()?;
+
+        Ok(Receipt {
+            epoch: self.state.epoch,
+            position: self.state.pending.len() - 1,
+        })
+    }
+
+    fn validate(&self, certificate: &Certificate) -> Result<(), Rejection> {
+        let network = certificate.header.network;
+
+        if self
+            .state
+            .pending
+            .iter()
+            .any(|pending| pending.header.network == network)
+        {
+            return Err(Rejection::AlreadyQueued { network });
+        }
+
+        if let Some(expected) = self.state.exit_roots.get(&network) {
+            if certificate.batch.prev_local_exit_root != *expected {
+                return Err(Rejection::UnexpectedExitRoot {
+                    network,
+                    got: certificate.batch.prev_local_exit_root,
+                    expected: *expected,
+                });
+            }
+        }
+
+        if let Some(expected) = self.state.balance_roots.get(&network) {
+            let got = certificate.batch.prev_local_balance_tree.hash();
+            if got != *expected {
+                return Err(Rejection::UnexpectedBalanceRoot {
+                    network,
+                    got,
+                    expected: *expected,
+                });
+            }
+        }
+
+        let mut epoch = self.state.pending.clone();
+        epoch.push(certificate.clone());
+        generate_certified_proof(&self.state.context, &epoch).map_err(|error| match error {
+            ProofError::InvalidCertificate(error) => Rejection::InvalidCertificate(error),
+            error => Rejection::InvalidEpoch(error),
+        })?;
+
+        Ok(())
+    }
+
+    /// Proves the certificates of the open epoch and opens the next one.
+    ///
+    /// Returns `None` without opening a new epoch if no certificate is queued.
+    pub fn close_epoch(&mut self) -> Result<Option<ProvenEpoch>> {
+        if self.state.pending.is_empty() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&self.proof_dir)
+            .with_context(|| format!("failed to create {}", self.proof_dir.display()))?;
+        let proof = self
+            .proof_dir
+            .join(format!("epoch-{}.bin", self.state.epoch));
+        let artifact = prover::prove(
+            &self.client,
+            &self.keys,
+            &self.state.context,
+            &self.state.pending,
+            &proof,
+        )?;
+        let output = artifact.output();
+        let batches: Vec<_> = self
+            .state
+            .pending
+            .iter()
+            .map(|certificate| certificate.batch.clone())
+            .collect();
+        let supply = prover::next_supply(&self.state.context, &batches, &output)?;
+
+        let mut ledger = self.state.ledger.clone();
+        let flows = ledger.record(
+            self.state.epoch,
+            self.state
+                .pending
+                .iter()
+                .map(|certificate| &certificate.batch),
+        )?;
+        ensure!(
+            flows.hash() == output.corridor_flows,
+            "the corridor flows of epoch {} differ from the ones of its proof",
+            self.state.epoch
+        );
+
+        let proven = ProvenEpoch {
+            epoch: self.state.epoch,
+            networks: self
+                .state
+                .pending
+                .iter()
+                .map(|certificate| certificate.header.network)
+                .collect(),
+            proof,
+            exit_roots: output.exit_roots,
+            balance_roots: output.balance_roots,
+            heights: output.heights,
+            ledger_root: ledger.root(),
+        };
+
+        self.state.exit_roots.extend(&proven.exit_roots);
+        self.state.balance_roots.extend(&proven.balance_roots);
+        self.state.context.settle(&proven.heights);
+        // Each epoch is proven at once, and the next one starts without any withdrawal.
+        self.state.context.withdrawn = EpochWithdrawn::new();
+        self.state.context.supply = supply;
+        self.state.exits.index(
+            proven.epoch,
+            self.state
+                .pending
+                .iter()
+                .map(|certificate| &certificate.batch),
+        );
+        self.state.ledger = ledger;
+        self.state.pending.clear();
+        self.state.proven.push(proven.clone());
+        self.state.epoch += 1;
+        self.save()?;
+
+        Ok(Some(proven))
+    }
+
+    /// Returns the proof of the withdrawal of `network` with the given deposit count, against the
+    /// roots of the last proven epoch.
+    pub fn exit_proof(
+        &self,
+        network: NetworkId,
+        deposit_count: u32,
+    ) -> Result<ExitProof, ExitProofError> {
+        self.state
+            .exits
+            .exit_proof(&self.state.context.registry, network, deposit_count)
+    }
+
+    /// Settles the pending exits matching the claims imported by their destination network.
+    ///
+    /// The claims are all settled or, if any does not match a pending exit, none is.
+    pub fn import_claims(&mut self, claims: &[Claim]) -> Result<()> {
+        let mut ledger = self.state.ledger.clone();
+        for claim in claims {
+            ledger.settle(claim)?;
+        }
+
+        self.state.ledger = ledger;
+        self.save()
+    }
+
+    /// Saves the state, replacing the previous file only once the new one is fully written.
+    fn save(&self) -> Result<()> {
+        let tmp_path = self.state_path.with_extension("tmp");
+        write_json(&tmp_path, &self.state)?;
+
+        fs::rename(&tmp_path, &self.state_path)
+            .with_context(|| format!("failed to replace {}", self.state_path.display()))
+    }
+}
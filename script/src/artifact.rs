@@ -0,0 +1,136 @@
+//! On-disk proof artifacts, so that proofs can be archived and re-verified later.
+//!
+//! An artifact file is the bincode encoding of a [`ProofArtifact`]:
+//!
+//! | Field            | Content                                                             |
+//! |------------------|---------------------------------------------------------------------|
+//! | `format_version` | [`FORMAT_VERSION`], bumped on any change of this layout             |
+//! | `crate_version`  | Version of `poly-pessimistic-proof` the program was built with      |
+//! | `vkey_hash`      | Hash of the program verifying key, as `0x`-prefixed hex             |
+//! | `stdin_digest`   | Keccak digest of the program input, see [`stdin_digest`]            |
+//! | `public_values`  | Raw public values committed by the program                          |
+//! | `proof`          | The SP1 proof itself, which also carries the public values          |
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use poly_pessimistic_proof::{
+    keccak::{keccak256_combine, Digest},
+    ProofOutput,
+};
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{HashableKey, ProverClient, SP1Proof, SP1Stdin, SP1VerifyingKey};
+
+use crate::prover::read_output;
+
+/// Version of the artifact layout.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// A proof along with what is needed to re-verify it later.
+#[derive(Serialize, Deserialize)]
+pub struct ProofArtifact {
+    pub format_version: u32,
+    pub crate_version: String,
+    pub vkey_hash: String,
+    pub stdin_digest: Digest,
+    pub public_values: Vec<u8>,
+    pub proof: SP1Proof,
+}
+
+/// Computes the digest of the program input, that is, of every buffer written to `stdin`.
+///
+/// Each buffer is prefixed with its length as a big-endian `u64`, so that the digest also covers
+/// the boundaries between buffers.
+pub fn stdin_digest(stdin: &SP1Stdin) -> Digest {
+    let lengths: Vec<[u8; 8]> = stdin
+        .buffer
+        .iter()
+        .map(|buffer| (buffer.len() as u64).to_be_bytes())
+        .collect();
+
+    keccak256_combine(
+        lengths
+            .iter()
+            .zip(&stdin.buffer)
+            .flat_map(|(length, buffer)| [length.as_slice(), buffer.as_slice()]),
+    )
+}
+
+impl ProofArtifact {
+    /// Creates the artifact of a proof generated from `stdin` for the program of `verifying_key`.
+    pub fn new(proof: SP1Proof, verifying_key: &SP1VerifyingKey, stdin: &SP1Stdin) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            crate_version: poly_pessimistic_proof::VERSION.to_string(),
+            vkey_hash: verifying_key.bytes32(),
+            stdin_digest: stdin_digest(stdin),
+            public_values: proof.public_values.as_slice().to_vec(),
+            proof,
+        }
+    }
+
+    /// Saves the artifact to `path`, overwriting any existing file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+
+        bincode::serialize_into(BufWriter::new(file), self)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Loads the artifact saved at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+        let artifact: Self = bincode::deserialize_from(BufReader::new(file))
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        if artifact.format_version != FORMAT_VERSION {
+            bail!(
+                "unsupported artifact format version {}, expected {FORMAT_VERSION}",
+                artifact.format_version
+            );
+        }
+
+        Ok(artifact)
+    }
+
+    /// Reads the roots and heights committed by the program.
+    pub fn output(&self) -> ProofOutput {
+        read_output(&mut self.proof.public_values.clone())
+    }
+}
+
+/// Verifies an artifact against the verifying key of the embedded ELF.
+///
+/// Besides the proof itself, this checks that the artifact was produced for the program of
+/// `verifying_key`, and that its stdin digest and public values are those of the proof.
+pub fn verify_artifact(
+    client: &ProverClient,
+    verifying_key: &SP1VerifyingKey,
+    artifact: &ProofArtifact,
+) -> Result<ProofOutput> {
+    let vkey_hash = verifying_key.bytes32();
+    if artifact.vkey_hash != vkey_hash {
+        bail!(
+            "the artifact is for the program {}, expected {vkey_hash}",
+            artifact.vkey_hash
+        );
+    }
+    if artifact.stdin_digest != stdin_digest(&artifact.proof.stdin) {
+        bail!("the artifact stdin digest differs from the digest of the proof input");
+    }
+    if artifact.public_values != artifact.proof.public_values.as_slice() {
+        bail!("the artifact public values differ from the proven ones");
+    }
+
+    client
+        .verify(&artifact.proof, verifying_key)
+        .map_err(|e| anyhow::anyhow!("invalid proof: {e}"))?;
+
+    Ok(artifact.output())
+}
@@ -0,0 +1,311 @@
+//! Cycle benchmarks of the program on generated batch sets.
+//!
+//! Each [`BenchParams`] describes a batch set, which is executed in the zkVM without proving. The
+//! resulting [`BenchReport`] records the total cycles and the cycles of each component tracked by
+//! [`poly_pessimistic_proof::cycles`], and is meant to be saved as JSON so that regressions show
+//! up in review.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use poly_pessimistic_proof::{
+    batch::Batch,
+    certificate::{signer_address, Certificate},
+    committee::Committee,
+    context::ProofContext,
+    cost::{CostFeatures, CostModel},
+    cycles::{COMPONENTS, LEAF_HASHING},
+    local_balance_tree::{Balance, Deposit},
+    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    LeafType, NetworkId, TokenInfo, Withdrawal,
+};
+use reth_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use sp1_sdk::ProverClient;
+
+use crate::{
+    input::{dev_certify, dev_context, dev_member_key},
+    keys::elf_hash,
+    prover,
+};
+
+/// Parameters of a generated batch set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchParams {
+    /// Number of networks, each submitting one batch, at least two so that every withdrawal goes
+    /// to another network
+    pub networks: u32,
+    /// Number of withdrawals in each batch
+    pub withdrawals: u32,
+    /// Number of distinct tokens, both in the balance trees and in the withdrawals
+    pub tokens: u32,
+    /// Size of the metadata of each withdrawal
+    pub metadata_len: u32,
+    /// Number of leaves in each exit tree before the batch
+    pub leaf_count: u32,
+    /// Number of committee members attesting each certificate, the networks having no committee
+    /// if zero
+    #[serde(default)]
+    pub attestations: u32,
+}
+
+/// Cycles of a batch set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub params: BenchParams,
+    /// Features of the batch set, as seen by the cost model
+    pub features: CostFeatures,
+    /// Cycles estimated by [`CostModel::CALIBRATED`]
+    pub estimate: u64,
+    /// Total number of cycles
+    pub cycles: u64,
+    /// Number of cycles of each component
+    pub components: BTreeMap<String, u64>,
+    /// Cycles outside of the tracked components, such as reading the input
+    pub other: u64,
+}
+
+/// Results of a benchmark run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// Hash of the benchmarked ELF, as hex
+    pub elf_hash: String,
+    pub results: Vec<BenchResult>,
+    /// Cost model fitted on the results, if they determine one
+    pub fitted: Option<CostModel>,
+}
+
+/// Returns the benchmarked batch sets, scaling each dimension from a small baseline in turn.
+///
+/// The `quick` suite only keeps the smaller sets. Both suites have more batch sets than the
+/// [`CostModel`] has coefficients, so that the fitted model is checked against the results rather
+/// than merely interpolating them.
+pub fn suite(quick: bool) -> Vec<BenchParams> {
+    let base = BenchParams {
+        networks: 2,
+        withdrawals: 10,
+        tokens: 1,
+        metadata_len: 0,
+        leaf_count: 0,
+        attestations: 0,
+    };
+    let scale: &[u32] = if quick { &[1, 2, 4] } else { &[1, 4, 16, 64] };
+
+    let mut suite = vec![base];
+    for &factor in &scale[1..] {
+        suite.push(BenchParams {
+            withdrawals: base.withdrawals * factor,
+            ..base
+        });
+        suite.push(BenchParams {
+            tokens: base.tokens * factor,
+            ..base
+        });
+        suite.push(BenchParams {
+            networks: base.networks * factor,
+            ..base
+        });
+        suite.push(BenchParams {
+            metadata_len: 32 * factor,
+            ..base
+        });
+        suite.push(BenchParams {
+            leaf_count: 1000 * factor,
+            ..base
+        });
+        suite.push(BenchParams {
+            attestations: factor,
+            ..base
+        });
+    }
+
+    suite
+}
+
+fn token(index: u32) -> TokenInfo {
+    let mut address = [0; 20];
+    address[16..].copy_from_slice(&index.to_be_bytes());
+
+    TokenInfo {
+        origin_network: 0.into(),
+        origin_token_address: Address::from(address),
+    }
+}
+
+/// Generates the batch set described by `params`.
+///
+/// Every network starts with enough balance for all its withdrawals, which go round-robin to the
+/// other networks and over the tokens, so that the batch set is always valid. At least two
+/// networks are generated.
+pub fn generate_batches(params: &BenchParams) -> Vec<Batch> {
+    let tokens: Vec<TokenInfo> = (0..params.tokens).map(token).collect();
+    let networks = params.networks.max(2);
+
+    (0..networks)
+        .map(|network| {
+            let origin_network = NetworkId::from(network);
+            let prev_local_exit_tree: LocalExitTree<Keccak256Hasher> =
+                LocalExitTree::from_leaves((0..params.leaf_count).map(|i| {
+                    let mut leaf = [0; 32];
+                    leaf[28..].copy_from_slice(&i.to_be_bytes());
+                    leaf
+                }));
+            let prev_local_exit_root = prev_local_exit_tree.get_root();
+
+            let balances: Vec<(TokenInfo, Balance)> = tokens
+                .iter()
+                .map(|token| (token.clone(), Deposit(U256::from(u64::MAX)).into()))
+                .collect();
+
+            let withdrawals = (0..params.withdrawals)
+                .map(|i| {
+                    let token = &tokens[(i % params.tokens) as usize];
+                    let dest_network = (network + 1 + i % (networks - 1)) % networks;
+
+                    Withdrawal::new(
+                        LeafType::Asset,
+                        token.origin_network,
+                        token.origin_token_address,
+                        dest_network.into(),
+                        Address::repeat_byte(0xaa),
+                        U256::from(1 + i),
+                        vec![0xbb; params.metadata_len as usize],
+                    )
+                })
+                .collect();
+
+            Batch::new(
+                origin_network,
+                prev_local_exit_tree,
+                prev_local_exit_root,
+                balances.into(),
+                withdrawals,
+            )
+        })
+        .collect()
+}
+
+/// Generates the certificates of the batch set described by `params`, each batch certified with
+/// the development key of its network and attested by the development keys of its committee, along
+/// with the context registering them.
+pub fn generate_certificates(params: &BenchParams) -> (ProofContext, Vec<Certificate>) {
+    let batches = generate_batches(params);
+    let mut context = dev_context(batches.iter().map(|batch| batch.origin_network));
+    let mut certificates = dev_certify(&context, batches);
+
+    if params.attestations > 0 {
+        for certificate in &mut certificates {
+            let network = certificate.header.network;
+            let members: Vec<_> = (0..params.attestations)
+                .map(|member| dev_member_key(network, member))
+                .collect();
+
+            for member_key in &members {
+                certificate.attest(member_key);
+            }
+            context.committees.insert(
+                network,
+                Committee::new(
+                    members.iter().map(signer_address).collect(),
+                    params.attestations,
+                ),
+            );
+        }
+    }
+
+    (context, certificates)
+}
+
+/// Executes the batch set described by `params`, as certified by [`generate_certificates`].
+pub fn run(client: &ProverClient, params: &BenchParams) -> Result<BenchResult> {
+    let (context, certificates) = generate_certificates(params);
+    let features = CostFeatures::of_certificates(&certificates);
+    let execution = prover::execute(client, &context, &certificates)?;
+
+    let components: BTreeMap<String, u64> = COMPONENTS
+        .iter()
+        .map(|component| {
+            let cycles = execution.component_cycles.get(*component).copied();
+            (component.to_string(), cycles.unwrap_or_default())
+        })
+        .collect();
+    // The leaves are hashed within the exit tree update, whose cycles already count them
+    let tracked: u64 = components
+        .iter()
+        .filter(|(component, _)| component.as_str() != LEAF_HASHING)
+        .map(|(_, cycles)| cycles)
+        .sum();
+    let other = execution.cycles.saturating_sub(tracked);
+
+    Ok(BenchResult {
+        params: *params,
+        features,
+        estimate: CostModel::CALIBRATED.estimate(&features),
+        cycles: execution.cycles,
+        components,
+        other,
+    })
+}
+
+/// Executes every batch set of `suite`.
+pub fn run_suite(client: &ProverClient, suite: &[BenchParams]) -> Result<BenchReport> {
+    let results: Vec<BenchResult> = suite
+        .iter()
+        .map(|params| run(client, params))
+        .collect::<Result<_>>()?;
+
+    Ok(BenchReport {
+        elf_hash: hex::encode(elf_hash(prover::ELF)),
+        fitted: fit(&results),
+        results,
+    })
+}
+
+/// Fits a [`CostModel`] on benchmark results by least squares.
+///
+/// Returns `None` if the results do not determine every coefficient, for instance when a feature
+/// is the same in all of them.
+pub fn fit(results: &[BenchResult]) -> Option<CostModel> {
+    const N: usize = 7;
+
+    // Normal equations: (XᵀX) b = Xᵀy
+    let mut matrix = [[0.0_f64; N + 1]; N];
+    for result in results {
+        let x = CostModel::features_vector(&result.features);
+        let y = result.cycles as f64;
+
+        for i in 0..N {
+            for j in 0..N {
+                matrix[i][j] += x[i] * x[j];
+            }
+            matrix[i][N] += x[i] * y;
+        }
+    }
+
+    // Gaussian elimination with partial pivoting
+    for column in 0..N {
+        let pivot = (column..N)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-9 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+
+        let pivot_row = matrix[column];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            if row != column {
+                let factor = values[column] / pivot_row[column];
+                for (value, pivot_value) in values.iter_mut().zip(pivot_row).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+
+    let mut coefficients = [0.0; N];
+    for (i, coefficient) in coefficients.iter_mut().enumerate() {
+        *coefficient = matrix[i][N] / matrix[i][i];
+    }
+
+    Some(CostModel::from_coefficients(coefficients))
+}
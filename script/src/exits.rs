@@ -0,0 +1,252 @@
+//! Index of the withdrawals of each network, providing the Merkle paths needed to claim them on
+//! the destination network.
+//!
+//! Withdrawals are indexed once the epoch of their certificate is proven, and their paths lead to
+//! the roots of the last proven epoch. Withdrawals from mainnet are claimed against its local exit
+//! root. Withdrawals from a rollup are claimed against the rollup exit root, whose leaf `i` is the
+//! local exit root of the network registered with the rollup index `i`, so their proof also holds
+//! the path of the local exit root in that tree.
+//!
+//! The rollup exit root is local to the aggregator: it is built from the local exit roots proven by
+//! the aggregator, with an empty leaf for the rollups which it has not proven. It only matches the
+//! rollup exit root of the L1 rollup manager if every registered rollup settles through the
+//! aggregator.
+
+use std::{collections::BTreeMap, fmt};
+
+use poly_pessimistic_proof::{
+    batch::Batch,
+    keccak::Digest,
+    local_exit_tree::{
+        data::{LocalExitTreeData, MerkleProof},
+        hasher::Keccak256Hasher,
+    },
+    registry::NetworkRegistry,
+    NetworkId, Withdrawal,
+};
+use serde::{Deserialize, Serialize};
+
+/// The network whose withdrawals are claimed against its local exit root.
+pub const MAINNET: NetworkId = NetworkId::new(0);
+
+/// Represents all errors that can occur while looking up a withdrawal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitProofError {
+    /// No certificate of the network was proven.
+    UnknownNetwork { network: NetworkId },
+    /// The withdrawal is not proven yet, or precedes the first proven certificate of the network.
+    UnknownDeposit {
+        network: NetworkId,
+        deposit_count: u32,
+    },
+    /// The network is not mainnet, and has no index in the rollup exit tree.
+    NoRollupIndex { network: NetworkId },
+}
+
+impl fmt::Display for ExitProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitProofError::UnknownNetwork { network } => {
+                write!(f, "no proven certificate for network {}", **network)
+            }
+            ExitProofError::UnknownDeposit {
+                network,
+                deposit_count,
+            } => write!(
+                f,
+                "no proven withdrawal {deposit_count} for network {}",
+                **network
+            ),
+            ExitProofError::NoRollupIndex { network } => {
+                write!(f, "network {} has no rollup index", **network)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExitProofError {}
+
+/// Everything needed to claim a withdrawal on its destination network.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExitProof {
+    /// Epoch whose roots the paths lead to
+    pub epoch: u64,
+    pub network: NetworkId,
+    pub deposit_count: u32,
+    pub withdrawal: Withdrawal,
+    /// Leaf of the withdrawal in the local exit tree of the network
+    pub leaf: Digest,
+    pub local_exit_root: Digest,
+    /// Path of the leaf to the local exit root
+    pub local_exit_path: MerkleProof<Keccak256Hasher>,
+    /// Index of the network in the rollup exit tree, for withdrawals from rollups
+    pub rollup_index: Option<u32>,
+    /// Rollup exit root computed by the aggregator, for withdrawals from rollups
+    pub rollup_exit_root: Option<Digest>,
+    /// Path of the local exit root to the rollup exit root, for withdrawals from rollups
+    pub rollup_exit_path: Option<MerkleProof<Keccak256Hasher>>,
+}
+
+impl ExitProof {
+    /// Checks that the paths lead from the withdrawal to the claimed roots.
+    pub fn verify(&self) -> bool {
+        if self.withdrawal.hash() != self.leaf
+            || self.local_exit_path.leaf_index != self.deposit_count
+            || !self.local_exit_path.verify(self.leaf, self.local_exit_root)
+        {
+            return false;
+        }
+
+        match (
+            self.rollup_index,
+            &self.rollup_exit_root,
+            &self.rollup_exit_path,
+        ) {
+            (None, None, None) => self.network == MAINNET,
+            (Some(rollup_index), Some(rollup_exit_root), Some(rollup_exit_path)) => {
+                self.network != MAINNET
+                    && rollup_exit_path.leaf_index == rollup_index
+                    && rollup_exit_path.verify(self.local_exit_root, *rollup_exit_root)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The proven withdrawals of a network.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct NetworkExits {
+    tree: LocalExitTreeData<Keccak256Hasher>,
+    /// Withdrawals of the known leaves of `tree`, from its first known leaf
+    withdrawals: Vec<Withdrawal>,
+}
+
+/// The proven withdrawals of all networks.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ExitIndex {
+    networks: BTreeMap<NetworkId, NetworkExits>,
+    /// Last proven epoch
+    epoch: Option<u64>,
+}
+
+impl ExitIndex {
+    /// Indexes the withdrawals of the batches proven in `epoch`.
+    ///
+    /// A batch which does not follow the indexed withdrawals of its network, such as the first one
+    /// of the network, restarts its index from its previous local exit tree.
+    pub fn index<'a>(&mut self, epoch: u64, batches: impl IntoIterator<Item = &'a Batch>) {
+        for batch in batches {
+            let exits = self.networks.entry(batch.origin_network).or_default();
+
+            let prev_tree = &batch.prev_local_exit_tree;
+            if exits.tree.leaf_count() != prev_tree.leaf_count()
+                || exits.tree.get_root() != prev_tree.get_root()
+            {
+                *exits = NetworkExits {
+                    tree: LocalExitTreeData::from_tree(prev_tree.clone()),
+                    withdrawals: Vec::new(),
+                };
+            }
+
+            for withdrawal in &batch.withdrawals {
+                exits.tree.add_leaf(withdrawal.hash());
+                exits.withdrawals.push(withdrawal.clone());
+            }
+        }
+
+        self.epoch = Some(epoch);
+    }
+
+    /// Returns the local exit root of `network` as of the last proven epoch.
+    pub fn local_exit_root(&self, network: NetworkId) -> Option<Digest> {
+        self.networks
+            .get(&network)
+            .map(|exits| exits.tree.get_root())
+    }
+
+    /// Returns the tree whose leaf `i` is the local exit root of the network registered with the
+    /// rollup index `i`, as of the last proven epoch.
+    fn rollup_exit_tree(&self, registry: &NetworkRegistry) -> LocalExitTreeData<Keccak256Hasher> {
+        let rollups: BTreeMap<u32, NetworkId> = registry
+            .networks
+            .iter()
+            .filter_map(|(network, config)| Some((config.rollup_index?, *network)))
+            .collect();
+        let rollup_count = rollups.last_key_value().map_or(0, |(index, _)| index + 1);
+
+        LocalExitTreeData::from_leaves((0..rollup_count).map(|rollup_index| {
+            rollups
+                .get(&rollup_index)
+                .and_then(|network| self.local_exit_root(*network))
+                .unwrap_or_default()
+        }))
+    }
+
+    /// Returns the rollup exit root computed from the local exit roots of the last proven epoch,
+    /// for the rollups of `registry`.
+    pub fn rollup_exit_root(&self, registry: &NetworkRegistry) -> Digest {
+        self.rollup_exit_tree(registry).get_root()
+    }
+
+    /// Returns the proof of the withdrawal of `network` with the given deposit count, the rollup
+    /// indexes being the ones of `registry`.
+    pub fn exit_proof(
+        &self,
+        registry: &NetworkRegistry,
+        network: NetworkId,
+        deposit_count: u32,
+    ) -> Result<ExitProof, ExitProofError> {
+        let (Some(exits), Some(epoch)) = (self.networks.get(&network), self.epoch) else {
+            return Err(ExitProofError::UnknownNetwork { network });
+        };
+
+        let unknown_deposit = ExitProofError::UnknownDeposit {
+            network,
+            deposit_count,
+        };
+        let offset = deposit_count
+            .checked_sub(exits.tree.first_leaf_index())
+            .ok_or(unknown_deposit.clone())?;
+        let withdrawal = exits
+            .withdrawals
+            .get(offset as usize)
+            .ok_or(unknown_deposit.clone())?;
+        let local_exit_path = exits
+            .tree
+            .get_proof(deposit_count, exits.tree.leaf_count())
+            .ok_or(unknown_deposit)?;
+        let local_exit_root = exits.tree.get_root();
+
+        let (rollup_index, rollup_exit_root, rollup_exit_path) = if network == MAINNET {
+            (None, None, None)
+        } else {
+            let rollup_index = registry
+                .get(network)
+                .and_then(|config| config.rollup_index)
+                .ok_or(ExitProofError::NoRollupIndex { network })?;
+            let rollup_exit_tree = self.rollup_exit_tree(registry);
+            let path = rollup_exit_tree
+                .get_proof(rollup_index, rollup_exit_tree.leaf_count())
+                .expect("every registered rollup is a leaf of the rollup exit tree");
+
+            (
+                Some(rollup_index),
+                Some(rollup_exit_tree.get_root()),
+                Some(path),
+            )
+        };
+
+        Ok(ExitProof {
+            epoch,
+            network,
+            deposit_count,
+            withdrawal: withdrawal.clone(),
+            leaf: withdrawal.hash(),
+            local_exit_root,
+            local_exit_path,
+            rollup_index,
+            rollup_exit_root,
+            rollup_exit_path,
+        })
+    }
+}
@@ -0,0 +1,187 @@
+//! Reading and writing the files consumed and produced by the CLI.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use poly_pessimistic_proof::{
+    batch::Batch,
+    certificate::{signer_address, Certificate, SigningKey},
+    context::ProofContext,
+    ingest::{read_records_from_file, DepositEventData},
+    keccak::{keccak256, keccak256_combine},
+    local_balance_tree::BalanceTree,
+    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
+    registry::{ChainType, NetworkConfig},
+    NetworkId, TokenInfo, Withdrawal,
+};
+use reth_primitives::Address;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The state of a network before a batch, as stored in a state file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NetworkState {
+    /// Network which the state belongs to
+    pub origin_network: NetworkId,
+    /// Local exit tree after the last proven batch
+    pub local_exit_tree: LocalExitTree<Keccak256Hasher>,
+    /// Balance tree after the last proven batch
+    pub local_balance_tree: BalanceTree,
+}
+
+/// Reads a JSON file.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Writes a JSON file, overwriting any existing one.
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+
+    serde_json::to_writer_pretty(BufWriter::new(file), value)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Reads and concatenates the batches of several batch files.
+pub fn read_batches<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<Batch>> {
+    let mut batches = Vec::new();
+
+    for path in paths {
+        let file_batches: Vec<Batch> = read_json(path.as_ref())?;
+        batches.extend(file_batches);
+    }
+
+    Ok(batches)
+}
+
+/// Reads and concatenates the certificates of several certificate files.
+pub fn read_certificates<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<Certificate>> {
+    let mut certificates = Vec::new();
+
+    for path in paths {
+        let file_certificates: Vec<Certificate> = read_json(path.as_ref())?;
+        certificates.extend(file_certificates);
+    }
+
+    Ok(certificates)
+}
+
+/// Parses a hex-encoded secp256k1 signing key.
+pub fn parse_signing_key(hex_key: &str) -> Result<SigningKey> {
+    let bytes = hex::decode(hex_key.trim_start_matches("0x")).context("invalid hex key")?;
+
+    SigningKey::from_slice(&bytes).context("invalid secp256k1 key")
+}
+
+/// Returns the development signing key of the sequencer of `network`.
+///
+/// These keys are derived from the network alone, hence public: they are only meant for tests,
+/// benchmarks and local runs.
+pub fn dev_signing_key(network: NetworkId) -> SigningKey {
+    SigningKey::from_slice(&keccak256(&network.to_be_bytes()))
+        .expect("the hash of a network is a valid key")
+}
+
+/// Returns the development signing key of the committee member `member` of `network`, public like
+/// the keys of [`dev_signing_key`].
+pub fn dev_member_key(network: NetworkId, member: u32) -> SigningKey {
+    SigningKey::from_slice(&keccak256_combine([
+        network.to_be_bytes().as_slice(),
+        &member.to_be_bytes(),
+    ]))
+    .expect("the hash of a network and a member is a valid key")
+}
+
+/// Returns the configuration of `network` signing with `signer`, as registered by default: network
+/// `0` is mainnet, and network `n` is the rollup of index `n - 1`, with ETH as gas token.
+pub fn default_network_config(network: NetworkId, signer: Address) -> NetworkConfig {
+    let (chain_type, rollup_index) = match network.checked_sub(1) {
+        Some(rollup_index) => (ChainType::Rollup, Some(rollup_index)),
+        None => (ChainType::Mainnet, None),
+    };
+
+    NetworkConfig {
+        rollup_index,
+        chain_type,
+        signer,
+        gas_token: TokenInfo::ETH,
+        enabled: true,
+    }
+}
+
+/// Returns the context registering each network with its development key, without settled
+/// certificates.
+pub fn dev_context(networks: impl IntoIterator<Item = NetworkId>) -> ProofContext {
+    let mut context = ProofContext::new();
+    for network in networks {
+        let signer = signer_address(&dev_signing_key(network));
+        context
+            .registry
+            .register(network, default_network_config(network, signer));
+    }
+
+    context
+}
+
+/// Certifies each batch at the next height of its network in `context`, with the development key
+/// of the network.
+pub fn dev_certify(context: &ProofContext, batches: Vec<Batch>) -> Vec<Certificate> {
+    batches
+        .into_iter()
+        .map(|batch| {
+            let network = batch.origin_network;
+            Certificate::new(
+                batch,
+                context.next_height(network),
+                &dev_signing_key(network),
+            )
+        })
+        .collect()
+}
+
+/// Reads the deposits of a deposit file, as exported by the bridge service.
+pub fn read_deposits(path: &Path) -> Result<Vec<DepositEventData>> {
+    read_records_from_file(path).with_context(|| format!("invalid deposits in {}", path.display()))
+}
+
+/// Builds the [`Batch`] that applies the given deposits on top of `state`.
+///
+/// Deposits already included in the local exit tree of `state` are skipped, and the remaining
+/// ones must follow each other without gaps.
+pub fn build_batch(state: NetworkState, mut deposits: Vec<DepositEventData>) -> Result<Batch> {
+    deposits.sort_unstable_by_key(|deposit| deposit.deposit_count);
+
+    let mut next_deposit_count = state.local_exit_tree.leaf_count();
+    let mut withdrawals: Vec<Withdrawal> = Vec::new();
+    for deposit in deposits {
+        if deposit.deposit_count < next_deposit_count {
+            continue;
+        }
+        if deposit.deposit_count != next_deposit_count {
+            bail!(
+                "missing deposit {next_deposit_count}, next available is {}",
+                deposit.deposit_count
+            );
+        }
+
+        withdrawals.push(deposit.into());
+        next_deposit_count += 1;
+    }
+
+    let prev_local_exit_root = state.local_exit_tree.get_root();
+
+    Ok(Batch::new(
+        state.origin_network,
+        state.local_exit_tree,
+        prev_local_exit_root,
+        state.local_balance_tree,
+        withdrawals,
+    ))
+}
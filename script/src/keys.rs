@@ -0,0 +1,109 @@
+//! Cache of the program proving and verifying keys across runs.
+//!
+//! `ProverClient::setup` takes a while, so the keys are saved in a directory, in one file per
+//! program along with the hash of the ELF they were generated for. Keys are reused as long as the
+//! ELF is unchanged, and regenerated in place when it changes, so that the keys of past builds do
+//! not accumulate. A file which cannot be parsed, or whose keys do not match its ELF hash, is
+//! refused rather than silently overwritten.
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use poly_pessimistic_proof::keccak::{keccak256, Digest};
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{ProverClient, SP1ProvingKey, SP1VerifyingKey};
+
+/// Default directory of the key cache.
+pub const DEFAULT_KEY_CACHE_DIR: &str = ".key-cache";
+
+/// Computes the hash identifying an ELF in the cache.
+pub fn elf_hash(elf: &[u8]) -> Digest {
+    keccak256(elf)
+}
+
+/// The content of a cache file.
+#[derive(Serialize, Deserialize)]
+struct CachedKeys {
+    elf_hash: Digest,
+    proving_key: SP1ProvingKey,
+    verifying_key: SP1VerifyingKey,
+}
+
+/// A directory of proving and verifying keys, keyed by program name.
+#[derive(Clone, Debug)]
+pub struct KeyCache {
+    dir: PathBuf,
+}
+
+impl KeyCache {
+    /// Creates a new [`KeyCache`] in `dir`, which is created on the first save.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the path of the cache file of `program`.
+    pub fn path(&self, program: &str) -> PathBuf {
+        self.dir.join(format!("{program}.keys"))
+    }
+
+    /// Returns the keys of `elf`, the ELF of `program`, loading them from the cache or generating
+    /// and saving them.
+    ///
+    /// Cached keys generated for another ELF of `program` are replaced.
+    pub fn setup(
+        &self,
+        client: &ProverClient,
+        program: &str,
+        elf: &[u8],
+    ) -> Result<(SP1ProvingKey, SP1VerifyingKey)> {
+        let hash = elf_hash(elf);
+        let path = self.path(program);
+
+        if path.exists() {
+            let keys = load(&path)?;
+            if keys.elf_hash != elf_hash(&keys.proving_key.elf) {
+                bail!(
+                    "corrupted keys in {}: the proving key does not match the ELF hash 0x{}; \
+                     delete the file to regenerate them",
+                    path.display(),
+                    hex::encode(keys.elf_hash)
+                );
+            }
+            if keys.elf_hash == hash {
+                return Ok((keys.proving_key, keys.verifying_key));
+            }
+        }
+
+        let (proving_key, verifying_key) = client.setup(elf);
+        let keys = CachedKeys {
+            elf_hash: hash,
+            proving_key,
+            verifying_key,
+        };
+        save(&path, &keys)?;
+
+        Ok((keys.proving_key, keys.verifying_key))
+    }
+}
+
+fn load(path: &Path) -> Result<CachedKeys> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    bincode::deserialize_from(BufReader::new(file))
+        .with_context(|| format!("failed to parse the keys in {}", path.display()))
+}
+
+fn save(path: &Path, keys: &CachedKeys) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+
+    bincode::serialize_into(BufWriter::new(file), keys)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
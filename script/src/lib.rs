@@ -0,0 +1,10 @@
+//! Host-side tooling for the pessimistic proof.
+
+pub mod aggregator;
+pub mod artifact;
+pub mod bench;
+pub mod exits;
+pub mod input;
+pub mod keys;
+pub mod prover;
+pub mod rpc;
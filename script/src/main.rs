@@ -1,136 +1,945 @@
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use pessimistic_proof_script::{
+    aggregator::Aggregator,
+    bench,
+    input::{
+        build_batch, default_network_config, dev_signing_key, parse_signing_key, read_batches,
+        read_certificates, read_deposits, read_json, write_json, NetworkState,
+    },
+    keys::{KeyCache, DEFAULT_KEY_CACHE_DIR},
+    prover, rpc,
+};
 use poly_pessimistic_proof::{
     batch::Batch,
+    certificate::{signer_address, Certificate, SigningKey},
+    committee::Committee,
+    compact::InputFormat,
+    context::ProofContext,
+    cost::CostModel,
+    estimate_cost,
+    ingest::stream::{stream_bridge_events, MergeSorted},
     keccak::Digest as KeccakDigest,
-    local_balance_tree::{Balance, BalanceTree, Deposit},
-    local_exit_tree::{hasher::Keccak256Hasher, LocalExitTree},
-    test_utils::{parse_json_file, DepositEventData},
-    NetworkId, TokenInfo, Withdrawal,
+    pause::Paused,
+    registry::ChainType,
+    replay::Replayer,
+    split::split_batch,
+    supply::SupplyCheck,
+    NetworkId, ProofOutput, TokenInfo,
 };
-use reth_primitives::{address, U256};
-use sp1_sdk::{ProverClient, SP1Stdin};
+use reth_primitives::{Address, U256};
+use tokio::net::TcpListener;
 
-const ELF: &[u8] = include_bytes!("../../program/elf/riscv32im-succinct-zkvm-elf");
-const WITHDRAWALS_JSON_FILE_PATH: &str = "src/data/withdrawals.json";
+/// Pessimistic proof workflow: from bridge events to verified proofs.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Uses the mock prover, whose proofs are not actually checked.
+    #[arg(long, global = true)]
+    mock: bool,
+    /// Directory of the cached proving and verifying keys.
+    #[arg(long, global = true, default_value = DEFAULT_KEY_CACHE_DIR)]
+    key_cache: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Local exit root operations.
+    #[command(subcommand)]
+    Ler(LerCommand),
+    /// Batch operations.
+    #[command(subcommand)]
+    Batch(BatchCommand),
+    /// Certificate operations.
+    #[command(subcommand)]
+    Certificate(CertificateCommand),
+    /// Proof context operations.
+    #[command(subcommand)]
+    Context(ContextCommand),
+    /// Runs the program on the given certificates without proving, reporting cycle and syscall
+    /// counts.
+    Execute {
+        #[command(flatten)]
+        certificates: CertificatesArgs,
+        /// Passes the batches serde-encoded rather than in the compact layout, to compare cycles.
+        #[arg(long)]
+        serde_input: bool,
+    },
+    /// Proves the program on the given certificates and saves the proof artifact.
+    Prove {
+        #[command(flatten)]
+        certificates: CertificatesArgs,
+        /// File to save the proof artifact to.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Runs the cycle benchmarks, fits the cost model and saves the report.
+    Bench {
+        /// Only runs the smaller batch sets.
+        #[arg(long)]
+        quick: bool,
+        /// File to save the JSON report to.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Verifies a saved proof artifact against the embedded program.
+    Verify {
+        /// File of the saved proof artifact.
+        #[arg(long)]
+        proof: PathBuf,
+    },
+    /// Runs the aggregator, accepting certificates over JSON-RPC and proving them per epoch.
+    Serve {
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8546")]
+        addr: String,
+        /// File the aggregator state is saved to, and resumed from on restart.
+        #[arg(long, default_value = "aggregator.json")]
+        state: PathBuf,
+        /// Proof context file, registering the networks.
+        #[arg(long)]
+        context: PathBuf,
+        /// Directory to save the proof artifact of each epoch to.
+        #[arg(long, default_value = "proofs")]
+        proof_dir: PathBuf,
+        /// Closes the open epoch every given number of seconds, rather than only on `close_epoch`
+        /// calls.
+        #[arg(long)]
+        epoch_secs: Option<u64>,
+    },
+}
 
-const INITIAL_LEAF_COUNT: u32 = 1853;
+#[derive(Subcommand)]
+enum LerCommand {
+    /// Computes the local exit root of an event dump, checking its exit root updates.
+    Compute {
+        /// Event dumps, as JSON arrays or JSON lines. Several dumps are merged by position.
+        #[arg(required = true)]
+        events: Vec<PathBuf>,
+        /// Whether the dumps contain raw `eth_getLogs` objects.
+        #[arg(long)]
+        logs: bool,
+        /// File to save the resulting local exit tree to.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
 
-fn make_batch(origin_network: NetworkId) -> Batch {
-    let withdrawals: Vec<Withdrawal> = {
-        let deposit_event_data: Vec<DepositEventData> = parse_json_file(WITHDRAWALS_JSON_FILE_PATH);
+#[derive(Subcommand)]
+enum BatchCommand {
+    /// Builds the batch applying deposits on top of a network state.
+    Build {
+        /// Network state file.
+        #[arg(long)]
+        state: PathBuf,
+        /// Deposit file, as exported by the bridge service.
+        #[arg(long)]
+        deposits: PathBuf,
+        /// File to save the batch to.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Splits a batch into consecutive sub-batches estimated to fit a cycle budget.
+    Split {
+        /// Batch file, holding a single batch.
+        #[arg(long)]
+        batch: PathBuf,
+        /// Cycle budget of each sub-batch.
+        #[arg(long)]
+        max_cycles: u64,
+        /// Directory to save the sub-batches to, as `batch-<i>.json`.
+        #[arg(long)]
+        out_dir: PathBuf,
+    },
+}
 
-        deposit_event_data.into_iter().map(Into::into).collect()
-    };
+#[derive(Subcommand)]
+enum CertificateCommand {
+    /// Signs each batch of a batch file as the certificate of its network at the given height.
+    Sign {
+        /// Batch file.
+        #[arg(long)]
+        batches: PathBuf,
+        /// Height of the certificates, following the last settled certificate of each network.
+        #[arg(long)]
+        height: u64,
+        #[command(flatten)]
+        key: KeyArgs,
+        /// File to save the certificates to.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Adds the attestation of a committee member to each certificate of a certificate file.
+    Attest {
+        /// Certificate file, updated in place.
+        #[arg(long)]
+        certificates: PathBuf,
+        /// File holding the secp256k1 key of the committee member, as hex.
+        #[arg(long)]
+        key_file: PathBuf,
+    },
+}
 
-    let prev_local_exit_tree: LocalExitTree<Keccak256Hasher> = LocalExitTree::from_parts(
-        INITIAL_LEAF_COUNT,
-        [
-            digest_from_hex("4a3c0e05a537700590e5cfa29654e7db5b36fbe85b24e7f34bdec7ed2b194aa6"),
-            digest_from_hex("167e9d479ed70cdd2918875dd368edacc1b900085a2db71832a951ac7df31e10"),
-            digest_from_hex("480549a7a72ab13cb9dd7a1c48f3b2749be3f3a7dd440f16125a1aa5cbf07991"),
-            digest_from_hex("81b8a2cf7a80538dee49ae721a87655b080523d37cdad80c6a002a33e91c96cb"),
-            digest_from_hex("5003a15ab43bbf7e8a86fe84c7af7a515e8086e53308b4321ac83560e44cd17b"),
-            digest_from_hex("02c16029dec2ad77fb3f45ade9b12be2a191dc5bde71e15c5e873695b06eebb2"),
-            digest_from_hex("9779f2ddec81f886c42d4813cd3fe44a8e5d077df11dab2d96d8e52e575ad196"),
-            digest_from_hex("ff709923054a0745097aa2bd8b74f3434c2ef34ba4245af36efbb7792c719012"),
-            digest_from_hex("47ea61b79f448e3d692755fdd7ea1242148f1736e2ec44910ed34397f093364d"),
-            digest_from_hex("96f8e65b2aaa2500a40c5f8e72886cbe47248bda77d76d89666e47509649fdba"),
-            digest_from_hex("50f7e8cc2d5e5e9f6ce5e5d0352fff94f6569449620e6e6a693b3dfb9d44e683"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-            digest_from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
-        ],
-    );
+#[derive(Subcommand)]
+enum ContextCommand {
+    /// Registers a network, creating the context file if needed.
+    ///
+    /// By default, network 0 is mainnet and network `n` is the rollup of index `n - 1`, with ETH as
+    /// gas token.
+    Register {
+        #[command(flatten)]
+        network: NetworkArgs,
+        /// Address of the key signing the certificates of the network.
+        #[arg(long, required_unless_present = "dev_key", conflicts_with = "dev_key")]
+        signer: Option<Address>,
+        /// Registers the public development key of the network, for local runs only.
+        #[arg(long)]
+        dev_key: bool,
+        /// Chain type: mainnet, rollup or validium.
+        #[arg(long)]
+        chain_type: Option<ChainType>,
+        /// Index of the network in the rollup exit tree.
+        #[arg(long)]
+        rollup_index: Option<u32>,
+        /// Origin network of the gas token.
+        #[arg(long, requires = "gas_token_address")]
+        gas_token_network: Option<u32>,
+        /// Address of the gas token on its origin network.
+        #[arg(long, requires = "gas_token_network")]
+        gas_token_address: Option<Address>,
+    },
+    /// Enables a registered network.
+    Enable {
+        #[command(flatten)]
+        network: NetworkArgs,
+    },
+    /// Disables a registered network, which can then neither settle certificates nor receive
+    /// withdrawals.
+    Disable {
+        #[command(flatten)]
+        network: NetworkArgs,
+    },
+    /// Sets the committee which must attest the certificates of a validium network.
+    Committee {
+        #[command(flatten)]
+        network: NetworkArgs,
+        /// Number of members whose attestations are required.
+        #[arg(long)]
+        threshold: u32,
+        /// Addresses of the keys of the members.
+        #[arg(long, required = true, num_args = 1..)]
+        members: Vec<Address>,
+    },
+    /// Caps the amount of a token which a network may withdraw per epoch.
+    RateLimit {
+        #[command(flatten)]
+        network: NetworkArgs,
+        /// Origin network of the token.
+        #[arg(long)]
+        token_network: u32,
+        /// Address of the token on its origin network.
+        #[arg(long)]
+        token_address: Address,
+        /// Maximum amount withdrawn per epoch. Removes the cap if omitted.
+        #[arg(long)]
+        limit: Option<U256>,
+    },
+    /// Allows a trusted network to overdraw a token.
+    CreditLine {
+        #[command(flatten)]
+        network: NetworkArgs,
+        /// Origin network of the token.
+        #[arg(long)]
+        token_network: u32,
+        /// Address of the token on its origin network.
+        #[arg(long)]
+        token_address: Address,
+        /// Maximum overdraft. Removes the credit line if omitted.
+        #[arg(long)]
+        limit: Option<U256>,
+    },
+    /// Pauses the withdrawals of a network, or of a token from every network.
+    Pause {
+        #[command(flatten)]
+        target: PauseArgs,
+    },
+    /// Resumes the withdrawals of a paused network or token.
+    Resume {
+        #[command(flatten)]
+        target: PauseArgs,
+    },
+    /// Lists the paused networks and tokens.
+    Paused {
+        /// Proof context file.
+        #[arg(long)]
+        context: PathBuf,
+    },
+    /// Checks the supply conservation of every token, starting from the totals of the current
+    /// states of every network.
+    Supply {
+        /// Proof context file.
+        #[arg(long)]
+        context: PathBuf,
+        /// State files of every network.
+        #[arg(long, required = true, num_args = 1..)]
+        states: Vec<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct PauseArgs {
+    /// Proof context file.
+    #[arg(long)]
+    context: PathBuf,
+    /// Network id.
+    #[arg(
+        long,
+        required_unless_present = "token_network",
+        conflicts_with = "token_network"
+    )]
+    network: Option<u32>,
+    /// Origin network of the token.
+    #[arg(long, requires = "token_address")]
+    token_network: Option<u32>,
+    /// Address of the token on its origin network.
+    #[arg(long, requires = "token_network")]
+    token_address: Option<Address>,
+}
+
+#[derive(Args)]
+struct NetworkArgs {
+    /// Proof context file.
+    #[arg(long)]
+    context: PathBuf,
+    /// Network id.
+    #[arg(long)]
+    network: u32,
+}
+
+#[derive(Args)]
+struct KeyArgs {
+    /// File holding the secp256k1 key of the sequencer, as hex.
+    #[arg(long, required_unless_present = "dev_key", conflicts_with = "dev_key")]
+    key_file: Option<PathBuf>,
+    /// Signs with the public development key of each network, for local runs only.
+    #[arg(long)]
+    dev_key: bool,
+}
+
+impl KeyArgs {
+    fn signing_key(&self, network: NetworkId) -> Result<SigningKey> {
+        match &self.key_file {
+            Some(path) => read_key_file(path),
+            None => Ok(dev_signing_key(network)),
+        }
+    }
+}
+
+#[derive(Args)]
+struct CertificatesArgs {
+    /// Certificate files, whose certificates are proven together.
+    #[arg(long = "certificates", required = true, num_args = 1..)]
+    paths: Vec<PathBuf>,
+    /// Proof context file, which the certificates are checked against.
+    #[arg(long)]
+    context: PathBuf,
+    /// Expected new local exit root of the first certificate, as hex.
+    #[arg(long)]
+    expected_exit_root: Option<String>,
+}
+
+fn main() -> Result<()> {
+    sp1_sdk::utils::setup_logger();
+
+    let cli = Cli::parse();
+    if cli.mock {
+        println!("Using the mock prover: proofs are not checked");
+    }
+
+    match cli.command {
+        Command::Ler(LerCommand::Compute { events, logs, out }) => ler_compute(&events, logs, out),
+        Command::Batch(BatchCommand::Build {
+            state,
+            deposits,
+            out,
+        }) => {
+            let state: NetworkState = read_json(&state)?;
+            let batch = build_batch(state, read_deposits(&deposits)?)?;
+            println!("{} withdrawals in the batch", batch.withdrawals.len());
+
+            write_json(&out, &vec![batch])
+        }
+        Command::Batch(BatchCommand::Split {
+            batch,
+            max_cycles,
+            out_dir,
+        }) => {
+            let [batch]: [Batch; 1] = read_batches(&[batch])?
+                .try_into()
+                .map_err(|_| anyhow!("expected a single batch"))?;
+            let sub_batches = split_batch(&batch, &CostModel::CALIBRATED, max_cycles)?;
+
+            std::fs::create_dir_all(&out_dir)
+                .with_context(|| format!("failed to create {}", out_dir.display()))?;
+            for (index, sub_batch) in sub_batches.iter().enumerate() {
+                println!(
+                    "Sub-batch {index}: {} withdrawals, {} estimated cycles",
+                    sub_batch.withdrawals.len(),
+                    estimate_cost(std::slice::from_ref(sub_batch))
+                );
+                write_json(
+                    &out_dir.join(format!("batch-{index}.json")),
+                    &vec![sub_batch],
+                )?;
+            }
+
+            Ok(())
+        }
+        Command::Certificate(CertificateCommand::Sign {
+            batches,
+            height,
+            key,
+            out,
+        }) => {
+            let certificates = read_batches(&[batches])?
+                .into_iter()
+                .map(|batch| {
+                    let signing_key = key.signing_key(batch.origin_network)?;
+                    Ok(Certificate::new(batch, height, &signing_key))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            for certificate in &certificates {
+                println!(
+                    "Signed the certificate of network {} by 0x{}",
+                    *certificate.header.network,
+                    hex::encode(certificate.header.signer().context("invalid signature")?)
+                );
+            }
+
+            write_json(&out, &certificates)
+        }
+        Command::Certificate(CertificateCommand::Attest {
+            certificates: path,
+            key_file,
+        }) => {
+            let member_key = read_key_file(&key_file)?;
+            let mut certificates: Vec<Certificate> = read_json(&path)?;
+            for certificate in &mut certificates {
+                certificate.attest(&member_key);
+                println!(
+                    "Attested the certificate of network {} by 0x{}",
+                    *certificate.header.network,
+                    hex::encode(signer_address(&member_key))
+                );
+            }
+
+            write_json(&path, &certificates)
+        }
+        Command::Context(ContextCommand::Register {
+            network:
+                NetworkArgs {
+                    context: path,
+                    network,
+                },
+            signer,
+            dev_key,
+            chain_type,
+            rollup_index,
+            gas_token_network,
+            gas_token_address,
+        }) => {
+            let mut context: ProofContext = if path.exists() {
+                read_json(&path)?
+            } else {
+                ProofContext::new()
+            };
+
+            let network = NetworkId::from(network);
+            let signer = match signer {
+                Some(signer) => signer,
+                None if dev_key => signer_address(&dev_signing_key(network)),
+                None => bail!("no signer address"),
+            };
+            let mut config = default_network_config(network, signer);
+            if let Some(chain_type) = chain_type {
+                config.chain_type = chain_type;
+                if chain_type == ChainType::Mainnet {
+                    config.rollup_index = None;
+                }
+            }
+            if rollup_index.is_some() {
+                config.rollup_index = rollup_index;
+            }
+            if let (Some(origin_network), Some(origin_token_address)) =
+                (gas_token_network, gas_token_address)
+            {
+                config.gas_token = TokenInfo {
+                    origin_network: origin_network.into(),
+                    origin_token_address,
+                };
+            }
+            println!(
+                "Registered network {} as {}, signed by 0x{}",
+                *network,
+                config.chain_type,
+                hex::encode(signer)
+            );
+            context.registry.register(network, config);
+
+            write_json(&path, &context)
+        }
+        Command::Context(ContextCommand::Enable { network }) => set_enabled(&network, true),
+        Command::Context(ContextCommand::Disable { network }) => set_enabled(&network, false),
+        Command::Context(ContextCommand::Pause { target }) => set_paused(&target, true),
+        Command::Context(ContextCommand::Resume { target }) => set_paused(&target, false),
+        Command::Context(ContextCommand::Paused { context: path }) => {
+            let context: ProofContext = read_json(&path)?;
+            if context.paused.is_empty() {
+                println!("Nothing is paused");
+            }
+            for paused in context.paused.iter() {
+                println!("Paused: {paused}");
+            }
+
+            Ok(())
+        }
+        Command::Context(ContextCommand::Supply {
+            context: path,
+            states,
+        }) => {
+            let mut context: ProofContext = read_json(&path)?;
+            let mut balance_trees = BTreeMap::new();
+            for state in &states {
+                let state: NetworkState = read_json(state)?;
+                if balance_trees
+                    .insert(state.origin_network, state.local_balance_tree)
+                    .is_some()
+                {
+                    bail!("network {} has several states", *state.origin_network);
+                }
+            }
+
+            let supply = SupplyCheck::bootstrap(balance_trees.values())?;
+            for (token, total) in supply.prev_totals.iter() {
+                println!(
+                    "Token 0x{} of network {}: {} deposited, {} withdrawn",
+                    hex::encode(token.origin_token_address),
+                    *token.origin_network,
+                    total.deposited(),
+                    total.withdrawn()
+                );
+            }
+            context.supply = Some(supply);
+
+            write_json(&path, &context)
+        }
+        Command::Context(ContextCommand::Committee {
+            network:
+                NetworkArgs {
+                    context: path,
+                    network,
+                },
+            threshold,
+            members,
+        }) => {
+            let mut context: ProofContext = read_json(&path)?;
+            let network = NetworkId::from(network);
+            match context.registry.get(network) {
+                Some(config) if config.chain_type == ChainType::Validium => {}
+                Some(_) => bail!("network {} is not a validium", *network),
+                None => bail!("network {} is not registered", *network),
+            }
+            let committee = Committee::new(members, threshold);
+            if !committee.has_valid_threshold() {
+                bail!("the threshold must be between 1 and the number of members");
+            }
+
+            println!(
+                "Network {} attested by {threshold} of {} members",
+                *network,
+                committee.members.len()
+            );
+            context.committees.insert(network, committee);
 
-    let prev_local_balance_tree: BalanceTree = {
-        let eth = TokenInfo {
-            origin_network: origin_network.clone(),
-            origin_token_address: address!("0000000000000000000000000000000000000000"),
-        };
+            write_json(&path, &context)
+        }
+        Command::Context(ContextCommand::RateLimit {
+            network:
+                NetworkArgs {
+                    context: path,
+                    network,
+                },
+            token_network,
+            token_address,
+            limit,
+        }) => {
+            let mut context: ProofContext = read_json(&path)?;
+            let network = NetworkId::from(network);
+            let token = TokenInfo {
+                origin_network: token_network.into(),
+                origin_token_address: token_address,
+            };
 
-        let token = TokenInfo {
-            origin_network: origin_network.clone(),
-            origin_token_address: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
-        };
+            match limit {
+                Some(limit) => {
+                    println!(
+                        "Network {} may withdraw up to {limit} of token 0x{} of network {} per \
+                         epoch",
+                        *network,
+                        hex::encode(token_address),
+                        token_network
+                    );
+                    context.rate_limits.set(network, token, limit);
+                }
+                None => {
+                    if context.rate_limits.remove(network, &token).is_none() {
+                        bail!("network {} has no cap on this token", *network);
+                    }
+                    println!("Removed the cap of network {}", *network);
+                }
+            }
 
-        let infinite_eth = || -> (TokenInfo, Balance) { (eth.clone(), Deposit(U256::MAX).into()) };
-        let infinite_token =
-            || -> (TokenInfo, Balance) { (token.clone(), Deposit(U256::MAX).into()) };
+            write_json(&path, &context)
+        }
+        Command::Context(ContextCommand::CreditLine {
+            network:
+                NetworkArgs {
+                    context: path,
+                    network,
+                },
+            token_network,
+            token_address,
+            limit,
+        }) => {
+            let mut context: ProofContext = read_json(&path)?;
+            let network = NetworkId::from(network);
+            let token = TokenInfo {
+                origin_network: token_network.into(),
+                origin_token_address: token_address,
+            };
 
-        BalanceTree::from(vec![infinite_eth(), infinite_token()])
+            match limit {
+                Some(limit) => {
+                    println!(
+                        "Network {} may overdraw up to {limit} of token 0x{} of network {}",
+                        *network,
+                        hex::encode(token_address),
+                        token_network
+                    );
+                    context.credit_lines.set(network, token, limit);
+                }
+                None => {
+                    if context.credit_lines.remove(network, &token).is_none() {
+                        bail!("network {} has no credit line on this token", *network);
+                    }
+                    println!("Removed the credit line of network {}", *network);
+                }
+            }
+
+            write_json(&path, &context)
+        }
+        Command::Execute {
+            certificates: args,
+            serde_input,
+        } => {
+            let context: ProofContext = read_json(&args.context)?;
+            let certificates = read_certificates(&args.paths)?;
+            let client = prover::client(cli.mock);
+            let format = if serde_input {
+                InputFormat::Serde
+            } else {
+                InputFormat::Compact
+            };
+
+            let now = Instant::now();
+            let execution = prover::execute_with_format(&client, &context, &certificates, format)?;
+            println!("Execution time: {}ms", now.elapsed().as_millis());
+            println!("Cycles: {}", execution.cycles);
+            println!("Syscalls:");
+            for (syscall, count) in &execution.syscall_counts {
+                println!("  {syscall}: {count}");
+            }
+            println!("Public values: 0x{}", hex::encode(&execution.public_values));
+
+            print_output(&execution.output);
+            check_exit_root(&args, &certificates, &execution.output)
+        }
+        Command::Prove {
+            certificates: args,
+            out,
+        } => {
+            let context: ProofContext = read_json(&args.context)?;
+            let certificates = read_certificates(&args.paths)?;
+            let client = prover::client(cli.mock);
+
+            let now = Instant::now();
+            let artifact = prover::prove(
+                &client,
+                &KeyCache::new(&cli.key_cache),
+                &context,
+                &certificates,
+                &out,
+            )?;
+            println!("Prover time: {}ms", now.elapsed().as_millis());
+            println!("Proof artifact saved to {}", out.display());
+
+            let output = artifact.output();
+            print_output(&output);
+            check_exit_root(&args, &certificates, &output)
+        }
+        Command::Bench { quick, out } => {
+            let report = bench::run_suite(&prover::client(cli.mock), &bench::suite(quick))?;
+
+            println!(
+                "{:>8} {:>11} {:>6} {:>8} {:>10} {:>12} {:>12} {:>12}  components",
+                "networks",
+                "withdrawals",
+                "tokens",
+                "metadata",
+                "leaf count",
+                "cycles",
+                "estimate",
+                "other"
+            );
+            for result in &report.results {
+                let params = &result.params;
+                let components: Vec<String> = result
+                    .components
+                    .iter()
+                    .map(|(component, cycles)| format!("{component}={cycles}"))
+                    .collect();
+                println!(
+                    "{:>8} {:>11} {:>6} {:>8} {:>10} {:>12} {:>12} {:>12}  {}",
+                    params.networks,
+                    params.withdrawals,
+                    params.tokens,
+                    params.metadata_len,
+                    params.leaf_count,
+                    result.cycles,
+                    result.estimate,
+                    result.other,
+                    components.join(" ")
+                );
+            }
+            match &report.fitted {
+                Some(model) => println!("Fitted cost model: {model:?}"),
+                None => println!("The results do not determine a cost model"),
+            }
+
+            if let Some(out) = out {
+                write_json(&out, &report)?;
+                println!("Report saved to {}", out.display());
+            }
+
+            Ok(())
+        }
+        Command::Verify { proof } => {
+            let client = prover::client(cli.mock);
+
+            let now = Instant::now();
+            let artifact = prover::verify(&client, &KeyCache::new(&cli.key_cache), &proof)?;
+            println!("Proof is valid");
+            println!("Verifier time: {}ms", now.elapsed().as_millis());
+            println!("Program: {}", artifact.vkey_hash);
+            println!("Crate version: {}", artifact.crate_version);
+            println!("Stdin digest: 0x{}", hex::encode(artifact.stdin_digest));
+
+            print_output(&artifact.output());
+            Ok(())
+        }
+        Command::Serve {
+            addr,
+            state,
+            context,
+            proof_dir,
+            epoch_secs,
+        } => {
+            let aggregator = Aggregator::open(
+                state,
+                read_json(&context)?,
+                proof_dir,
+                prover::client(cli.mock),
+                KeyCache::new(&cli.key_cache),
+            )?;
+            println!("Aggregator at epoch {}", aggregator.state().epoch);
+
+            tokio::runtime::Runtime::new()?.block_on(async {
+                let listener = TcpListener::bind(&addr)
+                    .await
+                    .with_context(|| format!("failed to listen on {addr}"))?;
+                println!("Listening on {}", listener.local_addr()?);
+
+                rpc::serve(listener, aggregator, epoch_secs.map(Duration::from_secs)).await
+            })
+        }
+    }
+}
+
+fn read_key_file(path: &Path) -> Result<SigningKey> {
+    let key = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    parse_signing_key(key.trim()).with_context(|| format!("invalid key in {}", path.display()))
+}
+
+fn set_paused(args: &PauseArgs, paused: bool) -> Result<()> {
+    let mut context: ProofContext = read_json(&args.context)?;
+
+    let (target, changed) = match (args.network, args.token_network, args.token_address) {
+        (Some(network), _, _) => (
+            Paused::Network(network.into()),
+            context.paused.set_network_paused(network.into(), paused),
+        ),
+        (None, Some(origin_network), Some(origin_token_address)) => {
+            let token = TokenInfo {
+                origin_network: origin_network.into(),
+                origin_token_address,
+            };
+            (
+                Paused::Token(token.clone()),
+                context.paused.set_token_paused(token, paused),
+            )
+        }
+        _ => bail!("either a network or a token is required"),
     };
 
-    let prev_local_exit_root = prev_local_exit_tree.get_root();
+    match (changed, paused) {
+        (true, true) => println!("Paused the withdrawals of {target}"),
+        (true, false) => println!("Resumed the withdrawals of {target}"),
+        (false, true) => bail!("the withdrawals of {target} are already paused"),
+        (false, false) => bail!("the withdrawals of {target} are not paused"),
+    }
+
+    write_json(&args.context, &context)
+}
 
-    Batch {
-        origin_network,
-        prev_local_exit_tree,
-        prev_local_exit_root,
-        prev_local_balance_tree,
-        withdrawals,
+fn set_enabled(args: &NetworkArgs, enabled: bool) -> Result<()> {
+    let mut context: ProofContext = read_json(&args.context)?;
+    if !context.registry.set_enabled(args.network.into(), enabled) {
+        bail!("network {} is not registered", args.network);
     }
+
+    write_json(&args.context, &context)
 }
 
-fn main() {
-    sp1_sdk::utils::setup_logger();
+fn ler_compute(paths: &[PathBuf], raw_logs: bool, out: Option<PathBuf>) -> Result<()> {
+    let chunks = paths
+        .iter()
+        .map(|path| {
+            stream_bridge_events(path, raw_logs)
+                .with_context(|| format!("failed to open {}", path.display()))
+        })
+        .collect::<Result<_>>()?;
+
+    let mut replayer = Replayer::new();
+    for event in MergeSorted::new(chunks) {
+        replayer.apply(&event?)?;
+    }
+
+    let summary = replayer.summary();
+    println!("Deposits: {}", summary.deposits);
+    println!("Checked exit root updates: {}", summary.mainnet_checkpoints);
+    println!(
+        "Local exit root: 0x{}",
+        hex::encode(summary.mainnet_exit_root)
+    );
 
-    // Generate proof.
-    let mut stdin = SP1Stdin::new();
-    let client = ProverClient::new();
-    let (proving_key, verifying_key) = client.setup(ELF);
-
-    // Make a single batch from network 0.
-    let origin_network: NetworkId = 0.into();
-    let batches = vec![make_batch(origin_network)];
-    stdin.write(&batches);
-
-    let now = Instant::now();
-    let mut proof = client.prove(&proving_key, stdin).expect("proving failed");
-    let prover_time = now.elapsed();
-
-    // Read output.
-    let new_roots: HashMap<NetworkId, (KeccakDigest, KeccakDigest)> = proof.public_values.read();
-    let (exit_root, _balance_root) = new_roots.get(&origin_network).expect("nonexistent network");
-
-    if *exit_root
-        == digest_from_hex("bd03ab620225bd2dbe77791aced3c995e1d1a4ba3685a72117d4dc3253f57658")
-    {
-        println!("Output root is as expected!");
-    } else {
-        println!("Oops, output root is incorrect");
+    if let Some(out) = out {
+        write_json(&out, replayer.mainnet_exit_tree())?;
+        println!("Local exit tree saved to {}", out.display());
     }
 
-    // Verify proof.
-    let now = Instant::now();
-    client
-        .verify(&proof, &verifying_key)
-        .expect("verification failed");
-    let verifier_time = now.elapsed();
+    Ok(())
+}
+
+fn print_output(output: &ProofOutput) {
+    let mut networks: Vec<_> = output
+        .exit_roots
+        .keys()
+        .chain(output.balance_roots.keys())
+        .collect();
+    networks.sort_unstable();
+    networks.dedup();
 
-    println!("successfully generated and verified proof for the program!");
-    println!("Prover time: {}ms", prover_time.as_millis());
-    println!("Verifier time: {}ms", verifier_time.as_millis());
+    println!("Context hash: 0x{}", hex::encode(output.context_hash));
+    println!("Registry root: 0x{}", hex::encode(output.registry_root));
+    println!("Rate limits: 0x{}", hex::encode(output.rate_limits));
+    println!("Pause list: 0x{}", hex::encode(output.paused));
+    println!("Credit lines: 0x{}", hex::encode(output.credit_lines));
+    if let Some(supplies) = output.supplies {
+        println!("Token supplies: 0x{}", hex::encode(supplies));
+    }
+    for network in networks {
+        println!("Network {}:", **network);
+        if let Some(height) = output.heights.get(network) {
+            println!("  height:            {height}");
+        }
+        if let Some(exit_root) = output.exit_roots.get(network) {
+            println!("  local exit root:   0x{}", hex::encode(exit_root));
+        }
+        if let Some(balance_root) = output.balance_roots.get(network) {
+            println!("  balance tree root: 0x{}", hex::encode(balance_root));
+        }
+        if let Some(input_digest) = output.input_digests.get(network) {
+            println!("  input digest:      0x{}", hex::encode(input_digest));
+        }
+        for (_, token, amount) in output
+            .withdrawn
+            .iter()
+            .filter(|(withdrawn_by, _, _)| withdrawn_by == network)
+        {
+            println!(
+                "  withdrawn:         {amount} of token 0x{} of network {}",
+                hex::encode(token.origin_token_address),
+                *token.origin_network
+            );
+        }
+        for credit in output
+            .credit_used
+            .iter()
+            .filter(|credit| credit.network == *network)
+        {
+            println!(
+                "  credit used:       {} of token 0x{} of network {}",
+                credit.amount,
+                hex::encode(credit.token.origin_token_address),
+                *credit.token.origin_network
+            );
+        }
+    }
 }
 
-fn digest_from_hex(hex_digest: &str) -> KeccakDigest {
-    hex::decode(hex_digest).unwrap().try_into().unwrap()
+fn check_exit_root(
+    args: &CertificatesArgs,
+    certificates: &[Certificate],
+    output: &ProofOutput,
+) -> Result<()> {
+    let Some(expected) = &args.expected_exit_root else {
+        return Ok(());
+    };
+
+    let expected: KeccakDigest = hex::decode(expected.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .context("invalid expected exit root")?;
+
+    let network = certificates
+        .first()
+        .context("no certificate")?
+        .header
+        .network;
+    if output.exit_roots.get(&network) != Some(&expected) {
+        bail!("unexpected exit root for network {}", *network);
+    }
+
+    println!("Output root is as expected!");
+    Ok(())
 }
@@ -0,0 +1,238 @@
+//! Execution, proving and verification of the pessimistic proof program.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use poly_pessimistic_proof::{
+    batch::Batch,
+    certificate::{Certificate, CertificateHeader, SigningKey},
+    compact::{encode_batches, InputFormat},
+    context::ProofContext,
+    cost::CostModel,
+    generate_full_proof,
+    split::{check_chain, split_batch},
+    supply::SupplyCheck,
+    ProofOutput,
+};
+use sp1_sdk::{ProverClient, SP1PublicValues, SP1Stdin};
+
+use crate::{
+    artifact::{verify_artifact, ProofArtifact},
+    keys::KeyCache,
+};
+
+/// The ELF of the pessimistic proof program.
+pub const ELF: &[u8] = include_bytes!("../../program/elf/riscv32im-succinct-zkvm-elf");
+
+/// Name of the pessimistic proof program in the [`KeyCache`].
+pub const PROGRAM: &str = "pessimistic-proof";
+
+/// The outcome of an execution of the program.
+pub struct Execution {
+    /// Raw public values committed by the program
+    pub public_values: Vec<u8>,
+    /// New roots and heights committed by the program
+    pub output: ProofOutput,
+    /// Number of RISC-V cycles
+    pub cycles: u64,
+    /// Number of calls to each syscall, such as the Keccak permutation
+    pub syscall_counts: BTreeMap<String, u64>,
+    /// Number of cycles spent in each component, see [`poly_pessimistic_proof::cycles`]
+    pub component_cycles: BTreeMap<String, u64>,
+}
+
+/// Creates the client, using the mock prover if `mock` is set.
+///
+/// The mock prover executes the program and yields proofs with the actual public values, but
+/// whose validity is not checked: it is meant for development and CI, never for production.
+pub fn client(mock: bool) -> ProverClient {
+    if mock {
+        ProverClient::mock()
+    } else {
+        ProverClient::new()
+    }
+}
+
+/// Writes the program input, in the default [`InputFormat`].
+pub fn stdin(context: &ProofContext, certificates: &[Certificate]) -> SP1Stdin {
+    stdin_with_format(context, certificates, InputFormat::default())
+}
+
+/// Writes the program input in the given [`InputFormat`].
+pub fn stdin_with_format(
+    context: &ProofContext,
+    certificates: &[Certificate],
+    format: InputFormat,
+) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(context);
+    stdin.write(&format);
+    match format {
+        InputFormat::Serde => stdin.write(&certificates.to_vec()),
+        InputFormat::Compact => {
+            let (headers, batches): (Vec<CertificateHeader>, Vec<Batch>) = certificates
+                .iter()
+                .map(|certificate| (certificate.header.clone(), certificate.batch.clone()))
+                .unzip();
+            stdin.write(&headers);
+            stdin.write_slice(&encode_batches(&batches));
+        }
+    }
+    stdin
+}
+
+/// Reads the roots and heights committed by the program.
+pub fn read_output(public_values: &mut SP1PublicValues) -> ProofOutput {
+    public_values.read()
+}
+
+/// Runs the program without proving.
+pub fn execute(
+    client: &ProverClient,
+    context: &ProofContext,
+    certificates: &[Certificate],
+) -> Result<Execution> {
+    execute_with_format(client, context, certificates, InputFormat::default())
+}
+
+/// Runs the program without proving, passing the input in the given [`InputFormat`].
+pub fn execute_with_format(
+    client: &ProverClient,
+    context: &ProofContext,
+    certificates: &[Certificate],
+    format: InputFormat,
+) -> Result<Execution> {
+    let (mut public_values, report) = client
+        .execute(ELF, stdin_with_format(context, certificates, format))
+        .map_err(|e| anyhow!("execution failed: {e}"))?;
+
+    let syscall_counts = report
+        .syscall_counts
+        .iter()
+        .map(|(syscall, count)| (format!("{syscall:?}"), *count))
+        .collect();
+
+    let component_cycles = report
+        .cycle_tracker
+        .iter()
+        .map(|(component, cycles)| (component.clone(), *cycles))
+        .collect();
+
+    Ok(Execution {
+        public_values: public_values.as_slice().to_vec(),
+        output: read_output(&mut public_values),
+        cycles: report.total_instruction_count(),
+        syscall_counts,
+        component_cycles,
+    })
+}
+
+/// Proves the program on the given certificates, and saves the proof artifact to `path`.
+pub fn prove(
+    client: &ProverClient,
+    keys: &KeyCache,
+    context: &ProofContext,
+    certificates: &[Certificate],
+    path: &Path,
+) -> Result<ProofArtifact> {
+    let (proving_key, verifying_key) = keys.setup(client, PROGRAM, ELF)?;
+    let stdin = stdin(context, certificates);
+
+    let proof = client
+        .prove(&proving_key, stdin.clone())
+        .map_err(|e| anyhow!("proving failed: {e}"))?;
+
+    let artifact = ProofArtifact::new(proof, &verifying_key, &stdin);
+    artifact.save(path)?;
+
+    Ok(artifact)
+}
+
+/// Loads the proof artifact saved at `path` and verifies it against the program.
+pub fn verify(client: &ProverClient, keys: &KeyCache, path: &Path) -> Result<ProofArtifact> {
+    let (_, verifying_key) = keys.setup(client, PROGRAM, ELF)?;
+
+    let artifact = ProofArtifact::load(path)?;
+    verify_artifact(client, &verifying_key, &artifact)?;
+
+    Ok(artifact)
+}
+
+/// Returns the supply check of the context of the next proof, whose totals are the ones of which
+/// `output` commits the hash.
+///
+/// The proof only commits the hash of the totals, so that they are computed again from `batches`.
+pub fn next_supply(
+    context: &ProofContext,
+    batches: &[Batch],
+    output: &ProofOutput,
+) -> Result<Option<SupplyCheck>> {
+    let Some(supply) = &context.supply else {
+        return Ok(None);
+    };
+
+    let totals = generate_full_proof(context, batches)
+        .map_err(|error| anyhow!("failed to compute the token supplies: {error:?}"))?
+        .supplies
+        .context("no token supplies in the full proof output")?;
+    if output.supplies != Some(totals.hash()) {
+        bail!("the token supplies differ from the ones of the proof");
+    }
+
+    let mut supply = supply.clone();
+    supply.advance(totals);
+    Ok(Some(supply))
+}
+
+/// Splits `batch` into consecutive sub-batches estimated to fit `max_cycles`, and proves them in
+/// sequence, saving the artifact of the sub-batch `i` to `dir/proof-{i}.bin`.
+///
+/// Each sub-batch is certified with `signing_key` at the height following the previous one,
+/// starting from the next height of the network in `context`, and its context holds the amounts
+/// withdrawn by the previous ones, so that the rate limits apply to the whole batch, along with the
+/// token supplies after them, so that the issuance is applied once. Each proof is checked to end at
+/// the local exit root which the next sub-batch starts from, and the last one at the local exit
+/// root of the whole batch.
+pub fn prove_split(
+    client: &ProverClient,
+    keys: &KeyCache,
+    context: &ProofContext,
+    signing_key: &SigningKey,
+    batch: &Batch,
+    max_cycles: u64,
+    dir: &Path,
+) -> Result<Vec<ProofArtifact>> {
+    let sub_batches = split_batch(batch, &CostModel::CALIBRATED, max_cycles)?;
+    check_chain(&sub_batches)?;
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let mut context = context.clone();
+    let mut artifacts: Vec<ProofArtifact> = Vec::with_capacity(sub_batches.len());
+    let mut exit_root = batch.prev_local_exit_root;
+    for (index, sub_batch) in sub_batches.iter().enumerate() {
+        if sub_batch.prev_local_exit_root != exit_root {
+            bail!("sub-batch {index} does not start from the previous local exit root");
+        }
+
+        let height = context.next_height(batch.origin_network);
+        let certificate = Certificate::new(sub_batch.clone(), height, signing_key);
+        let path = dir.join(format!("proof-{index}.bin"));
+        let artifact = prove(client, keys, &context, &[certificate], &path)?;
+
+        let output = artifact.output();
+        exit_root = *output
+            .exit_roots
+            .get(&batch.origin_network)
+            .context("no local exit root for the origin network")?;
+        context.supply = next_supply(&context, std::slice::from_ref(sub_batch), &output)?;
+        context.settle(&output.heights);
+        context.withdrawn = output.withdrawn.clone();
+        artifacts.push(artifact);
+    }
+
+    if exit_root != batch.compute_new_exit_root() {
+        bail!("the sub-batches do not end at the local exit root of the whole batch");
+    }
+
+    Ok(artifacts)
+}
@@ -0,0 +1,304 @@
+//! JSON-RPC 2.0 endpoint of the [`Aggregator`], served over HTTP.
+//!
+//! Calls are posted to `/`:
+//! - `submit_certificate`, whose only parameter is the signed
+//!   [`Certificate`](poly_pessimistic_proof::certificate::Certificate) of a network, returns a
+//!   [`Receipt`](crate::aggregator::Receipt);
+//! - `status` returns a [`Status`];
+//! - `get_exit_proof`, whose parameters are a network and a deposit count, returns the
+//!   [`ExitProof`](crate::exits::ExitProof) of the withdrawal, or the error code [`NOT_FOUND`] if
+//!   it is not proven yet;
+//! - `import_claims`, whose parameters are a network and the
+//!   [`ClaimEventData`](poly_pessimistic_proof::ingest::ClaimEventData) of the claims emitted by its
+//!   bridge, settles the matching pending exits, or yields the error code [`REJECTED`] if any does
+//!   not match;
+//! - `get_corridors` returns the [`Corridors`] of the reconciliation ledger;
+//! - `close_epoch` proves the open epoch and returns the
+//!   [`ProvenEpoch`](crate::aggregator::ProvenEpoch), or `null` if no
+//!   certificate is queued.
+//!
+//! Refused certificates yield the error code [`REJECTED`], with the [`Rejection`] as message.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use anyhow::Result;
+use axum::{extract::State, routing::post, Json, Router};
+use poly_pessimistic_proof::{
+    certificate::Certificate,
+    ingest::ClaimEventData,
+    keccak::Digest,
+    ledger::{Claim, Corridor, CorridorBalance, LedgerError},
+    BalanceRoot, ExitRoot, NetworkId,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::net::TcpListener;
+
+use crate::aggregator::{Aggregator, Rejection};
+
+/// Error code of the refused certificates and claims.
+pub const REJECTED: i64 = -32000;
+/// Error code of the lookups of withdrawals which are not proven.
+pub const NOT_FOUND: i64 = -32001;
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// A JSON-RPC call.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default)]
+    pub id: Value,
+}
+
+impl Request {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+            id: id.into(),
+        }
+    }
+}
+
+/// The response to a JSON-RPC call, holding either a result or an error.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// The state of the open epoch, returned by `status`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Status {
+    /// Number of the open epoch
+    pub epoch: u64,
+    /// Networks with a certificate queued in the open epoch
+    pub pending: Vec<NetworkId>,
+    /// Local exit root of each network after the last proven epoch
+    pub exit_roots: BTreeMap<NetworkId, ExitRoot>,
+    /// Balance tree root of each network after the last proven epoch
+    pub balance_roots: BTreeMap<NetworkId, BalanceRoot>,
+    /// Height of the last proven certificate of each network
+    pub heights: BTreeMap<NetworkId, u64>,
+}
+
+/// The amounts in flight and claimed through each corridor, returned by `get_corridors`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Corridors {
+    /// Root of the reconciliation ledger
+    pub root: Digest,
+    pub corridors: Vec<(Corridor, CorridorBalance)>,
+}
+
+type SharedAggregator = Arc<Mutex<Aggregator>>;
+
+/// Serves the endpoint on `listener` until the task is dropped, and closes the open epoch every
+/// `epoch_duration` if set.
+pub async fn serve(
+    listener: TcpListener,
+    aggregator: Aggregator,
+    epoch_duration: Option<Duration>,
+) -> Result<()> {
+    let aggregator = Arc::new(Mutex::new(aggregator));
+
+    if let Some(epoch_duration) = epoch_duration {
+        tokio::spawn(close_epochs(aggregator.clone(), epoch_duration));
+    }
+
+    let router = Router::new()
+        .route("/", post(handle))
+        .with_state(aggregator);
+    axum::serve(listener, router).await?;
+
+    Ok(())
+}
+
+/// Proving holds the lock for its whole duration, so calls wait for the open epoch to be proven.
+fn lock(aggregator: &Mutex<Aggregator>) -> MutexGuard<'_, Aggregator> {
+    // The state is saved after every complete change, so it stays usable after a panic.
+    aggregator.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+async fn close_epochs(aggregator: SharedAggregator, epoch_duration: Duration) {
+    let mut interval = tokio::time::interval(epoch_duration);
+    // The first tick completes immediately.
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let aggregator = aggregator.clone();
+        let closed = tokio::task::spawn_blocking(move || lock(&aggregator).close_epoch()).await;
+        match closed {
+            Ok(Ok(Some(proven))) => println!(
+                "Epoch {} proven with {} certificates: {}",
+                proven.epoch,
+                proven.networks.len(),
+                proven.proof.display()
+            ),
+            Ok(Ok(None)) => {}
+            Ok(Err(error)) => eprintln!("Failed to close the epoch: {error:#}"),
+            Err(error) => eprintln!("Failed to close the epoch: {error}"),
+        }
+    }
+}
+
+async fn handle(State(aggregator): State<SharedAggregator>, body: String) -> Json<Response> {
+    let request: Request = match serde_json::from_str(&body) {
+        Ok(request) => request,
+        Err(error) => {
+            return Json(response(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, error)),
+            ))
+        }
+    };
+
+    let id = request.id.clone();
+    let outcome = tokio::task::spawn_blocking(move || dispatch(&aggregator, request))
+        .await
+        .unwrap_or_else(|error| Err(RpcError::new(INTERNAL_ERROR, error)));
+
+    Json(response(id, outcome))
+}
+
+fn response(id: Value, outcome: Result<Value, RpcError>) -> Response {
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+
+    Response {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result,
+        error,
+    }
+}
+
+fn dispatch(aggregator: &Mutex<Aggregator>, request: Request) -> Result<Value, RpcError> {
+    if request.jsonrpc != "2.0" {
+        return Err(RpcError::new(INVALID_REQUEST, "expected JSON-RPC 2.0"));
+    }
+
+    match request.method.as_str() {
+        "submit_certificate" => {
+            let (certificate,): (Certificate,) = serde_json::from_value(request.params)
+                .map_err(|error| RpcError::new(INVALID_PARAMS, error))?;
+
+            let receipt = lock(aggregator).submit(certificate).map_err(|error| {
+                let code = if error.is::<Rejection>() {
+                    REJECTED
+                } else {
+                    INTERNAL_ERROR
+                };
+                RpcError::new(code, format!("{error:#}"))
+            })?;
+            to_value(&receipt)
+        }
+        "status" => {
+            let aggregator = lock(aggregator);
+            let state = aggregator.state();
+
+            to_value(&Status {
+                epoch: state.epoch,
+                pending: state
+                    .pending
+                    .iter()
+                    .map(|certificate| certificate.header.network)
+                    .collect(),
+                exit_roots: state.exit_roots.clone(),
+                balance_roots: state.balance_roots.clone(),
+                heights: state.context.heights.clone(),
+            })
+        }
+        "get_exit_proof" => {
+            let (network, deposit_count): (NetworkId, u32) = serde_json::from_value(request.params)
+                .map_err(|error| RpcError::new(INVALID_PARAMS, error))?;
+
+            let exit_proof = lock(aggregator)
+                .exit_proof(network, deposit_count)
+                .map_err(|error| RpcError::new(NOT_FOUND, error))?;
+            to_value(&exit_proof)
+        }
+        "import_claims" => {
+            let (network, events): (NetworkId, Vec<ClaimEventData>) =
+                serde_json::from_value(request.params)
+                    .map_err(|error| RpcError::new(INVALID_PARAMS, error))?;
+            let mut aggregator = lock(aggregator);
+            let registry = &aggregator.state().context.registry;
+            let claims = events
+                .iter()
+                .map(|event| Claim::from_event(registry, network, event))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|error| RpcError::new(REJECTED, error))?;
+
+            aggregator.import_claims(&claims).map_err(|error| {
+                let code = if error.is::<LedgerError>() {
+                    REJECTED
+                } else {
+                    INTERNAL_ERROR
+                };
+                RpcError::new(code, format!("{error:#}"))
+            })?;
+            Ok(Value::Null)
+        }
+        "get_corridors" => {
+            let aggregator = lock(aggregator);
+            let ledger = &aggregator.state().ledger;
+
+            to_value(&Corridors {
+                root: ledger.root(),
+                corridors: ledger
+                    .corridors()
+                    .map(|(corridor, balance)| (corridor.clone(), balance.clone()))
+                    .collect(),
+            })
+        }
+        "close_epoch" => {
+            let proven = lock(aggregator)
+                .close_epoch()
+                .map_err(|error| RpcError::new(INTERNAL_ERROR, format!("{error:#}")))?;
+            to_value(&proven)
+        }
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method {method}"),
+        )),
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|error| RpcError::new(INTERNAL_ERROR, error))
+}
@@ -0,0 +1,469 @@
+//! Runs the aggregator end-to-end over JSON-RPC on the demo data, with the mock prover.
+
+use std::path::{Path, PathBuf};
+
+use pessimistic_proof_script::{
+    aggregator::{Aggregator, AggregatorState, ProvenEpoch, Receipt},
+    exits::{ExitProof, MAINNET},
+    input::{
+        build_batch, dev_certify, dev_context, dev_signing_key, read_deposits, read_json,
+        NetworkState,
+    },
+    keys::KeyCache,
+    prover,
+    rpc::{
+        self, Corridors, Request, Response, Status, METHOD_NOT_FOUND, NOT_FOUND, PARSE_ERROR,
+        REJECTED,
+    },
+};
+use poly_pessimistic_proof::{
+    batch::Batch,
+    certificate::Certificate,
+    context::ProofContext,
+    keccak::Digest,
+    local_balance_tree::{Balance, BalanceTree, Deposit},
+    local_exit_tree::LocalExitTree,
+    supply::{SupplyCheck, TokenSupplies},
+    LeafType, TokenInfo, Withdrawal,
+};
+use reth_primitives::{Address, U256};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+const STATE_PATH: &str = "src/data/state.json";
+const DEPOSITS_PATH: &str = "src/data/withdrawals.json";
+
+fn demo_batch() -> Batch {
+    let state: NetworkState = read_json(Path::new(STATE_PATH)).unwrap();
+    let deposits = read_deposits(Path::new(DEPOSITS_PATH)).unwrap();
+
+    build_batch(state, deposits).unwrap()
+}
+
+/// The context registering the development keys of the networks of the tests.
+fn context() -> ProofContext {
+    dev_context((0..4).map(Into::into))
+}
+
+/// Certifies the first batch of a network.
+fn certify(batch: Batch) -> Certificate {
+    dev_certify(&context(), vec![batch]).remove(0)
+}
+
+/// First batch of the rollup receiving the first demo deposit, sending part of it to another
+/// rollup. Sending it back to mainnet would overflow its balance, which is set to the maximum.
+fn rollup_batch(demo_batch: &Batch) -> Batch {
+    let deposit = &demo_batch.withdrawals[0];
+    let withdrawal = Withdrawal::new(
+        LeafType::Asset,
+        deposit.token_info.origin_network,
+        deposit.token_info.origin_token_address,
+        (*deposit.dest_network + 1).into(),
+        deposit.dest_address,
+        U256::from(1),
+        Vec::new(),
+    );
+    let tree = LocalExitTree::new();
+    let root = tree.get_root();
+
+    Batch::new(
+        deposit.dest_network,
+        tree,
+        root,
+        BalanceTree::default(),
+        vec![withdrawal],
+    )
+}
+
+fn expected_exit_root() -> Digest {
+    hex::decode("bd03ab620225bd2dbe77791aced3c995e1d1a4ba3685a72117d4dc3253f57658")
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+/// Empty directory of a single test, holding the aggregator state, proofs and keys.
+fn test_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join("pessimistic-proof-aggregator")
+        .join(test);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+struct Server {
+    url: String,
+    task: JoinHandle<anyhow::Result<()>>,
+}
+
+impl Server {
+    /// Starts the aggregator whose state is saved in `dir`, resuming it if it exists.
+    async fn start(dir: &Path) -> Self {
+        Self::start_with(dir, context()).await
+    }
+
+    /// Same as [`Server::start`], with the networks of `context`.
+    async fn start_with(dir: &Path, context: ProofContext) -> Self {
+        let aggregator = Aggregator::open(
+            dir.join("aggregator.json"),
+            context,
+            dir.join("proofs"),
+            prover::client(true),
+            KeyCache::new(dir.join("keys")),
+        )
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let task = tokio::spawn(rpc::serve(listener, aggregator, None));
+
+        Self { url, task }
+    }
+
+    async fn send(&self, body: String) -> Response {
+        reqwest::Client::new()
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Response {
+        let request = Request::new(1, method, params);
+        self.send(serde_json::to_string(&request).unwrap()).await
+    }
+
+    async fn call_ok<T: DeserializeOwned>(&self, method: &str, params: Value) -> T {
+        let response = self.call(method, params).await;
+        assert_eq!(response.error, None);
+        // A `null` result is read back as `None`.
+        serde_json::from_value(response.result.unwrap_or_default()).unwrap()
+    }
+
+    async fn call_err(&self, method: &str, params: Value) -> (i64, String) {
+        let error = self.call(method, params).await.error.unwrap();
+        (error.code, error.message)
+    }
+
+    fn stop(self) {
+        self.task.abort();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn submit_and_prove_epoch() {
+    let dir = test_dir("submit_and_prove_epoch");
+    let server = Server::start(&dir).await;
+    let certificate = certify(demo_batch());
+    let network = certificate.header.network;
+
+    let status: Status = server.call_ok("status", json!([])).await;
+    assert_eq!(status.epoch, 0);
+    assert!(status.pending.is_empty());
+
+    let receipt: Receipt = server
+        .call_ok("submit_certificate", json!([certificate]))
+        .await;
+    assert_eq!(
+        receipt,
+        Receipt {
+            epoch: 0,
+            position: 0
+        }
+    );
+
+    let (code, message) = server
+        .call_err("submit_certificate", json!([certificate]))
+        .await;
+    assert_eq!(code, REJECTED);
+    assert!(message.contains("already has a certificate"), "{message}");
+
+    let proven: Option<ProvenEpoch> = server.call_ok("close_epoch", json!([])).await;
+    let proven = proven.unwrap();
+    assert_eq!(proven.epoch, 0);
+    assert_eq!(proven.networks, vec![network]);
+    assert_eq!(proven.exit_roots.get(&network), Some(&expected_exit_root()));
+
+    let artifact = prover::verify(
+        &prover::client(true),
+        &KeyCache::new(dir.join("keys")),
+        &proven.proof,
+    )
+    .unwrap();
+    let output = artifact.output();
+    assert_eq!(output.exit_roots.get(&network), Some(&expected_exit_root()));
+    assert_eq!(output.heights.get(&network), Some(&0));
+    assert_eq!(proven.heights, output.heights);
+
+    let status: Status = server.call_ok("status", json!([])).await;
+    assert_eq!(status.epoch, 1);
+    assert!(status.pending.is_empty());
+    assert_eq!(status.exit_roots.get(&network), Some(&expected_exit_root()));
+    assert_eq!(status.heights.get(&network), Some(&0));
+
+    // Nothing to prove in the new epoch.
+    let proven: Option<ProvenEpoch> = server.call_ok("close_epoch", json!([])).await;
+    assert!(proven.is_none());
+
+    server.stop();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resume_after_restart() {
+    let dir = test_dir("resume_after_restart");
+    let certificate = certify(demo_batch());
+    let network = certificate.header.network;
+
+    let server = Server::start(&dir).await;
+    let _: Receipt = server
+        .call_ok("submit_certificate", json!([certificate]))
+        .await;
+    server.stop();
+
+    // The queued certificate survives the restart.
+    let server = Server::start(&dir).await;
+    let status: Status = server.call_ok("status", json!([])).await;
+    assert_eq!(status.pending, vec![network]);
+    let proven: Option<ProvenEpoch> = server.call_ok("close_epoch", json!([])).await;
+    assert!(proven.is_some());
+    server.stop();
+
+    // So do the roots of the proven epoch, which the same certificate no longer starts from.
+    let server = Server::start(&dir).await;
+    let status: Status = server.call_ok("status", json!([])).await;
+    assert_eq!(status.epoch, 1);
+    assert_eq!(status.exit_roots.get(&network), Some(&expected_exit_root()));
+
+    let (code, message) = server
+        .call_err("submit_certificate", json!([certificate]))
+        .await;
+    assert_eq!(code, REJECTED);
+    assert!(message.contains("local exit root"), "{message}");
+    server.stop();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resume_supply_after_restart() {
+    let dir = test_dir("resume_supply_after_restart");
+    let eth = TokenInfo::ETH;
+    let totals = |deposited: u32, withdrawn: u32| {
+        let mut totals = TokenSupplies::new();
+        totals.add(
+            eth.clone(),
+            &Balance::new(U256::from(deposited), U256::from(withdrawn)),
+        );
+        totals
+    };
+    // First batch of `network`, holding `balance` ETH and sending `amount` of it to `dest_network`.
+    let transfer = |network: u32, balance: u32, dest_network: u32, amount: u32| {
+        let withdrawal = Withdrawal::new(
+            LeafType::Asset,
+            eth.origin_network,
+            eth.origin_token_address,
+            dest_network.into(),
+            Address::repeat_byte(0xaa),
+            U256::from(amount),
+            Vec::new(),
+        );
+        let tree = LocalExitTree::new();
+        let root = tree.get_root();
+        let balances = vec![(eth.clone(), Deposit(U256::from(balance)).into())];
+
+        Batch::new(
+            network.into(),
+            tree,
+            root,
+            balances.into(),
+            vec![withdrawal],
+        )
+    };
+
+    // 100 ETH on mainnet, of which 10 go to network 1.
+    let mut context = context();
+    let mainnet = BalanceTree::from(vec![(eth.clone(), Deposit(U256::from(100)).into())]);
+    context.supply = Some(SupplyCheck::bootstrap([&mainnet]).unwrap());
+    let server = Server::start_with(&dir, context.clone()).await;
+    let _: Receipt = server
+        .call_ok(
+            "submit_certificate",
+            json!([certify(transfer(0, 100, 1, 10))]),
+        )
+        .await;
+    let proven: Option<ProvenEpoch> = server.call_ok("close_epoch", json!([])).await;
+    assert!(proven.is_some());
+    server.stop();
+
+    // The context still holds the totals before the first epoch, while the next one starts from
+    // the totals after it.
+    let server = Server::start_with(&dir, context).await;
+    let _: Receipt = server
+        .call_ok(
+            "submit_certificate",
+            json!([certify(transfer(1, 10, 2, 4))]),
+        )
+        .await;
+    let proven: Option<ProvenEpoch> = server.call_ok("close_epoch", json!([])).await;
+    assert!(proven.is_some());
+    server.stop();
+
+    let state: AggregatorState = read_json(&dir.join("aggregator.json")).unwrap();
+    assert_eq!(state.context.supply.unwrap().prev_totals, totals(114, 14));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reject_invalid_calls() {
+    let dir = test_dir("reject_invalid_calls");
+    let server = Server::start(&dir).await;
+
+    let mut batch = demo_batch();
+    batch.prev_local_exit_root = [0; 32];
+    let (code, message) = server
+        .call_err("submit_certificate", json!([certify(batch)]))
+        .await;
+    assert_eq!(code, REJECTED);
+    assert!(message.contains("InvalidLocalExitRoot"), "{message}");
+
+    // Signed by the sequencer of another network.
+    let certificate = Certificate::new(demo_batch(), 0, &dev_signing_key(1.into()));
+    let (code, message) = server
+        .call_err("submit_certificate", json!([certificate]))
+        .await;
+    assert_eq!(code, REJECTED);
+    assert!(message.contains("not signed by its sequencer"), "{message}");
+
+    // Not following the last settled certificate of the network.
+    let certificate = Certificate::new(demo_batch(), 1, &dev_signing_key(0.into()));
+    let (code, message) = server
+        .call_err("submit_certificate", json!([certificate]))
+        .await;
+    assert_eq!(code, REJECTED);
+    assert!(message.contains("at height 1, expected 0"), "{message}");
+
+    let (code, _) = server.call_err("unknown", json!([])).await;
+    assert_eq!(code, METHOD_NOT_FOUND);
+
+    let response = server.send("not json".to_string()).await;
+    assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+
+    let status: Status = server.call_ok("status", json!([])).await;
+    assert!(status.pending.is_empty());
+
+    server.stop();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exit_proofs_of_proven_withdrawals() {
+    let dir = test_dir("exit_proofs_of_proven_withdrawals");
+    let server = Server::start(&dir).await;
+    let batch = demo_batch();
+    let first_deposit = batch.prev_local_exit_tree.leaf_count();
+    let last_deposit = first_deposit + batch.withdrawals.len() as u32 - 1;
+    let rollup_batch = rollup_batch(&batch);
+    let rollup = rollup_batch.origin_network;
+
+    let _: Receipt = server
+        .call_ok("submit_certificate", json!([certify(batch)]))
+        .await;
+    let _: Receipt = server
+        .call_ok("submit_certificate", json!([certify(rollup_batch.clone())]))
+        .await;
+
+    // Withdrawals are only indexed once proven.
+    let (code, _) = server
+        .call_err("get_exit_proof", json!([MAINNET, first_deposit]))
+        .await;
+    assert_eq!(code, NOT_FOUND);
+
+    let _: Option<ProvenEpoch> = server.call_ok("close_epoch", json!([])).await;
+
+    for deposit_count in [first_deposit, last_deposit] {
+        let proof: ExitProof = server
+            .call_ok("get_exit_proof", json!([MAINNET, deposit_count]))
+            .await;
+        assert!(proof.verify());
+        assert_eq!(proof.epoch, 0);
+        assert_eq!(proof.local_exit_root, expected_exit_root());
+        assert!(proof.rollup_exit_root.is_none());
+    }
+
+    let proof: ExitProof = server.call_ok("get_exit_proof", json!([rollup, 0])).await;
+    assert!(proof.verify());
+    assert_eq!(proof.leaf, rollup_batch.withdrawals[0].hash());
+    assert!(proof.rollup_exit_root.is_some());
+    assert_eq!(proof.rollup_index, Some(*rollup - 1));
+
+    // The withdrawals preceding the first certificate are unknown.
+    for (network, deposit_count) in [(MAINNET, first_deposit - 1), (MAINNET, last_deposit + 1)] {
+        let (code, _) = server
+            .call_err("get_exit_proof", json!([network, deposit_count]))
+            .await;
+        assert_eq!(code, NOT_FOUND);
+    }
+
+    server.stop();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconcile_claimed_exits() {
+    let dir = test_dir("reconcile_claimed_exits");
+    let server = Server::start(&dir).await;
+    let batch = demo_batch();
+    let rollup_batch = rollup_batch(&batch);
+    let withdrawal = &rollup_batch.withdrawals[0];
+
+    let _: Receipt = server
+        .call_ok("submit_certificate", json!([certify(batch)]))
+        .await;
+    let _: Receipt = server
+        .call_ok("submit_certificate", json!([certify(rollup_batch.clone())]))
+        .await;
+    let proven: Option<ProvenEpoch> = server.call_ok("close_epoch", json!([])).await;
+    let proven = proven.unwrap();
+
+    let corridors: Corridors = server.call_ok("get_corridors", json!([])).await;
+    assert_eq!(corridors.root, proven.ledger_root);
+    let (_, balance) = corridors
+        .corridors
+        .iter()
+        .find(|(corridor, _)| corridor.origin_network == rollup_batch.origin_network)
+        .unwrap();
+    assert_eq!((balance.pending, balance.pending_exits), (U256::from(1), 1));
+
+    // First withdrawal of the rollup, claimed on its destination network.
+    let rollup_index = *rollup_batch.origin_network - 1;
+    let claim = json!({
+        "index": (u64::from(rollup_index) << 32).to_string(),
+        "originNetwork": *withdrawal.token_info.origin_network,
+        "originAddress": withdrawal.token_info.origin_token_address,
+        "destinationAddress": withdrawal.dest_address,
+        "amount": withdrawal.amount.to_string(),
+    });
+    let (code, _) = server
+        .call_err("import_claims", json!([MAINNET, [claim]]))
+        .await;
+    assert_eq!(code, REJECTED);
+    let _: Option<()> = server
+        .call_ok("import_claims", json!([withdrawal.dest_network, [claim]]))
+        .await;
+
+    let corridors: Corridors = server.call_ok("get_corridors", json!([])).await;
+    assert_ne!(corridors.root, proven.ledger_root);
+    let (_, balance) = corridors
+        .corridors
+        .iter()
+        .find(|(corridor, _)| corridor.origin_network == rollup_batch.origin_network)
+        .unwrap();
+    assert_eq!(
+        (balance.pending, balance.settled),
+        (U256::ZERO, U256::from(1))
+    );
+
+    server.stop();
+}
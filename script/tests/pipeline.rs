@@ -0,0 +1,369 @@
+//! Runs the whole host-to-guest pipeline on the demo data, with the mock prover so that it fits on
+//! a laptop CPU.
+
+use std::path::{Path, PathBuf};
+
+use pessimistic_proof_script::{
+    artifact::{stdin_digest, ProofArtifact},
+    bench::{self, BenchResult},
+    input::{
+        build_batch, dev_certify, dev_context, dev_signing_key, read_deposits, read_json,
+        NetworkState,
+    },
+    keys::KeyCache,
+    prover,
+};
+use poly_pessimistic_proof::{
+    batch::Batch,
+    certificate::Certificate,
+    compact::InputFormat,
+    context::ProofContext,
+    cost::{CostFeatures, CostModel},
+    cycles::{COMPONENTS, EXIT_TREE, LEAF_HASHING},
+    estimate_cost,
+    keccak::Digest,
+};
+use sp1_sdk::SP1Stdin;
+
+const STATE_PATH: &str = "src/data/state.json";
+const DEPOSITS_PATH: &str = "src/data/withdrawals.json";
+
+fn demo_batch() -> Batch {
+    let state: NetworkState = read_json(Path::new(STATE_PATH)).unwrap();
+    let deposits = read_deposits(Path::new(DEPOSITS_PATH)).unwrap();
+
+    build_batch(state, deposits).unwrap()
+}
+
+/// The context registering the network of the demo batch and the destinations of its withdrawals.
+fn demo_context(batch: &Batch) -> ProofContext {
+    dev_context(
+        batch
+            .withdrawals
+            .iter()
+            .map(|withdrawal| withdrawal.dest_network)
+            .chain([batch.origin_network]),
+    )
+}
+
+/// The demo batch, certified with the development key of its network.
+fn demo_certificates() -> (ProofContext, Vec<Certificate>) {
+    let batch = demo_batch();
+    let context = demo_context(&batch);
+    let certificates = dev_certify(&context, vec![batch]);
+
+    (context, certificates)
+}
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join("pessimistic-proof-pipeline");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Key cache of a single test, so that tests running in parallel do not write the same files.
+fn key_cache(test: &str) -> KeyCache {
+    KeyCache::new(temp_dir().join(test).join("keys"))
+}
+
+fn expected_exit_root() -> Digest {
+    hex::decode("bd03ab620225bd2dbe77791aced3c995e1d1a4ba3685a72117d4dc3253f57658")
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+#[test]
+fn execute_demo_batch() {
+    let (context, certificates) = demo_certificates();
+    let network = certificates[0].header.network;
+
+    let execution = prover::execute(&prover::client(true), &context, &certificates).unwrap();
+
+    let output = &execution.output;
+    assert_eq!(output.exit_roots.get(&network), Some(&expected_exit_root()));
+    assert!(output.balance_roots.contains_key(&network));
+    assert_eq!(output.heights.get(&network), Some(&0));
+    assert_eq!(output.context_hash, context.hash());
+    assert!(execution.cycles > 0);
+    assert!(!execution.syscall_counts.is_empty());
+}
+
+#[test]
+fn mock_prove_and_verify_demo_batch() {
+    let (context, certificates) = demo_certificates();
+    let network = certificates[0].header.network;
+    let client = prover::client(true);
+    let keys = key_cache("mock_prove_and_verify_demo_batch");
+    let path = temp_dir().join("proof.bin");
+
+    let proved = prover::prove(&client, &keys, &context, &certificates, &path).unwrap();
+    let verified = prover::verify(&client, &keys, &path).unwrap();
+
+    assert_eq!(verified.vkey_hash, proved.vkey_hash);
+    assert_eq!(verified.stdin_digest, proved.stdin_digest);
+    assert_eq!(verified.crate_version, poly_pessimistic_proof::VERSION);
+    let output = verified.output();
+    assert_eq!(output.exit_roots.get(&network), Some(&expected_exit_root()));
+}
+
+#[test]
+fn refuse_unsigned_certificate() {
+    let (context, mut certificates) = demo_certificates();
+    let other_network = (*certificates[0].header.network + 1).into();
+    certificates[0] = Certificate::new(
+        certificates[0].batch.clone(),
+        0,
+        &dev_signing_key(other_network),
+    );
+
+    assert!(prover::execute(&prover::client(true), &context, &certificates).is_err());
+}
+
+#[test]
+fn refuse_unknown_destination() {
+    let batch = demo_batch();
+    let context = dev_context([batch.origin_network]);
+    let certificates = dev_certify(&context, vec![batch]);
+
+    assert!(prover::execute(&prover::client(true), &context, &certificates).is_err());
+}
+
+#[test]
+fn reject_tampered_artifact() {
+    let client = prover::client(true);
+    let keys = key_cache("reject_tampered_artifact");
+    let path = temp_dir().join("tampered.bin");
+
+    let (context, certificates) = demo_certificates();
+    let mut artifact = prover::prove(&client, &keys, &context, &certificates, &path).unwrap();
+    let digest = artifact.stdin_digest;
+    artifact.vkey_hash = format!("0x{}", "00".repeat(32));
+    artifact.save(&path).unwrap();
+    assert!(prover::verify(&client, &keys, &path).is_err());
+
+    let (context, certificates) = demo_certificates();
+    let mut artifact = prover::prove(&client, &keys, &context, &certificates, &path).unwrap();
+    artifact.stdin_digest = [0; 32];
+    artifact.save(&path).unwrap();
+    assert!(prover::verify(&client, &keys, &path).is_err());
+    artifact.stdin_digest = digest;
+    artifact.save(&path).unwrap();
+    prover::verify(&client, &keys, &path).unwrap();
+
+    let mut artifact = ProofArtifact::load(&path).unwrap();
+    artifact.format_version += 1;
+    artifact.save(&path).unwrap();
+    assert!(ProofArtifact::load(&path).is_err());
+}
+
+#[test]
+fn stdin_digest_covers_buffer_boundaries() {
+    let mut joined = SP1Stdin::new();
+    joined.write_slice(b"ab");
+    let mut split = SP1Stdin::new();
+    split.write_slice(b"a");
+    split.write_slice(b"b");
+
+    assert_ne!(stdin_digest(&joined), stdin_digest(&split));
+}
+
+#[test]
+fn replace_stale_keys() {
+    let client = prover::client(true);
+    let keys = key_cache("replace_stale_keys");
+    let path = keys.path(prover::PROGRAM);
+    let _ = std::fs::remove_file(&path);
+
+    keys.setup(&client, prover::PROGRAM, prover::ELF).unwrap();
+    let cached = std::fs::read(&path).unwrap();
+    keys.setup(&client, prover::PROGRAM, prover::ELF).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), cached);
+
+    // A rebuilt program, whose keys replace those of the previous ELF.
+    let mut rebuilt = prover::ELF.to_vec();
+    rebuilt.extend_from_slice(&[0; 4]);
+    keys.setup(&client, prover::PROGRAM, &rebuilt).unwrap();
+    assert_ne!(std::fs::read(&path).unwrap(), cached);
+    assert_eq!(
+        std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+        1
+    );
+
+    // Unreadable keys are refused rather than overwritten.
+    std::fs::write(&path, &cached[..cached.len() / 2]).unwrap();
+    assert!(keys.setup(&client, prover::PROGRAM, prover::ELF).is_err());
+}
+
+#[test]
+fn compact_input_saves_cycles() {
+    let (context, certificates) = demo_certificates();
+    let client = prover::client(true);
+
+    let serde =
+        prover::execute_with_format(&client, &context, &certificates, InputFormat::Serde).unwrap();
+    let compact =
+        prover::execute_with_format(&client, &context, &certificates, InputFormat::Compact)
+            .unwrap();
+    println!(
+        "cycles: serde input {}, compact input {}",
+        serde.cycles, compact.cycles
+    );
+
+    assert_eq!(compact.public_values, serde.public_values);
+    assert!(compact.cycles < serde.cycles);
+}
+
+#[test]
+fn bench_reports_components() {
+    let client = prover::client(true);
+    let params = bench::suite(true)[0];
+
+    let result = bench::run(&client, &params).unwrap();
+
+    assert_eq!(result.params, params);
+    for component in COMPONENTS {
+        assert!(
+            result.components[component] > 0,
+            "no cycles for {component}"
+        );
+    }
+    // The leaf hashing is part of the exit tree update
+    assert!(result.components[LEAF_HASHING] < result.components[EXIT_TREE]);
+    let tracked: u64 = result
+        .components
+        .iter()
+        .filter(|(component, _)| component.as_str() != LEAF_HASHING)
+        .map(|(_, cycles)| cycles)
+        .sum();
+    assert_eq!(tracked + result.other, result.cycles);
+}
+
+#[test]
+fn bench_withdrawals_leave_their_network() {
+    for params in bench::suite(false) {
+        let batches = bench::generate_batches(&params);
+
+        assert!(batches.len() >= 2, "{params:?}");
+        for batch in &batches {
+            assert!(
+                batch
+                    .withdrawals
+                    .iter()
+                    .all(|withdrawal| withdrawal.dest_network != batch.origin_network),
+                "{params:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn fit_overdetermined_suite() {
+    let model = CostModel::from_coefficients([
+        30_000.0,
+        150_000.0,
+        2_000_000.0,
+        4_000.0,
+        2.0,
+        1_000.0,
+        3_000.0,
+    ]);
+    let results: Vec<BenchResult> = bench::suite(true)
+        .into_iter()
+        .enumerate()
+        .map(|(i, params)| {
+            let (_, certificates) = bench::generate_certificates(&params);
+            let features = CostFeatures::of_certificates(&certificates);
+            let exact = model.estimate(&features);
+            // Measurement noise of ±0.5%
+            let noise = exact / 200;
+            let cycles = if i % 2 == 0 {
+                exact + noise
+            } else {
+                exact - noise
+            };
+
+            BenchResult {
+                params,
+                features,
+                estimate: exact,
+                cycles,
+                components: Default::default(),
+                other: 0,
+            }
+        })
+        .collect();
+    assert!(results.len() > model.coefficients().len());
+
+    let fitted = bench::fit(&results).expect("the quick suite determines the model");
+    for result in &results {
+        let error = fitted.estimate(&result.features).abs_diff(result.estimate);
+        assert!(error * 100 <= result.estimate, "{result:?}");
+    }
+
+    // A feature which is the same in every result does not determine its coefficient.
+    assert!(bench::fit(&results[..1]).is_none());
+}
+
+#[test]
+fn calibrated_cost_model_matches_bench() {
+    let client = prover::client(true);
+    let report = bench::run_suite(&client, &bench::suite(true)).unwrap();
+
+    // The suite overdetermines the model, and the fitted model reproduces it, so the cost is
+    // indeed linear in the features.
+    assert!(report.results.len() > CostModel::CALIBRATED.coefficients().len());
+    let fitted = report.fitted.expect("the quick suite determines the model");
+    for result in &report.results {
+        let error = fitted.estimate(&result.features).abs_diff(result.cycles);
+        assert!(error * 100 <= result.cycles, "{result:?}");
+    }
+
+    // The calibrated model is still accurate, otherwise refit it from `bench`.
+    for result in &report.results {
+        let error = result.estimate.abs_diff(result.cycles);
+        assert!(
+            error * 5 <= result.cycles,
+            "estimated {} cycles for {:?}, got {}: refit `CostModel::CALIBRATED` with the fitted \
+             model {fitted:?}",
+            result.estimate,
+            result.params,
+            result.cycles
+        );
+    }
+}
+
+#[test]
+fn prove_split_demo_batch() {
+    let batch = demo_batch();
+    let network = batch.origin_network;
+    let client = prover::client(true);
+    let keys = key_cache("prove_split_demo_batch");
+    // Each sub-batch pays for its certificate, and for about half of the withdrawals.
+    let certificate_cycles = estimate_cost(&[Batch {
+        withdrawals: Vec::new(),
+        ..batch.clone()
+    }]);
+    let max_cycles =
+        certificate_cycles + (estimate_cost(std::slice::from_ref(&batch)) - certificate_cycles) / 2;
+    let context = demo_context(&batch);
+
+    let artifacts = prover::prove_split(
+        &client,
+        &keys,
+        &context,
+        &dev_signing_key(network),
+        &batch,
+        max_cycles,
+        &temp_dir().join("split"),
+    )
+    .unwrap();
+
+    assert!(artifacts.len() >= 2);
+    let output = artifacts.last().unwrap().output();
+    assert_eq!(output.exit_roots.get(&network), Some(&expected_exit_root()));
+    assert_eq!(
+        output.heights.get(&network),
+        Some(&(artifacts.len() as u64 - 1))
+    );
+}